@@ -55,46 +55,24 @@ fn main() -> ! {
     // Frequency (in Hz, u32) of the clock the chip is connected to (Pin 22 on the YM2149)
     let master_clock_freq: u32 = 2_000_000;
 
-    // DynPins for the 8-bit data bus (LSB, pin D0 to MSB, pin D7)
-    let data_pins = [
-        pins.gpio1.into_push_pull_output().into_dyn_pin(),
-        pins.gpio2.into_push_pull_output().into_dyn_pin(),
-        pins.gpio3.into_push_pull_output().into_dyn_pin(),
-        pins.gpio4.into_push_pull_output().into_dyn_pin(),
-        pins.gpio5.into_push_pull_output().into_dyn_pin(),
-        pins.gpio6.into_push_pull_output().into_dyn_pin(),
-        pins.gpio7.into_push_pull_output().into_dyn_pin(),
-        pins.gpio8.into_push_pull_output().into_dyn_pin(),
-    ];
-
-    // Initialize a DataBus
-    let mut data_bus = DataBus::new(data_pins);
-    data_bus.write_u8(0); // Write 0b0000_0000 as a safety measure
-
-    // Bus control decoder pins
-    let bc1 = pins.gpio9.into_push_pull_output();
-    let bdir = pins.gpio10.into_push_pull_output();
-
-    // Build the chip by passing:
-    let mut chip = YM2149::new(
-        data_bus,          // - A variable w/ type that implements the `OutputBus` trait
-        master_clock_freq, // - The frequency of the master clock
-        bc1,               // - The GPIO pin connected to BC1
-        bdir,              // - The GPIO pin connected to BDIR
+    // Build the chip from its pins:
+    let mut chip = ym2149_pins!(
+        pins,
+        data: [gpio1, gpio2, gpio3, gpio4, gpio5, gpio6, gpio7, gpio8], // - The 8-bit data bus (LSB, pin D0 to MSB, pin D7)
+        bc1: gpio9,                                                      // - The GPIO pin connected to BC1
+        bdir: gpio10,                                                    // - The GPIO pin connected to BDIR
+        master_clock: master_clock_freq,                                 // - The frequency of the master clock
     );
 
     // Set the chip's mode to `Inactive`
-    chip.set_mode(Mode::INACTIVE);
+    chip.set_mode(Mode::INACTIVE).unwrap();
     // Configure the mixer according to the datasheet (the docs for IoPortMixerSettings also explain this process)
-    chip.write_register(Register::IoPortMixerSettings, 0b00111110);
+    chip.write_register(Register::IoPortMixerSettings, 0b00111110)
+        .unwrap();
 
     // Reset the chip (optional but recommended)
     let mut reset_pin = pins.gpio11.into_push_pull_output();
-
-    reset_pin.set_low();
-    timer.delay_ms(10);
-    reset_pin.set_high();
-    timer.delay_ms(10);
+    chip.reset(&mut reset_pin, &mut timer).unwrap();
 
     // Do-re-mi code
     const C_MAJOR: [u32; 8] = [262, 294, 330, 349, 392, 440, 494, 523];
@@ -102,7 +80,7 @@ fn main() -> ! {
     let mut direction: isize = 0;
 
     // Set channel A's volume to 0x0F (there are only 4 bits dedicated to channel levels)
-    chip.volume(AudioChannel::A, 0xF);
+    chip.volume(AudioChannel::A, Level::MAX);
 
     loop {
         // Play a tone on channel A and keep it audible for 250ms
@@ -110,11 +88,11 @@ fn main() -> ! {
         timer.delay_ms(250);
 
         // Silence the channel for 250ms before playing the next note
-        chip.volume(AudioChannel::A, 0x0);
+        chip.volume(AudioChannel::A, Level::OFF);
         timer.delay_ms(250);
 
         // Bring the volume back up
-        chip.volume(AudioChannel::A, 0xF);
+        chip.volume(AudioChannel::A, Level::MAX);
 
         // Access the table in a ping-pong fashion, playing the first and last notes twice
         direction += (i == 0) as isize - (i == 7) as isize;
@@ -55,53 +55,31 @@ fn main() -> ! {
     // Frequency (in Hz, u32) of the clock the chip is connected to (Pin 22 on the YM2149)
     let master_clock_freq: u32 = 2_000_000;
 
-    // DynPins for the 8-bit data bus (LSB, pin D0 to MSB, pin D7)
-    let data_pins = [
-        pins.gpio1.into_push_pull_output().into_dyn_pin(),
-        pins.gpio2.into_push_pull_output().into_dyn_pin(),
-        pins.gpio3.into_push_pull_output().into_dyn_pin(),
-        pins.gpio4.into_push_pull_output().into_dyn_pin(),
-        pins.gpio5.into_push_pull_output().into_dyn_pin(),
-        pins.gpio6.into_push_pull_output().into_dyn_pin(),
-        pins.gpio7.into_push_pull_output().into_dyn_pin(),
-        pins.gpio8.into_push_pull_output().into_dyn_pin(),
-    ];
-
-    // Initialize a DataBus
-    let mut data_bus = DataBus::new(data_pins);
-    data_bus.write_u8(0); // Write 0b0000_0000 as a safety measure
-
-    // Bus control decoder pins
-    let bc1 = pins.gpio9.into_push_pull_output();
-    let bdir = pins.gpio10.into_push_pull_output();
-
-    // Build the chip by passing:
-    let mut chip = YM2149::new(
-        data_bus,          // - A variable w/ type that implements the `OutputBus` trait
-        master_clock_freq, // - The frequency of the master clock
-        bc1,               // - The GPIO pin connected to BC1
-        bdir,              // - The GPIO pin connected to BDIR
+    // Build the chip from its pins:
+    let mut chip = ym2149_pins!(
+        pins,
+        data: [gpio1, gpio2, gpio3, gpio4, gpio5, gpio6, gpio7, gpio8], // - The 8-bit data bus (LSB, pin D0 to MSB, pin D7)
+        bc1: gpio9,                                                      // - The GPIO pin connected to BC1
+        bdir: gpio10,                                                    // - The GPIO pin connected to BDIR
+        master_clock: master_clock_freq,                                 // - The frequency of the master clock
     );
 
     // Set the chip's mode to `Inactive`
-    chip.set_mode(Mode::INACTIVE);
+    chip.set_mode(Mode::INACTIVE).unwrap();
     // Configure the mixer according to the datasheet (the docs for IoPortMixerSettings also explain this process)
-    chip.write_register(Register::IoPortMixerSettings, 0b11110111);
+    chip.write_register(Register::IoPortMixerSettings, 0b11110111)
+        .unwrap();
 
     // Reset the chip (optional but recommended)
     let mut reset_pin = pins.gpio11.into_push_pull_output();
-
-    reset_pin.set_low();
-    timer.delay_ms(10);
-    reset_pin.set_high();
-    timer.delay_ms(10);
+    chip.reset(&mut reset_pin, &mut timer).unwrap();
 
     // Noise sweep code
     let mut c: u8 = 0x001;
 
     loop {
         // Set channel A's volume to 0x0F (there are only 4 bits dedicated to channel levels)
-        chip.volume(AudioChannel::A, 0xF);
+        chip.volume(AudioChannel::A, Level::MAX);
         // Set noise frequency to 'c'
         if c < 0b00011111 {
             chip.set_noise_freq(c);
@@ -0,0 +1,201 @@
+//! Register-level access to a PSG, so the same frame-driven code can drive the chip wired to
+//! this MCU, an emulator, or a chip somewhere else.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::frame::{Frame, FRAME_REGISTERS, MIXER};
+use crate::{Error, OutputBus, YM2149};
+
+/// Anything holding the 16 registers of an AY-3-8910 / YM2149.
+///
+/// [YM2149] implements it for real hardware, and `[u8; 16]` for a bare register file,
+/// e.g. to check what a player would write in tests. Code written against the trait rather
+/// than the driver works on any of them.
+///
+/// Example:
+/// ```no_run
+/// fn play_frames<P: PsgBackend>(psg: &mut P, frames: &[Frame]) -> Result<(), Error> {
+///     psg.reset()?;
+///     for frame in frames {
+///         psg.apply_frame(frame)?;
+///         wait_for_next_frame();
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait PsgBackend {
+    /// Write a register, 0 - 15.
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Error>;
+
+    /// The value of a register, as far as the backend knows.
+    fn read_register(&self, register: u8) -> u8;
+
+    /// Bring the sound registers (R0 - R13) back to 0, their state at power-up, silencing
+    /// the chip.
+    fn reset(&mut self) -> Result<(), Error> {
+        for register in 0..FRAME_REGISTERS as u8 {
+            let value = match register {
+                // Keep the I/O directions
+                7 => self.read_register(register) & 0xC0,
+                _ => 0,
+            };
+            self.write_register(register, value)?;
+        }
+        Ok(())
+    }
+
+    /// Bring the sound registers to the state of a [Frame], writing only the ones that
+    /// changed (see [Frame::diff]). The I/O direction bits of R7 are kept as they are.
+    fn apply_frame(&mut self, frame: &Frame) -> Result<(), Error> {
+        let mut current = Frame::default();
+        for (register, value) in current.registers.iter_mut().enumerate() {
+            *value = self.read_register(register as u8);
+        }
+        let mut next = *frame;
+        next.registers[MIXER] = (next.registers[MIXER] & 0x3F) | (current.registers[MIXER] & 0xC0);
+
+        for (register, value) in Frame::diff(&current, &next) {
+            self.write_register(register as u8, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Goes through [YM2149::write_register], so [safe mode](YM2149::with_safe_mode) applies.
+/// [reset](PsgBackend::reset) writes the registers rather than pulsing the RESET pin, and
+/// frames are applied as by [YM2149::apply_frame], through the
+/// [channel map](YM2149::with_channel_map). A bus failure in a frame is returned once
+/// the rest of it is written.
+impl<DATABUS, BC1, BDIR> PsgBackend for YM2149<DATABUS, BC1, BDIR>
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Error> {
+        YM2149::write_register(self, register, value)
+    }
+
+    fn read_register(&self, register: u8) -> u8 {
+        self.register(register)
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.write_all(&[0; FRAME_REGISTERS])
+    }
+
+    fn apply_frame(&mut self, frame: &Frame) -> Result<(), Error> {
+        self.write_frame(frame)
+    }
+}
+
+/// A bare register file: writes are kept, nothing is played.
+impl PsgBackend for [u8; 16] {
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Error> {
+        if let Some(slot) = self.get_mut(register.min(15) as usize) {
+            *slot = value;
+        }
+        Ok(())
+    }
+
+    fn read_register(&self, register: u8) -> u8 {
+        self.get(register.min(15) as usize).copied().unwrap_or(0)
+    }
+}
+
+/// Sends every write to two backends, e.g. the chip and an emulator, and tells where
+/// they went apart, to debug timing-dependent effects frame by frame.
+///
+/// Reads come from the first backend. A write is sent to the second one even when the
+/// first one fails, and the first error is returned.
+///
+/// Example:
+/// ```no_run
+/// let mut psg = MirrorBackend::new(chip, [0u8; 16]);
+/// for frame in frames {
+///     psg.apply_frame(frame)?;
+///     if psg.diverging_registers() != 0 {
+///         defmt::warn!("Tone periods (chip, copy): {}", psg.tone_periods());
+///     }
+/// }
+/// ```
+pub struct MirrorBackend<A, B> {
+    first: A,
+    second: B,
+    /// Writes that failed on one backend only
+    divergences: u32,
+}
+
+impl<A, B> MirrorBackend<A, B>
+where
+    A: PsgBackend,
+    B: PsgBackend,
+{
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+            divergences: 0,
+        }
+    }
+
+    /// Get back both backends.
+    pub fn release(self) -> (A, B) {
+        (self.first, self.second)
+    }
+
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+
+    /// Number of writes that went through on one backend and failed on the other.
+    pub fn divergences(&self) -> u32 {
+        self.divergences
+    }
+
+    /// Registers holding different values on the two backends, one bit each (R0 in B0).
+    pub fn diverging_registers(&self) -> u16 {
+        (0..16u8)
+            .filter(|&r| self.first.read_register(r) != self.second.read_register(r))
+            .fold(0, |mask, r| mask | 1 << r)
+    }
+
+    /// Tone period of each output (A, B, C) on the first and on the second backend.
+    pub fn tone_periods(&self) -> [(u16, u16); 3] {
+        core::array::from_fn(|output| {
+            (
+                tone_period(&self.first, output as u8),
+                tone_period(&self.second, output as u8),
+            )
+        })
+    }
+}
+
+fn tone_period<P: PsgBackend>(psg: &P, output: u8) -> u16 {
+    let fine = psg.read_register(output * 2);
+    let rough = psg.read_register(output * 2 + 1) & 0x0F;
+    u16::from_le_bytes([fine, rough])
+}
+
+impl<A, B> PsgBackend for MirrorBackend<A, B>
+where
+    A: PsgBackend,
+    B: PsgBackend,
+{
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Error> {
+        let first = self.first.write_register(register, value);
+        let second = self.second.write_register(register, value);
+        if first.is_ok() != second.is_ok() {
+            self.divergences = self.divergences.wrapping_add(1);
+        }
+        first.and(second)
+    }
+
+    fn read_register(&self, register: u8) -> u8 {
+        self.first.read_register(register)
+    }
+}
@@ -0,0 +1,324 @@
+//! [OutputBus] adapters for less direct wirings.
+
+use embedded_hal::digital::{OutputPin, PinState};
+use embedded_hal::i2c::I2c;
+use embedded_hal::spi::SpiDevice;
+
+use crate::{BusDirection, BusError, OutputBus, Polarity};
+
+/// Wraps a bus going through a bidirectional transceiver (e.g. 74LVC245), and drives
+/// its DIR pin from the chip's [mode](crate::Mode).
+///
+/// On 3.3 V MCUs this is what makes [Mode::READ](crate::Mode::READ) safe: the transceiver
+/// shields the MCU from the chip's 5 V outputs. DIR is high when data flows towards the
+/// chip (A → B on a '245, with the MCU on the A side), use
+/// [with_polarity](TransceiverBus::with_polarity) if your board has it the other way around.
+///
+/// Direction changes are ordered so the chip and the MCU never drive the bus at the same
+/// time, provided the wrapped bus also releases its pins in
+/// [set_direction](OutputBus::set_direction).
+///
+/// Example:
+/// ```no_run
+/// let dir = pins.gpio12.into_push_pull_output();
+/// let data_bus = TransceiverBus::new(DataBus::new(data_pins), dir);
+///
+/// let mut chip = YM2149::new(data_bus, master_clock_freq, bc1, bdir);
+/// ```
+pub struct TransceiverBus<BUS, DIR> {
+    bus: BUS,
+    dir: DIR,
+    polarity: Polarity,
+    direction: Option<BusDirection>,
+}
+
+impl<BUS, DIR> TransceiverBus<BUS, DIR>
+where
+    BUS: OutputBus,
+    DIR: OutputPin,
+{
+    pub fn new(bus: BUS, dir: DIR) -> Self {
+        let mut transceiver = Self {
+            bus,
+            dir,
+            polarity: Polarity::Normal,
+            direction: None,
+        };
+        transceiver.set_direction(BusDirection::Write);
+        transceiver
+    }
+
+    /// Set the polarity of the DIR pin.
+    pub fn with_polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        // Force the pin to be driven again with the new polarity
+        self.direction = None;
+        self.set_direction(BusDirection::Write);
+        self
+    }
+
+    /// Get back the wrapped bus and DIR pin.
+    pub fn release(self) -> (BUS, DIR) {
+        (self.bus, self.dir)
+    }
+
+    /// Drive the DIR pin, `false` if it couldn't be set.
+    fn drive_dir(&mut self, direction: BusDirection) -> bool {
+        let state = match direction {
+            BusDirection::Write => PinState::High,
+            BusDirection::Read => PinState::Low,
+        };
+        let driven = self.dir.set_state(self.polarity.apply(state)).is_ok();
+        if !driven {
+            defmt::warn!("ym2149: couldn't set the transceiver's DIR pin");
+        }
+        driven
+    }
+}
+
+impl<BUS, DIR> OutputBus for TransceiverBus<BUS, DIR>
+where
+    BUS: OutputBus,
+    DIR: OutputPin,
+{
+    fn write_u8(&mut self, data: u8) {
+        self.bus.write_u8(data);
+    }
+
+    fn try_write_u8(&mut self, data: u8) -> Result<(), BusError> {
+        self.bus.try_write_u8(data)
+    }
+
+    fn cache_address(&self) -> bool {
+        self.bus.cache_address()
+    }
+
+    fn set_direction(&mut self, direction: BusDirection) {
+        if self.direction == Some(direction) {
+            return;
+        }
+
+        let driven = match direction {
+            // Let go of the MCU side before the transceiver starts driving it
+            BusDirection::Read => {
+                self.bus.set_direction(direction);
+                self.drive_dir(direction)
+            }
+            // And the other way around
+            BusDirection::Write => {
+                let driven = self.drive_dir(direction);
+                self.bus.set_direction(direction);
+                driven
+            }
+        };
+        // Try again on the next change if DIR is stuck
+        self.direction = driven.then_some(direction);
+    }
+}
+
+/// Data bus behind a 74HC595 shift register, loaded over SPI.
+///
+/// Wire the SPI clock to SRCLK, MOSI to SER and chip select to RCLK, so outputs are latched
+/// at the end of each transfer, and QA~QH to D0~D7. The SPI bus must be MSB first, mode 0.
+///
+/// Example:
+/// ```no_run
+/// let spi = ExclusiveDevice::new(spi_bus, latch_pin, timer)?;
+/// let mut chip = YM2149::new(ShiftRegisterBus::new(spi), master_clock_freq, bc1, bdir);
+/// ```
+pub struct ShiftRegisterBus<SPI> {
+    spi: SPI,
+    cache_address: bool,
+}
+
+impl<SPI> ShiftRegisterBus<SPI>
+where
+    SPI: SpiDevice,
+{
+    pub fn new(spi: SPI) -> Self {
+        Self {
+            spi,
+            cache_address: false,
+        }
+    }
+
+    /// Enable or disable [address caching](OutputBus::cache_address) (off by default).
+    pub fn with_address_caching(mut self, enabled: bool) -> Self {
+        self.cache_address = enabled;
+        self
+    }
+
+    /// Get back the SPI device.
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI> OutputBus for ShiftRegisterBus<SPI>
+where
+    SPI: SpiDevice,
+{
+    fn write_u8(&mut self, data: u8) {
+        let _ = self.try_write_u8(data);
+    }
+
+    fn try_write_u8(&mut self, data: u8) -> Result<(), BusError> {
+        self.spi.write(&[data]).map_err(|_| BusError)
+    }
+
+    fn cache_address(&self) -> bool {
+        self.cache_address
+    }
+}
+
+/// Supported I²C GPIO expanders for [I2cExpanderBus].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expander {
+    /// NXP/TI PCF8574 (or PCF8574A), P0~P7 wired to D0~D7
+    Pcf8574,
+    /// Microchip MCP23008, GP0~GP7 wired to D0~D7
+    Mcp23008,
+}
+
+/// MCP23008 I/O direction register
+const MCP23008_IODIR: u8 = 0x00;
+/// MCP23008 output latch register
+const MCP23008_OLAT: u8 = 0x0A;
+
+/// Data bus behind an I²C GPIO expander.
+///
+/// Each byte is a full I²C transaction, so expect around half a millisecond per register
+/// write at 100 kHz. Great for boards short on GPIO, not so much for sample playback.
+///
+/// Example:
+/// ```no_run
+/// let data_bus = I2cExpanderBus::new(i2c, Expander::Pcf8574, 0x20)?;
+/// let mut chip = YM2149::new(data_bus, master_clock_freq, bc1, bdir);
+/// ```
+pub struct I2cExpanderBus<I2C> {
+    i2c: I2C,
+    expander: Expander,
+    address: u8,
+    cache_address: bool,
+}
+
+impl<I2C> I2cExpanderBus<I2C>
+where
+    I2C: I2c,
+{
+    /// Set up the expander at `address`, making all its pins outputs.
+    pub fn new(mut i2c: I2C, expander: Expander, address: u8) -> Result<Self, I2C::Error> {
+        if expander == Expander::Mcp23008 {
+            i2c.write(address, &[MCP23008_IODIR, 0x00])?;
+        }
+
+        Ok(Self {
+            i2c,
+            expander,
+            address,
+            cache_address: false,
+        })
+    }
+
+    /// Enable or disable [address caching](OutputBus::cache_address) (off by default).
+    ///
+    /// At about half a millisecond per byte, this is where it pays off the most.
+    pub fn with_address_caching(mut self, enabled: bool) -> Self {
+        self.cache_address = enabled;
+        self
+    }
+
+    /// Get back the I²C bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C> OutputBus for I2cExpanderBus<I2C>
+where
+    I2C: I2c,
+{
+    fn write_u8(&mut self, data: u8) {
+        let _ = self.try_write_u8(data);
+    }
+
+    fn try_write_u8(&mut self, data: u8) -> Result<(), BusError> {
+        match self.expander {
+            Expander::Pcf8574 => self.i2c.write(self.address, &[data]),
+            Expander::Mcp23008 => self.i2c.write(self.address, &[MCP23008_OLAT, data]),
+        }
+        .map_err(|_| BusError)
+    }
+
+    fn cache_address(&self) -> bool {
+        self.cache_address
+    }
+}
+
+/// Passes writes through to another bus, and reports each complete register write to a
+/// callback, e.g. to record a live performance as a register dump.
+///
+/// The chip always sends the register address then the value, so the callback gets them
+/// as pairs: `capture(register, value)`. It runs right after the address and value went out.
+///
+/// Example:
+/// ```no_run
+/// let mut recording: heapless::Vec<(u64, u8, u8), 4096> = heapless::Vec::new();
+///
+/// let data_bus = TeeBus::new(DataBus::new(data_pins), |register, value| {
+///     let _ = recording.push((timer.get_counter().ticks(), register, value));
+/// });
+/// let mut chip = YM2149::new(data_bus, master_clock_freq, bc1, bdir);
+/// ```
+pub struct TeeBus<BUS, F> {
+    bus: BUS,
+    capture: F,
+    /// Register address sent, waiting for its value
+    address: Option<u8>,
+}
+
+impl<BUS, F> TeeBus<BUS, F>
+where
+    BUS: OutputBus,
+    F: FnMut(u8, u8),
+{
+    pub fn new(bus: BUS, capture: F) -> Self {
+        Self {
+            bus,
+            capture,
+            address: None,
+        }
+    }
+
+    /// Get back the wrapped bus and the callback.
+    pub fn release(self) -> (BUS, F) {
+        (self.bus, self.capture)
+    }
+}
+
+impl<BUS, F> OutputBus for TeeBus<BUS, F>
+where
+    BUS: OutputBus,
+    F: FnMut(u8, u8),
+{
+    fn write_u8(&mut self, data: u8) {
+        let _ = self.try_write_u8(data);
+    }
+
+    fn try_write_u8(&mut self, data: u8) -> Result<(), BusError> {
+        if let Err(error) = self.bus.try_write_u8(data) {
+            // The driver starts over with the address
+            self.address = None;
+            return Err(error);
+        }
+        match self.address.take() {
+            Some(register) => (self.capture)(register, data),
+            None => self.address = Some(data),
+        }
+        Ok(())
+    }
+
+    fn set_direction(&mut self, direction: BusDirection) {
+        self.bus.set_direction(direction);
+    }
+}
@@ -0,0 +1,266 @@
+//! Analog controls: turning ADC readings (pots, LDRs, sensors, microphones) into smooth
+//! pitch and volume.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::frame::{self, Frame};
+use crate::note::{Accidental, Note, NoteName};
+use crate::{AudioChannel, Level, OutputBus, YM2149};
+
+/// How a reading is spread over the output range of an [AdcMap].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// More resolution at the low end of the input (`x²`)
+    Squared,
+    /// More resolution at the high end of the input (`√x`)
+    SquareRoot,
+}
+
+impl Curve {
+    /// Apply the curve to `x` in Q16 (`0..=0x10000`).
+    fn apply(self, x: u32) -> u32 {
+        match self {
+            Self::Linear => x,
+            Self::Squared => ((x as u64 * x as u64) >> 16) as u32,
+            Self::SquareRoot => ((x as u64) << 16).isqrt() as u32,
+        }
+    }
+}
+
+/// Maps a stream of ADC readings to a control value, smoothed and slew-limited.
+///
+/// Readings are clamped to the input range, shaped by a [Curve], then scaled to the output
+/// range (which may be reversed, e.g. for an LDR getting darker as the hand comes closer).
+/// Smoothing filters out ADC noise, the slew limit caps how fast the output moves.
+///
+/// Example:
+/// ```no_run
+/// // 12 bit pot to a pitch, C3 to C6 in cents from A4
+/// let mut pitch = AdcMap::new((0, 4095), (-2100, 1500)).with_smoothing(192);
+/// let cents = pitch.update(adc.read(&mut pot)?);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdcMap {
+    input: (u16, u16),
+    output: (i32, i32),
+    curve: Curve,
+    smoothing: u8,
+    slew: u32,
+    /// Current output, Q8. `None` until the first reading
+    value: Option<i64>,
+}
+
+impl AdcMap {
+    /// A linear map from `input` (lowest and highest reading) to `output`, without
+    /// smoothing or slew limit.
+    pub const fn new(input: (u16, u16), output: (i32, i32)) -> Self {
+        Self {
+            input,
+            output,
+            curve: Curve::Linear,
+            smoothing: 0,
+            slew: 0,
+            value: None,
+        }
+    }
+
+    pub const fn with_curve(mut self, curve: Curve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    /// Keep `smoothing`/256 of the previous output on every reading (one-pole low-pass).
+    /// Higher is smoother but slower.
+    pub const fn with_smoothing(mut self, smoothing: u8) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Move the output by at most `step` per reading, 0 for no limit.
+    pub const fn with_slew(mut self, step: u32) -> Self {
+        self.slew = step;
+        self
+    }
+
+    /// The current output, 0 before the first reading.
+    pub fn value(&self) -> i32 {
+        self.value.map_or(0, |value| ((value + 0x80) >> 8) as i32)
+    }
+
+    /// Feed a reading, returning the new output.
+    pub fn update(&mut self, reading: u16) -> i32 {
+        let (low, high) = (
+            self.input.0.min(self.input.1),
+            self.input.0.max(self.input.1),
+        );
+        let span = (high - low).max(1) as u32;
+        let x = ((reading.clamp(low, high) - low) as u32) << 16;
+        let x = self.curve.apply(x / span) as i64;
+
+        let (from, to) = (self.output.0 as i64, self.output.1 as i64);
+        let target = (from << 8) + (((to - from) * x) >> 8);
+
+        let value = match self.value {
+            // The first reading is taken as is
+            None => target,
+            Some(value) => {
+                let smoothing = self.smoothing as i64;
+                let smoothed = (value * smoothing + target * (256 - smoothing)) >> 8;
+                match self.slew {
+                    0 => smoothed,
+                    slew => {
+                        let slew = (slew as i64) << 8;
+                        smoothed.clamp(value - slew, value + slew)
+                    }
+                }
+            }
+        };
+        self.value = Some(value);
+        self.value()
+    }
+}
+
+/// A two-knob (or two-hand) synth voice: one [AdcMap] for the pitch, in cents from A4,
+/// one for the level, 0 - 15.
+///
+/// Example:
+/// ```no_run
+/// let mut theremin = Theremin::new(
+///     AudioChannel::A,
+///     AdcMap::new((0, 4095), (-2400, 1200)).with_smoothing(224).with_slew(40),
+///     // LDR: darker is louder
+///     AdcMap::new((300, 3800), (15, 0)).with_smoothing(128),
+/// );
+/// chip.set_vibrato(AudioChannel::A, 12, 5);
+///
+/// // Every few milliseconds
+/// theremin.update(&mut chip, adc.read(&mut pitch_pot)?, adc.read(&mut ldr)?);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theremin {
+    channel: AudioChannel,
+    pitch: AdcMap,
+    level: AdcMap,
+}
+
+impl Theremin {
+    pub const fn new(channel: AudioChannel, pitch: AdcMap, level: AdcMap) -> Self {
+        Self {
+            channel,
+            pitch,
+            level,
+        }
+    }
+
+    /// Feed one reading of each control, playing the result.
+    pub fn update<DATABUS, BC1, BDIR>(
+        &mut self,
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+        pitch: u16,
+        level: u16,
+    ) where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let cents = self
+            .pitch
+            .update(pitch)
+            .clamp(i16::MIN as i32, i16::MAX as i32);
+        let level = self.level.update(level).clamp(0, 15);
+
+        let a4 = Note::new(NoteName::A, Accidental::Natural, 4);
+        chip.play_note(self.channel, a4.detune(cents as i16));
+        chip.volume(self.channel, Level::fixed(level as u8));
+    }
+}
+
+/// Ducks the music under an external signal, e.g. an ADC watching a microphone, so the
+/// chip sits under announcements (sidechain ducking).
+///
+/// Readings are levels of the external signal (e.g. peak or rectified average): over the
+/// threshold, the music is lowered by `depth` level steps. The attack sets how fast it
+/// goes down, the release how fast it comes back, both as the part of the previous
+/// attenuation kept on every reading (`x`/256, higher is slower).
+///
+/// [drive](Ducker::drive) sets it as the chip's
+/// [master attenuation](YM2149::set_master_attenuation), so everything playing is lowered,
+/// live notes and effects included. [apply](Ducker::apply) lowers a single frame instead.
+///
+/// Example:
+/// ```no_run
+/// // 12 bit ADC on a mic envelope detector, feeding at 1 kHz
+/// let mut ducker = Ducker::new(600, 8).with_attack(200).with_release(254);
+/// ducker.update(adc.read(&mut mic)?);
+/// ducker.drive(&mut chip);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ducker {
+    threshold: u16,
+    depth: u8,
+    attack: u8,
+    release: u8,
+    /// Current attenuation in level steps, Q8
+    attenuation: u32,
+}
+
+impl Ducker {
+    /// A ducker lowering the music by `depth` level steps (15 silences it) while readings
+    /// are over `threshold`, with a fast attack and a slow release.
+    pub const fn new(threshold: u16, depth: u8) -> Self {
+        Self {
+            threshold,
+            depth: if depth > 15 { 15 } else { depth },
+            attack: 128,
+            release: 250,
+            attenuation: 0,
+        }
+    }
+
+    /// Keep `attack`/256 of the attenuation on every reading while ducking.
+    pub const fn with_attack(mut self, attack: u8) -> Self {
+        self.attack = attack;
+        self
+    }
+
+    /// Keep `release`/256 of the attenuation on every reading while coming back.
+    pub const fn with_release(mut self, release: u8) -> Self {
+        self.release = release;
+        self
+    }
+
+    /// How many level steps the music is lowered by.
+    pub fn attenuation(&self) -> u8 {
+        ((self.attenuation + 0x80) >> 8) as u8
+    }
+
+    /// Feed a reading of the external signal, returning the new attenuation.
+    pub fn update(&mut self, reading: u16) -> u8 {
+        let (target, keep) = if reading > self.threshold {
+            ((self.depth as u32) << 8, self.attack as u32)
+        } else {
+            (0, self.release as u32)
+        };
+        self.attenuation = (self.attenuation * keep + target * (256 - keep)) >> 8;
+        self.attenuation()
+    }
+
+    /// Lower the whole chip by the current attenuation, as its
+    /// [master attenuation](YM2149::set_master_attenuation).
+    pub fn drive<DATABUS, BC1, BDIR>(&self, chip: &mut YM2149<DATABUS, BC1, BDIR>)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        chip.set_master_attenuation(self.attenuation());
+    }
+
+    /// The frame with every channel lowered by the current attenuation. Envelope-driven
+    /// channels can't be lowered, and only go silent with a depth of 15.
+    pub fn apply(&self, frame: &Frame) -> Frame {
+        frame::attenuate(frame, [self.attenuation(); 3])
+    }
+}
@@ -0,0 +1,117 @@
+//! Known signals for checking boards and chips with a scope or an analyzer.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::envelope::envelope_period_for_hz;
+use crate::note::{Accidental, Note, NoteName};
+use crate::{AudioChannel, OutputBus, Register, YM2149};
+
+/// One of the [test signals](test_signals), numbered so a measurement session can be
+/// scripted by index.
+///
+/// Signals are written straight to the chip's outputs, ignoring the
+/// [channel map](YM2149::with_channel_map), pitch shift and the like. Everything not part
+/// of the signal is muted.
+///
+/// | Index   | Signal                                                         |
+/// |---------|----------------------------------------------------------------|
+/// | 0 - 2   | 440 Hz square at full level on output A, B, then C             |
+/// | 3 - 5   | Slowest square (TP 0xFFF) at full level, for measuring the DAC |
+/// | 6 - 37  | Noise at period 0 to 31, on output A                           |
+/// | 38 - 53 | Envelope with R13 = 0 to 15, 5 Hz, on output A                 |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestSignal {
+    Tone440(AudioChannel),
+    Square(AudioChannel),
+    /// Noise period, 0 - 31
+    Noise(u8),
+    /// Value of R13, 0 - 15
+    Envelope(u8),
+}
+
+impl TestSignal {
+    /// Number of test signals.
+    pub const COUNT: usize = 54;
+
+    /// The signal at `index`, `None` past the last one.
+    pub const fn from_index(index: usize) -> Option<Self> {
+        Some(match index {
+            0..=2 => Self::Tone440(AudioChannel::wrapping_from_index(index)),
+            3..=5 => Self::Square(AudioChannel::wrapping_from_index(index)),
+            6..=37 => Self::Noise((index - 6) as u8),
+            38..=53 => Self::Envelope((index - 38) as u8),
+            _ => return None,
+        })
+    }
+
+    /// Set the chip up to output the signal.
+    pub fn play<DATABUS, BC1, BDIR>(self, chip: &mut YM2149<DATABUS, BC1, BDIR>)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        // Mute everything, keeping the I/O directions
+        let io = chip.register(Register::IoPortMixerSettings) & 0xC0;
+        chip.write(Register::IoPortMixerSettings, io | 0x3F);
+        for channel in AudioChannel::ALL {
+            chip.write(Register::ALevel as u8 + channel as u8, 0);
+        }
+
+        let master_clock_frequency = chip.master_clock_frequency();
+        let (output, mixer, level) = match self {
+            Self::Tone440(channel) => {
+                let a4 = Note::new(NoteName::A, Accidental::Natural, 4);
+                write_tone(chip, channel, a4.period(master_clock_frequency));
+                (channel, 1 << channel as u8, 0x0F)
+            }
+            Self::Square(channel) => {
+                write_tone(chip, channel, 0x0FFF);
+                (channel, 1 << channel as u8, 0x0F)
+            }
+            Self::Noise(period) => {
+                chip.write(Register::NoiseFreq5bit, period & 0x1F);
+                (AudioChannel::A, 1 << 3, 0x0F)
+            }
+            Self::Envelope(shape) => {
+                let period = envelope_period_for_hz(master_clock_frequency, 5).to_le_bytes();
+                chip.write(Register::EFreq8bitFineAdj, period[0]);
+                chip.write(Register::EFreq8bitRoughAdj, period[1]);
+                chip.write(Register::EShape, shape & 0x0F);
+                (AudioChannel::A, 0, 0x10)
+            }
+        };
+        chip.write(Register::IoPortMixerSettings, (io | 0x3F) & !mixer);
+        chip.write(Register::ALevel as u8 + output as u8, level);
+    }
+}
+
+fn write_tone<DATABUS, BC1, BDIR>(
+    chip: &mut YM2149<DATABUS, BC1, BDIR>,
+    output: AudioChannel,
+    period: u16,
+) where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    let [fine, rough] = period.to_le_bytes();
+    chip.write(output as u8 * 2, fine);
+    chip.write(output as u8 * 2 + 1, rough & 0x0F);
+}
+
+/// Every [TestSignal], in index order.
+///
+/// Example:
+/// ```no_run
+/// // Step through the signals on a button press
+/// for (index, signal) in test_signals().enumerate() {
+///     defmt::info!("Test signal {}", index);
+///     signal.play(&mut chip);
+///     wait_for_button();
+/// }
+/// chip.silence();
+/// ```
+pub fn test_signals() -> impl Iterator<Item = TestSignal> {
+    (0..TestSignal::COUNT).filter_map(TestSignal::from_index)
+}
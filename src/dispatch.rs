@@ -0,0 +1,86 @@
+//! Running each subsystem at its own rate from a single hardware timer.
+
+/// Divides one timer into `N` slower rates, one per subsystem (task), so each only runs as
+/// often as it needs to: e.g. a sequencer at 50 Hz, software envelopes at 200 Hz and a
+/// sample voice at 8 kHz, all from an 8 kHz timer.
+///
+/// Rates don't have to divide the timer rate: a task at 300 Hz on a 1 kHz timer runs on
+/// 3 ticks out of 10, evenly spread. Rates over the timer rate run on every tick.
+///
+/// Example:
+/// ```no_run
+/// const SEQUENCER: usize = 0;
+/// const ENVELOPES: usize = 1;
+/// const SAMPLES: usize = 2;
+///
+/// let mut dispatcher = Dispatcher::new(8000, [50, 200, 8000]);
+///
+/// // In the 8 kHz timer interrupt
+/// dispatcher.tick();
+/// if dispatcher.due(SAMPLES) {
+///     sample.tick(&mut chip);
+/// }
+/// if dispatcher.due(ENVELOPES) {
+///     chip.tick();
+/// }
+/// if dispatcher.due(SEQUENCER) {
+///     scheduler.tick();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dispatcher<const N: usize> {
+    tick_rate: u32,
+    rates: [u32; N],
+    /// Accumulated rate of each task since it last ran
+    phases: [u32; N],
+    /// Tasks due on the current tick, one bit each
+    due: u32,
+}
+
+impl<const N: usize> Dispatcher<N> {
+    /// A dispatcher for a timer ticking `tick_rate` times per second, running task `n`
+    /// `rates[n]` times per second. Every task is due on the first tick.
+    ///
+    /// At most 32 tasks.
+    pub const fn new(tick_rate: u32, rates: [u32; N]) -> Self {
+        assert!(N <= 32, "a Dispatcher runs at most 32 tasks");
+        let tick_rate = if tick_rate == 0 { 1 } else { tick_rate };
+        Self {
+            tick_rate,
+            rates,
+            phases: [tick_rate; N],
+            due: 0,
+        }
+    }
+
+    /// Change the rate of a task, e.g. to follow the tempo. Ignored past the last task.
+    pub fn set_rate(&mut self, task: usize, rate: u32) {
+        if let Some(slot) = self.rates.get_mut(task) {
+            *slot = rate;
+        }
+    }
+
+    /// The rate of a task, 0 past the last one.
+    pub fn rate(&self, task: usize) -> u32 {
+        self.rates.get(task).copied().unwrap_or(0)
+    }
+
+    /// Advance by one timer tick, working out which tasks are due.
+    pub fn tick(&mut self) {
+        self.due = 0;
+        for (task, (phase, &rate)) in self.phases.iter_mut().zip(&self.rates).enumerate() {
+            if *phase >= self.tick_rate {
+                *phase -= self.tick_rate;
+                self.due |= 1 << task;
+            }
+            *phase = phase
+                .saturating_add(rate)
+                .min(self.tick_rate.saturating_mul(2));
+        }
+    }
+
+    /// Whether `task` runs on the current tick.
+    pub fn due(&self, task: usize) -> bool {
+        task < N && self.due & (1 << task) != 0
+    }
+}
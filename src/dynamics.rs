@@ -0,0 +1,66 @@
+//! Playing with dynamics: MIDI velocities (or any 0 - 127 value) to channel levels.
+
+use crate::{Level, RawEnvelope};
+
+/// How velocities (0 - 127) map to the 16 levels of a channel.
+///
+/// The chip's levels are about 3 dB apart, so even [Linear](VelocityCurve::Linear) covers
+/// a wide range of loudness. Any velocity over 0 gives at least level 1.
+///
+/// Example:
+/// ```no_run
+/// const PLUCK: RawEnvelope = RawEnvelope::from_slice(&[15, 12, 10, 8, 7, 6]).with_loop(5);
+/// let curve = VelocityCurve::Linear;
+///
+/// // Static level
+/// chip.note_on_now(AudioChannel::A, Note::from_midi(key), curve.level(velocity));
+/// // Or scale the envelope, so its peak follows the velocity
+/// chip.set_software_envelope(AudioChannel::B, Some(curve.envelope(velocity, PLUCK)));
+/// chip.play_note(AudioChannel::B, Note::from_midi(key));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VelocityCurve {
+    /// Level proportional to the velocity
+    #[default]
+    Linear,
+    /// Level rising slowly then faster (doubling every 32 velocities), keeping soft
+    /// playing quiet and most of the range for hard hits
+    Exponential,
+    /// A few fixed levels, evenly spread up to 15 (e.g. 4 for pp, p, f, ff)
+    Steps(u8),
+}
+
+impl VelocityCurve {
+    /// The level for `velocity`. Velocity 0 is silent, 127 (and over) the loudest.
+    pub fn level(self, velocity: u8) -> Level {
+        let velocity = velocity.min(127) as u32;
+        if velocity == 0 {
+            return Level::OFF;
+        }
+        let level = match self {
+            Self::Linear => (velocity * 15 + 63) / 127,
+            Self::Exponential => {
+                let top = pow2(127) - pow2(0);
+                ((pow2(velocity) - pow2(0)) * 15 + top / 2) / top
+            }
+            Self::Steps(0) => 15,
+            Self::Steps(steps) => {
+                let steps = steps.min(15) as u32;
+                let step = ((velocity - 1) * steps / 127) + 1;
+                (step * 15 + steps / 2) / steps
+            }
+        };
+        Level::fixed(level.max(1) as u8)
+    }
+
+    /// `envelope` with its levels scaled so its peak (15) becomes the level for `velocity`.
+    pub fn envelope(self, velocity: u8, envelope: RawEnvelope) -> RawEnvelope {
+        let level = self.level(velocity).bits() as u16;
+        envelope.scale((level * 256 + 7) / 15)
+    }
+}
+
+/// `2^(x/32)` in Q16, interpolating linearly between powers of 2.
+fn pow2(x: u32) -> u32 {
+    (0x10000 + (x % 32) * 0x800) << (x / 32)
+}
@@ -0,0 +1,99 @@
+//! Echo: a lead played again, later and softer, on a spare channel.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::note::Note;
+use crate::scheduler::Scheduler;
+use crate::{AudioChannel, Level, OutputBus, YM2149};
+
+/// Replays the notes of a lead on another channel, `delay` ticks later and a few levels
+/// down, for the classic "lead + echo" sound.
+///
+/// Tell it about the lead's notes with [play](Echo::play) and [release](Echo::release),
+/// and call [tick](Echo::tick) from the same timer as [YM2149::tick]. Up to `N` notes can
+/// be waiting to be echoed, more are dropped.
+///
+/// Example:
+/// ```no_run
+/// // Echo on channel C, 3 ticks (60 ms at 50 Hz) late and 4 levels softer
+/// let mut echo: Echo = Echo::new(AudioChannel::C, 3, 4);
+///
+/// let note = "A4".parse()?;
+/// chip.play_note(AudioChannel::A, note);
+/// chip.volume(AudioChannel::A, Level::fixed(14));
+/// echo.play(note, 14);
+///
+/// // 50 Hz timer
+/// chip.tick();
+/// echo.tick(&mut chip);
+/// ```
+pub struct Echo<const N: usize = 8> {
+    channel: AudioChannel,
+    delay: u32,
+    attenuation: u8,
+    /// Notes to replay and their level, `None` for releases
+    queue: Scheduler<Option<(Note, u8)>, N>,
+}
+
+impl<const N: usize> Echo<N> {
+    /// An echo playing on `channel`, `delay` ticks late and `attenuation` levels softer.
+    pub const fn new(channel: AudioChannel, delay: u32, attenuation: u8) -> Self {
+        Self {
+            channel,
+            delay,
+            attenuation,
+            // Only absolute ticks are used, no need for the tick rate
+            queue: Scheduler::new(0),
+        }
+    }
+
+    /// The channel the echo plays on.
+    pub fn channel(&self) -> AudioChannel {
+        self.channel
+    }
+
+    /// Echo a note the lead started at `level` (0 - 15).
+    pub fn play(&mut self, note: Note, level: u8) {
+        self.push(Some((note, level)));
+    }
+
+    /// Echo the lead's note ending.
+    pub fn release(&mut self) {
+        self.push(None);
+    }
+
+    /// Forget the notes waiting to be echoed.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Advance by one tick, playing the echoes that are due.
+    pub fn tick<DATABUS, BC1, BDIR>(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        self.queue.tick();
+        while let Some(event) = self.queue.pop_due() {
+            match event {
+                Some((note, level)) => {
+                    chip.play_note(self.channel, note);
+                    let level = level.min(15).saturating_sub(self.attenuation);
+                    chip.volume(self.channel, Level::fixed(level));
+                }
+                None => {
+                    chip.release_note(self.channel);
+                    chip.volume(self.channel, Level::OFF);
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, event: Option<(Note, u8)>) {
+        let due = self.queue.now().wrapping_add(self.delay);
+        if self.queue.at(due, event).is_err() {
+            defmt::warn!("ym2149: echo queue full, note dropped");
+        }
+    }
+}
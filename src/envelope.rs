@@ -0,0 +1,348 @@
+//! The chip's hardware envelope generator (R11 - R13).
+
+use crate::note::{self, Note};
+
+/// Shape of the hardware envelope, as written to [Register::EShape](crate::Register::EShape).
+///
+/// From the datasheet, the envelope repeats every `256 * EP / fMaster` seconds:
+///
+/// | Shape            | R13  | Waveform  |
+/// |------------------|------|-----------|
+/// | `Decay`          | 0x00 | `\___`    |
+/// | `Attack`         | 0x04 | `/___`    |
+/// | `SawDown`        | 0x08 | `\\\\`    |
+/// | `TriangleDown`   | 0x0A | `\/\/`    |
+/// | `DecayHoldHigh`  | 0x0B | `\‾‾‾`    |
+/// | `SawUp`          | 0x0C | `////`    |
+/// | `AttackHold`     | 0x0D | `/‾‾‾`    |
+/// | `TriangleUp`     | 0x0E | `/\/\`    |
+///
+/// The other 8 values of R13 are aliases of these, see [EnvelopeShape::TABLE]. To write
+/// one of them exactly, use [ShapeValue] or [ShapeFlags].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EnvelopeShape {
+    Decay = 0x00,
+    Attack = 0x04,
+    SawDown = 0x08,
+    TriangleDown = 0x0A,
+    DecayHoldHigh = 0x0B,
+    SawUp = 0x0C,
+    AttackHold = 0x0D,
+    TriangleUp = 0x0E,
+}
+
+impl EnvelopeShape {
+    /// The shape played for each of the 16 values of R13, aliases included.
+    pub const TABLE: [EnvelopeShape; 16] = [
+        Self::Decay,
+        Self::Decay,
+        Self::Decay,
+        Self::Decay,
+        Self::Attack,
+        Self::Attack,
+        Self::Attack,
+        Self::Attack,
+        Self::SawDown,
+        Self::Decay,
+        Self::TriangleDown,
+        Self::DecayHoldHigh,
+        Self::SawUp,
+        Self::AttackHold,
+        Self::TriangleUp,
+        Self::Attack,
+    ];
+
+    /// The shape a value of R13 plays. Only the lower 4 bits count.
+    pub const fn from_bits(bits: u8) -> Self {
+        match crate::nth(&Self::TABLE, (bits & 0x0F) as usize) {
+            Some(&shape) => shape,
+            None => Self::Decay,
+        }
+    }
+
+    /// Name of the shape, for display.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Decay => "Decay",
+            Self::Attack => "Attack",
+            Self::SawDown => "Saw down",
+            Self::TriangleDown => "Triangle down",
+            Self::DecayHoldHigh => "Decay, hold high",
+            Self::SawUp => "Saw up",
+            Self::AttackHold => "Attack, hold",
+            Self::TriangleUp => "Triangle up",
+        }
+    }
+
+    /// Rough drawing of the waveform, as in the table above.
+    pub const fn waveform(self) -> &'static str {
+        match self {
+            Self::Decay => "\\___",
+            Self::Attack => "/___",
+            Self::SawDown => "\\\\\\\\",
+            Self::TriangleDown => "\\/\\/",
+            Self::DecayHoldHigh => "\\‾‾‾",
+            Self::SawUp => "////",
+            Self::AttackHold => "/‾‾‾",
+            Self::TriangleUp => "/\\/\\",
+        }
+    }
+
+    /// Number of envelope periods it takes the shape to repeat, `None` for one-shot shapes.
+    ///
+    /// Saws repeat every period, triangles every other period (one up, one down).
+    pub const fn cycle_length(self) -> Option<u32> {
+        match self {
+            Self::SawDown | Self::SawUp => Some(1),
+            Self::TriangleDown | Self::TriangleUp => Some(2),
+            _ => None,
+        }
+    }
+}
+
+impl From<EnvelopeShape> for u8 {
+    fn from(value: EnvelopeShape) -> Self {
+        value as u8
+    }
+}
+
+/// Each of the 16 values of R13, aliases included, named after the shape it plays and
+/// its value. For code ported from other machines that writes exact values.
+///
+/// Example:
+/// ```no_run
+/// chip.write_register(Register::EShape, ShapeValue::Decay9)?;
+/// assert_eq!(ShapeValue::Decay9.shape(), EnvelopeShape::Decay);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ShapeValue {
+    Decay0 = 0x00,
+    Decay1 = 0x01,
+    Decay2 = 0x02,
+    Decay3 = 0x03,
+    Attack4 = 0x04,
+    Attack5 = 0x05,
+    Attack6 = 0x06,
+    Attack7 = 0x07,
+    SawDown8 = 0x08,
+    Decay9 = 0x09,
+    TriangleDown10 = 0x0A,
+    DecayHoldHigh11 = 0x0B,
+    SawUp12 = 0x0C,
+    AttackHold13 = 0x0D,
+    TriangleUp14 = 0x0E,
+    Attack15 = 0x0F,
+}
+
+impl ShapeValue {
+    /// Every value, in order, so `ALL[n]` is R13 = `n`.
+    pub const ALL: [ShapeValue; 16] = [
+        Self::Decay0,
+        Self::Decay1,
+        Self::Decay2,
+        Self::Decay3,
+        Self::Attack4,
+        Self::Attack5,
+        Self::Attack6,
+        Self::Attack7,
+        Self::SawDown8,
+        Self::Decay9,
+        Self::TriangleDown10,
+        Self::DecayHoldHigh11,
+        Self::SawUp12,
+        Self::AttackHold13,
+        Self::TriangleUp14,
+        Self::Attack15,
+    ];
+
+    /// The value of R13. Only the lower 4 bits count.
+    pub const fn from_bits(bits: u8) -> Self {
+        match crate::nth(&Self::ALL, (bits & 0x0F) as usize) {
+            Some(&value) => value,
+            None => Self::Decay0,
+        }
+    }
+
+    pub const fn bits(self) -> u8 {
+        self as u8
+    }
+
+    /// The shape the value plays.
+    pub const fn shape(self) -> EnvelopeShape {
+        EnvelopeShape::from_bits(self as u8)
+    }
+
+    /// Whether another value is usually written for the same shape.
+    pub const fn is_alias(self) -> bool {
+        self.shape() as u8 != self as u8
+    }
+
+    pub const fn flags(self) -> ShapeFlags {
+        ShapeFlags::from_bits(self as u8)
+    }
+}
+
+impl From<ShapeValue> for u8 {
+    fn from(value: ShapeValue) -> Self {
+        value as u8
+    }
+}
+
+impl From<EnvelopeShape> for ShapeValue {
+    fn from(shape: EnvelopeShape) -> Self {
+        Self::from_bits(shape as u8)
+    }
+}
+
+/// The four bits of R13, as named in the datasheet.
+///
+/// Example:
+/// ```no_run
+/// let flags = ShapeFlags { cont: true, att: true, alt: true, hold: false };
+/// assert_eq!(flags.value(), ShapeValue::TriangleUp14);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShapeFlags {
+    /// B3: keep going after the first cycle, otherwise drop to 0 and stay there
+    pub cont: bool,
+    /// B2: the first cycle counts up, otherwise down
+    pub att: bool,
+    /// B1: flip the direction after every cycle
+    pub alt: bool,
+    /// B0: stop after the first cycle, holding the last level (flipped if `alt`)
+    pub hold: bool,
+}
+
+impl ShapeFlags {
+    /// The flags of a value of R13. Only the lower 4 bits count.
+    pub const fn from_bits(bits: u8) -> Self {
+        Self {
+            cont: bits & 0x08 != 0,
+            att: bits & 0x04 != 0,
+            alt: bits & 0x02 != 0,
+            hold: bits & 0x01 != 0,
+        }
+    }
+
+    /// The value of R13.
+    pub const fn bits(self) -> u8 {
+        (self.cont as u8) << 3 | (self.att as u8) << 2 | (self.alt as u8) << 1 | self.hold as u8
+    }
+
+    pub const fn value(self) -> ShapeValue {
+        ShapeValue::from_bits(self.bits())
+    }
+
+    /// The shape the flags play.
+    pub const fn shape(self) -> EnvelopeShape {
+        EnvelopeShape::from_bits(self.bits())
+    }
+}
+
+impl From<u8> for ShapeFlags {
+    fn from(bits: u8) -> Self {
+        Self::from_bits(bits)
+    }
+}
+
+impl From<ShapeFlags> for u8 {
+    fn from(flags: ShapeFlags) -> Self {
+        flags.bits()
+    }
+}
+
+impl From<ShapeValue> for ShapeFlags {
+    fn from(value: ShapeValue) -> Self {
+        value.flags()
+    }
+}
+
+impl From<ShapeFlags> for ShapeValue {
+    fn from(flags: ShapeFlags) -> Self {
+        flags.value()
+    }
+}
+
+impl From<EnvelopeShape> for ShapeFlags {
+    fn from(shape: EnvelopeShape) -> Self {
+        Self::from_bits(shape as u8)
+    }
+}
+
+/// Frequency of the hardware envelope, either as a raw envelope period (EP) or in Hz.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeFrequency {
+    /// The 16 bit value of R11 (fine) and R12 (rough)
+    Period(u16),
+    /// Repetition rate of the waveform in Hz. A triangle takes two envelope periods,
+    /// one-shot shapes play for one.
+    Hz(u32),
+}
+
+impl EnvelopeFrequency {
+    /// The envelope period (EP) making `shape` repeat at this frequency, for a given
+    /// master clock frequency.
+    pub fn period(self, master_clock_frequency: u32, shape: EnvelopeShape) -> u16 {
+        match self {
+            Self::Period(period) => period,
+            Self::Hz(frequency) => {
+                let cycle = shape.cycle_length().unwrap_or(1);
+                envelope_period_for_hz(master_clock_frequency, frequency.saturating_mul(cycle))
+            }
+        }
+    }
+}
+
+/// The envelope period (EP) for an envelope frequency (one period per cycle).
+///
+/// The formula for the frequency is ``f = fMaster / (256 * EP)``, rounded to the nearest
+/// period and clamped to `1..=0xFFFF`. 0 Hz gives the slowest envelope.
+pub const fn envelope_period_for_hz(master_clock_frequency: u32, hz: u32) -> u16 {
+    if hz == 0 {
+        return u16::MAX;
+    }
+    let divisor = 256 * hz as u64;
+    let period = (master_clock_frequency as u64 + divisor / 2) / divisor;
+    if period < 1 {
+        1
+    } else if period > u16::MAX as u64 {
+        u16::MAX
+    } else {
+        period as u16
+    }
+}
+
+/// The envelope frequency for an envelope period (EP), rounded to the nearest Hz.
+///
+/// Slow envelopes round down to 0 Hz: at 2 MHz, anything over EP 15625 is under 0.5 Hz.
+/// A period of 0 plays like 1.
+pub const fn envelope_hz_for_period(master_clock_frequency: u32, period: u16) -> u32 {
+    let divisor = 256 * if period == 0 { 1 } else { period as u64 };
+    ((master_clock_frequency as u64 + divisor / 2) / divisor) as u32
+}
+
+/// The envelope period (EP) that makes a repeating `shape` sound at the pitch of `note`,
+/// for "envelope bass". `None` for one-shot shapes.
+///
+/// The exact period is rarely a whole number, so of the two closest ones this picks
+/// the one closest in pitch (geometric, not arithmetic, rounding).
+pub fn period_for_note(
+    master_clock_frequency: u32,
+    note: Note,
+    shape: EnvelopeShape,
+) -> Option<u16> {
+    let divider = 256 * shape.cycle_length()? as u64;
+    let exact = note::period_q8(master_clock_frequency, note.cents_from_a4(), divider)
+        .clamp(1 << 8, (u16::MAX as u64) << 8);
+
+    // Between EP and EP + 1, EP is closer in pitch if exact / EP <= (EP + 1) / exact
+    let period = exact >> 8;
+    let period = if exact * exact <= (period * (period + 1)) << 16 {
+        period
+    } else {
+        period + 1
+    };
+    Some(period.min(u16::MAX as u64) as u16)
+}
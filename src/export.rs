@@ -0,0 +1,184 @@
+//! Writing captured register writes (e.g. from a [TeeBus](crate::TeeBus)) as `.psg` or
+//! `.ym` files, for desktop AY players. The bytes go to an [ExportSink]: a file on the
+//! host, a UART or USB link on the board.
+//!
+//! Captures are `(timestamp, register, value)` triples, with timestamps in microseconds
+//! (the RP2040 timer's unit), in order. They're cut into frames at the song's frame rate,
+//! starting from the first write.
+
+use core::iter::Peekable;
+
+use crate::frame::Frame;
+use crate::Register;
+
+/// Where an exported file goes.
+///
+/// Example:
+/// ```no_run
+/// // On the host
+/// struct File(std::fs::File);
+///
+/// impl ExportSink for File {
+///     type Error = std::io::Error;
+///
+///     fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+///         std::io::Write::write_all(&mut self.0, bytes)
+///     }
+/// }
+///
+/// let mut file = File(std::fs::File::create("capture.psg")?);
+/// export::write_psg(&mut file, capture, 50)?;
+/// ```
+pub trait ExportSink {
+    type Error;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Header information of a `.ym` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YmInfo<'a> {
+    /// Frames per second, usually 50
+    pub frame_rate: u16,
+    pub master_clock_frequency: u32,
+    pub title: &'a str,
+    pub author: &'a str,
+    pub comment: &'a str,
+}
+
+/// Frame index of each write, relative to the first one.
+fn frame_indices(
+    writes: impl IntoIterator<Item = (u64, u8, u8)>,
+    frame_rate: u16,
+) -> impl Iterator<Item = (usize, u8, u8)> {
+    let mut start = None;
+    writes.into_iter().map(move |(timestamp, register, value)| {
+        let start = *start.get_or_insert(timestamp);
+        let frame = timestamp.saturating_sub(start) * frame_rate as u64 / 1_000_000;
+        (frame as usize, register, value)
+    })
+}
+
+/// Replay captured writes into one [Frame] per frame period.
+///
+/// A frame has [retrigger](Frame::retrigger) set if R13 was written during it.
+/// Writes to the I/O ports are dropped.
+pub fn frames(
+    writes: impl IntoIterator<Item = (u64, u8, u8)>,
+    frame_rate: u16,
+) -> impl Iterator<Item = Frame> {
+    Frames {
+        writes: frame_indices(writes, frame_rate).peekable(),
+        state: Frame::default(),
+        index: 0,
+    }
+}
+
+/// The iterator behind [frames].
+struct Frames<I: Iterator> {
+    writes: Peekable<I>,
+    /// The registers as of the last write
+    state: Frame,
+    /// Index of the next frame
+    index: usize,
+}
+
+impl<I: Iterator<Item = (usize, u8, u8)>> Iterator for Frames<I> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        // Ends with the frame of the last write
+        self.writes.peek()?;
+        self.state.retrigger = false;
+        while let Some((_, register, value)) = self.writes.next_if(|&(i, ..)| i <= self.index) {
+            if let Some(slot) = self.state.registers.get_mut(register as usize) {
+                *slot = value;
+                self.state.retrigger |= register == Register::EShape as u8;
+            }
+        }
+        self.index += 1;
+        Some(self.state)
+    }
+}
+
+/// Write captured register writes as a `.psg` file.
+///
+/// Writes are stored as they happened, only grouped by frame.
+pub fn write_psg<S: ExportSink>(
+    out: &mut S,
+    writes: impl IntoIterator<Item = (u64, u8, u8)>,
+    frame_rate: u16,
+) -> Result<(), S::Error> {
+    let mut header = [0; 16];
+    header[..4].copy_from_slice(b"PSG\x1A");
+    // Version 1.0, whose header carries the frame rate
+    header[4] = 0x10;
+    header[5] = frame_rate.min(u8::MAX as u16) as u8;
+    out.write_all(&header)?;
+
+    let mut frame = 0;
+    for (index, register, value) in frame_indices(writes, frame_rate) {
+        let mut skip = index.saturating_sub(frame);
+        // 0xFE n waits 4 * n frames, 0xFF a single one
+        while skip >= 4 {
+            let n = (skip / 4).min(u8::MAX as usize);
+            out.write_all(&[0xFE, n as u8])?;
+            skip -= 4 * n;
+        }
+        for _ in 0..skip {
+            out.write_all(&[0xFF])?;
+        }
+        frame = frame.max(index);
+
+        if register < 16 {
+            out.write_all(&[register, value])?;
+        }
+    }
+    // End of the last frame, then end of music
+    out.write_all(&[0xFF, 0xFD])
+}
+
+/// Write captured register writes as an uncompressed, interleaved YM5 file.
+///
+/// The file is written column by column, so `writes` are gone through once per register
+/// and once more to count the frames, without keeping the frames in memory.
+///
+/// Many players expect `.ym` files to be LHA-compressed, run the result through
+/// `lha a -h0` if yours does.
+pub fn write_ym<S: ExportSink>(
+    out: &mut S,
+    writes: impl IntoIterator<Item = (u64, u8, u8)> + Clone,
+    info: &YmInfo,
+) -> Result<(), S::Error> {
+    let frame_count = frames(writes.clone(), info.frame_rate).count();
+
+    out.write_all(b"YM5!LeOnArD!")?;
+    out.write_all(&(frame_count as u32).to_be_bytes())?;
+    // Attributes: interleaved
+    out.write_all(&1u32.to_be_bytes())?;
+    // No digidrums
+    out.write_all(&0u16.to_be_bytes())?;
+    out.write_all(&info.master_clock_frequency.to_be_bytes())?;
+    out.write_all(&info.frame_rate.to_be_bytes())?;
+    // Loop frame
+    out.write_all(&0u32.to_be_bytes())?;
+    // No additional data
+    out.write_all(&0u16.to_be_bytes())?;
+    for text in [info.title, info.author, info.comment] {
+        out.write_all(text.as_bytes())?;
+        out.write_all(&[0])?;
+    }
+
+    // Interleaved: all the R0 values, then all the R1 values...
+    for register in 0..16 {
+        for frame in frames(writes.clone(), info.frame_rate) {
+            let value = match register {
+                // 0xFF means "leave the envelope alone"
+                13 if !frame.retrigger => 0xFF,
+                r => frame.registers.get(r).copied().unwrap_or(0),
+            };
+            out.write_all(&[value])?;
+        }
+    }
+    out.write_all(b"End!")
+}
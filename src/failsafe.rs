@@ -0,0 +1,115 @@
+//! Watchdog-style protection against stuck notes.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use embedded_hal::digital::OutputPin;
+
+use crate::{OutputBus, SharedChip, YM2149};
+
+/// Mutes the chip when whatever is supposed to drive it stops doing so.
+///
+/// The player task calls [feed](AudioFailsafe::feed) every time it does its job, and a
+/// timer interrupt calls [check](AudioFailsafe::check). If `check` runs `window` times in a row
+/// without a `feed` in between (e.g. the player task died), every channel is
+/// [silenced](YM2149::silence). Feeding again re-arms it.
+///
+/// Only atomic loads and stores are used (the M0+ has no compare-and-swap), so the
+/// failsafe can live in a `static` shared between the task and the interrupt. The
+/// interrupt reaches the chip through a [SharedChip], with
+/// [check_shared](AudioFailsafe::check_shared):
+/// ```no_run
+/// // With a 100 Hz timer interrupt, mute after 250 ms without a feed
+/// static FAILSAFE: AudioFailsafe = AudioFailsafe::new(25);
+/// static CHIP: SharedChip<DataBus<DynOutputPin>, Bc1Pin, BdirPin> = SharedChip::new();
+///
+/// // Player loop
+/// loop {
+///     CHIP.lock(|chip| play_next_frame(chip));
+///     FAILSAFE.feed();
+/// }
+///
+/// // Timer interrupt
+/// #[interrupt]
+/// fn TIMER_IRQ_0() {
+///     FAILSAFE.check_shared(&CHIP);
+/// }
+/// ```
+pub struct AudioFailsafe {
+    window: u32,
+    elapsed: AtomicU32,
+    tripped: AtomicBool,
+}
+
+impl AudioFailsafe {
+    /// Create a failsafe tripping after `window` calls to [check](AudioFailsafe::check) without a feed.
+    pub const fn new(window: u32) -> Self {
+        Self {
+            window,
+            elapsed: AtomicU32::new(0),
+            tripped: AtomicBool::new(false),
+        }
+    }
+
+    /// Signal that the chip is still being driven.
+    pub fn feed(&self) {
+        self.elapsed.store(0, Ordering::Relaxed);
+        self.tripped.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether the failsafe muted the chip since the last feed.
+    pub fn tripped(&self) -> bool {
+        self.tripped.load(Ordering::Relaxed)
+    }
+
+    /// Count one period, muting the chip once the window is over.
+    ///
+    /// Returns `true` when this call tripped the failsafe.
+    ///
+    /// For a chip owned by the caller. From an interrupt, use
+    /// [check_shared](AudioFailsafe::check_shared).
+    pub fn check<DATABUS, BC1, BDIR>(&self, chip: &mut YM2149<DATABUS, BC1, BDIR>) -> bool
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        if !self.expired() {
+            return false;
+        }
+        chip.silence();
+        self.tripped.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Like [check](AudioFailsafe::check), for a chip in a [SharedChip], so it can be
+    /// called from an interrupt. The chip is only [locked](SharedChip::lock) to mute it.
+    ///
+    /// If the chip can't be locked (not [initialized](SharedChip::init) yet, or already
+    /// locked further up the stack), the failsafe stays armed and tries again on the
+    /// next check.
+    pub fn check_shared<DATABUS, BC1, BDIR>(&self, chip: &SharedChip<DATABUS, BC1, BDIR>) -> bool
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        if !self.expired() || chip.lock(YM2149::silence).is_none() {
+            return false;
+        }
+        self.tripped.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Count one period, `true` if the window is over and the chip should be muted.
+    fn expired(&self) -> bool {
+        if self.tripped() {
+            return false;
+        }
+
+        // Not a true read-modify-write: a feed landing in between is
+        // only noticed on the next check, which is fine here.
+        let elapsed = self.elapsed.load(Ordering::Relaxed).saturating_add(1);
+        self.elapsed.store(elapsed, Ordering::Relaxed);
+        elapsed >= self.window
+    }
+}
@@ -0,0 +1,251 @@
+//! Frames: the state of the sound registers at one point of a song, as found in
+//! YM / PSG dumps, and the writes needed to go from one frame to the next.
+
+use crate::{AudioChannel, Register};
+
+/// Number of registers in a [Frame], R0 to R13 (the I/O ports aren't part of the sound).
+pub const FRAME_REGISTERS: usize = 14;
+
+/// Index of the mixer register in a [Frame].
+pub(crate) const MIXER: usize = Register::IoPortMixerSettings as usize;
+
+/// The sound registers (R0 - R13) at one point in time.
+///
+/// Writing [R13](Register::EShape) restarts the envelope, even with the same value, so
+/// it's only written when `retrigger` is set. Set it to change the shape, too.
+///
+/// Example:
+/// ```no_run
+/// let mut frame = chip.frame();
+/// frame.registers[Register::ALevel as usize] = 0x0F;
+/// chip.apply_frame(&frame); // Only writes R8
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub registers: [u8; FRAME_REGISTERS],
+    /// Write R13, restarting the envelope
+    pub retrigger: bool,
+}
+
+impl Frame {
+    pub const fn new(registers: [u8; FRAME_REGISTERS], retrigger: bool) -> Self {
+        Self {
+            registers,
+            retrigger,
+        }
+    }
+
+    /// The same frame with the registers of each channel `n` moved to channel `map[n]`.
+    ///
+    /// `map` must be a permutation of the 3 channels.
+    pub fn remap(&self, map: [AudioChannel; 3]) -> Frame {
+        let mut remapped = *self;
+        // I/O direction bits stay where they are
+        remapped.registers[MIXER] &= 0xC0;
+
+        for (from, to) in AudioChannel::ALL.into_iter().zip(map) {
+            let (from, to) = (from as usize, to as usize);
+            remapped.set_register(2 * to, self.register(2 * from));
+            remapped.set_register(2 * to + 1, self.register(2 * from + 1));
+            remapped.set_register(
+                Register::ALevel as usize + to,
+                self.register(Register::ALevel as usize + from),
+            );
+
+            let tone = (self.registers[MIXER] >> from) & 1;
+            let noise = (self.registers[MIXER] >> (from + 3)) & 1;
+            remapped.registers[MIXER] |= (tone << to) | (noise << (to + 3));
+        }
+        remapped
+    }
+
+    /// The value of register `r`, 0 past R13.
+    pub(crate) fn register(&self, r: usize) -> u8 {
+        self.registers.get(r).copied().unwrap_or(0)
+    }
+
+    /// Set register `r`, ignored past R13.
+    pub(crate) fn set_register(&mut self, r: usize, value: u8) {
+        if let Some(register) = self.registers.get_mut(r) {
+            *register = value;
+        }
+    }
+
+    /// The register writes turning `prev` into `next`, in register order.
+    ///
+    /// Registers that didn't change are skipped, R13 is only included if `next` asks
+    /// for a [retrigger](Frame::retrigger).
+    pub fn diff<'a>(prev: &'a Frame, next: &'a Frame) -> impl Iterator<Item = (Register, u8)> + 'a {
+        let shape = next.register(Register::EShape as usize);
+        Register::ALL
+            .into_iter()
+            .zip(prev.registers.iter().zip(&next.registers))
+            .filter(|&(r, (prev, next))| r != Register::EShape && prev != next)
+            .map(|(r, (_, &value))| (r, value))
+            .chain(next.retrigger.then_some((Register::EShape, shape)))
+    }
+}
+
+/// How a [Downmixer] picks, for each channel, which of two chips gets to play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Downmix {
+    /// Keep the first chip, drop the second one
+    #[default]
+    Drop,
+    /// The louder of the two channels plays, envelope-driven ones counting as loudest
+    Loudest,
+    /// Switch between the chips every frame, so both are heard (with some buzz)
+    Alternate,
+}
+
+/// Folds the frames of two chips (e.g. TurboSound songs) into one, for boards with a single chip.
+///
+/// Each channel is taken whole (tone, level and mixer bits) from one of the chips, following
+/// the [Downmix] policy. The shared noise and envelope settings come from the first chip,
+/// unless only channels taken from the second one use them.
+///
+/// Example:
+/// ```no_run
+/// let mut downmixer = Downmixer::new(Downmix::Loudest);
+///
+/// // Every frame
+/// let (first, second) = song.next_turbosound_frame();
+/// chip.apply_frame(&downmixer.mix(&first, &second));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Downmixer {
+    policy: Downmix,
+    /// Frames mixed so far, for [Downmix::Alternate]
+    count: u32,
+}
+
+impl Downmixer {
+    pub const fn new(policy: Downmix) -> Self {
+        Self { policy, count: 0 }
+    }
+
+    /// Mix one frame of each chip into one.
+    pub fn mix(&mut self, first: &Frame, second: &Frame) -> Frame {
+        let level = |frame: &Frame, channel: usize| {
+            let level = frame.register(Register::ALevel as usize + channel);
+            if level & 0x10 != 0 {
+                16
+            } else {
+                level & 0x0F
+            }
+        };
+        let uses_noise =
+            |frame: &Frame, channel: usize| frame.registers[MIXER] & (1 << (channel + 3)) == 0;
+        let uses_envelope = |frame: &Frame, channel: usize| level(frame, channel) == 16;
+
+        let mut mixed = *first;
+        mixed.registers[MIXER] &= 0xC0;
+        let (mut noise_from_first, mut noise_from_second) = (false, false);
+        let (mut envelope_from_first, mut envelope_from_second) = (false, false);
+
+        for channel in 0..3 {
+            let take_second = match self.policy {
+                Downmix::Drop => false,
+                Downmix::Loudest => level(second, channel) > level(first, channel),
+                Downmix::Alternate => self.count % 2 == 1,
+            };
+            let source = if take_second { second } else { first };
+
+            for r in [
+                2 * channel,
+                2 * channel + 1,
+                Register::ALevel as usize + channel,
+            ] {
+                mixed.set_register(r, source.register(r));
+            }
+            mixed.registers[MIXER] |=
+                source.registers[MIXER] & ((1 << channel) | (1 << (channel + 3)));
+
+            if take_second {
+                noise_from_second |= uses_noise(source, channel);
+                envelope_from_second |= uses_envelope(source, channel);
+            } else {
+                noise_from_first |= uses_noise(source, channel);
+                envelope_from_first |= uses_envelope(source, channel);
+            }
+        }
+
+        if noise_from_second && !noise_from_first {
+            let noise = Register::NoiseFreq5bit as usize;
+            mixed.set_register(noise, second.register(noise));
+        }
+        if envelope_from_second && !envelope_from_first {
+            for r in Register::EFreq8bitFineAdj as usize..FRAME_REGISTERS {
+                mixed.set_register(r, second.register(r));
+            }
+            mixed.retrigger = second.retrigger;
+        }
+
+        self.count = self.count.wrapping_add(1);
+        mixed
+    }
+}
+
+/// Plays one chip's frames in stereo on two chips, one per side, by attenuating each
+/// channel differently on the left and right chips.
+///
+/// Attenuations are in level steps (about 3 dB each). A channel attenuated by 15 or more is
+/// silent on that side. Envelope-driven channels can't be attenuated, so they play at full
+/// level on any side where they aren't silent.
+///
+/// Example:
+/// ```no_run
+/// // Atari ST style, A left, B center, C right
+/// let stereo = Stereo::ABC;
+///
+/// // Every frame
+/// let (left, right) = stereo.split(&song.next_frame());
+/// left_chip.apply_frame(&left);
+/// right_chip.apply_frame(&right);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stereo {
+    left: [u8; 3],
+    right: [u8; 3],
+}
+
+impl Stereo {
+    /// A left, B center, C right.
+    pub const ABC: Stereo = Stereo::new([0, 1, 15], [15, 1, 0]);
+    /// A left, C center, B right.
+    pub const ACB: Stereo = Stereo::new([0, 15, 1], [15, 0, 1]);
+    /// Every channel on both sides.
+    pub const MONO: Stereo = Stereo::new([0; 3], [0; 3]);
+
+    /// Attenuation of channels A, B and C on each side, in level steps.
+    pub const fn new(left: [u8; 3], right: [u8; 3]) -> Self {
+        Self { left, right }
+    }
+
+    /// The frames for the left and right chips.
+    pub fn split(&self, frame: &Frame) -> (Frame, Frame) {
+        (attenuate(frame, self.left), attenuate(frame, self.right))
+    }
+}
+
+/// The frame with the level of each channel lowered by `attenuation` steps.
+pub(crate) fn attenuate(frame: &Frame, attenuation: [u8; 3]) -> Frame {
+    let mut attenuated = *frame;
+    let levels = attenuated
+        .registers
+        .iter_mut()
+        .skip(Register::ALevel as usize);
+    for (level, attenuation) in levels.zip(attenuation) {
+        *level = attenuate_level(*level, attenuation);
+    }
+    attenuated
+}
+
+/// A level register (R8 - R10) lowered by `attenuation` steps.
+pub(crate) const fn attenuate_level(level: u8, attenuation: u8) -> u8 {
+    match level {
+        _ if attenuation >= 15 => 0,
+        level if level & 0x10 != 0 => level,
+        level => (level & 0x0F).saturating_sub(attenuation),
+    }
+}
@@ -0,0 +1,162 @@
+//! Endless generative sequences for installations: random walks over a scale, played
+//! on probability-weighted rhythms, from a seedable PRNG.
+
+use rand_core::{impls, RngCore};
+
+use crate::note::{Note, Tuning};
+
+/// Small seedable pseudo-random generator (xorshift32), the default random source.
+///
+/// Not suitable for anything security related, but cheap and good enough for music.
+/// The same seed always gives the same sequence. Anything implementing [RngCore] can be
+/// used instead, e.g. the RP2040's ring oscillator for output that differs on every boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng(u32);
+
+impl Rng {
+    /// A generator starting from `seed`. A seed of 0 is replaced, as xorshift would stay stuck on it.
+    pub const fn new(seed: u32) -> Self {
+        Self(if seed == 0 { 0x9E37_79B9 } else { seed })
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    /// A number in `0..n`, 0 if `n` is 0.
+    pub fn below(&mut self, n: u32) -> u32 {
+        below(self, n)
+    }
+
+    /// `true` with a probability of `p`/256 (255 being "almost always").
+    pub fn chance(&mut self, p: u8) -> bool {
+        chance(self, p)
+    }
+}
+
+impl RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        Rng::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// A number in `0..n` from any random source, 0 if `n` is 0.
+pub(crate) fn below<R: RngCore>(rng: &mut R, n: u32) -> u32 {
+    ((rng.next_u32() as u64 * n as u64) >> 32) as u32
+}
+
+/// `true` with a probability of `p`/256, from any random source.
+pub(crate) fn chance<R: RngCore>(rng: &mut R, p: u8) -> bool {
+    (rng.next_u32() >> 24) < p as u32
+}
+
+/// A melody wandering up and down the degrees of a [Tuning], one step at a time.
+///
+/// Each call to [step](RandomWalk::step) is one rhythmic step: the rhythm gives the
+/// probability (out of 256) of a note starting on each step, and loops. Randomness comes
+/// from an [Rng] by default, or any [RngCore].
+///
+/// Example:
+/// ```no_run
+/// const PENTATONIC: [u16; 5] = [200, 400, 700, 900, 1200];
+/// const RHYTHM: [u8; 8] = [255, 0, 96, 0, 192, 0, 64, 32];
+///
+/// let mut melody = RandomWalk::new(Tuning::Table(&PENTATONIC), "D4".parse()?, Rng::new(seed))
+///     .with_range(-5, 10)
+///     .with_rhythm(&RHYTHM);
+/// chip.set_vibrato(AudioChannel::A, 8, 6);
+///
+/// // Every 8th note
+/// if let Some(note) = melody.step() {
+///     chip.play_note(AudioChannel::A, note);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RandomWalk<'a, R = Rng> {
+    tuning: Tuning<'a>,
+    root: Note,
+    rng: R,
+    lowest: i32,
+    highest: i32,
+    max_leap: u8,
+    rhythm: &'a [u8],
+    degree: i32,
+    position: usize,
+}
+
+impl<'a, R: RngCore> RandomWalk<'a, R> {
+    /// A walk starting on `root`, within an octave of it (for 12 steps per octave), moving
+    /// at most 2 degrees at a time and playing a note on every step.
+    pub fn new(tuning: Tuning<'a>, root: Note, rng: R) -> Self {
+        Self {
+            tuning,
+            root,
+            rng,
+            lowest: -12,
+            highest: 12,
+            max_leap: 2,
+            rhythm: &[255],
+            degree: 0,
+            position: 0,
+        }
+    }
+
+    /// Keep the walk between two degrees of the tuning, relative to the root.
+    pub fn with_range(mut self, lowest: i32, highest: i32) -> Self {
+        self.lowest = lowest.min(highest);
+        self.highest = lowest.max(highest);
+        self.degree = self.degree.clamp(self.lowest, self.highest);
+        self
+    }
+
+    /// Set the largest move between two notes, in degrees.
+    pub fn with_max_leap(mut self, degrees: u8) -> Self {
+        self.max_leap = degrees;
+        self
+    }
+
+    /// Set the probability (out of 256) of a note on each step. An empty rhythm never plays.
+    pub fn with_rhythm(mut self, rhythm: &'a [u8]) -> Self {
+        self.rhythm = rhythm;
+        self.position = 0;
+        self
+    }
+
+    /// Advance by one rhythmic step, returning the note to start, if any.
+    pub fn step(&mut self) -> Option<Note> {
+        let probability = *self.rhythm.get(self.position)?;
+        self.position = (self.position + 1) % self.rhythm.len();
+        if !chance(&mut self.rng, probability) {
+            return None;
+        }
+
+        let span = 2 * self.max_leap as u32 + 1;
+        let leap = below(&mut self.rng, span) as i32 - self.max_leap as i32;
+        // Bounce off the ends of the range rather than sticking to them
+        let mut degree = self.degree + leap;
+        if degree < self.lowest || degree > self.highest {
+            degree = self.degree - leap;
+        }
+        self.degree = degree.clamp(self.lowest, self.highest);
+
+        Some(self.tuning.note(self.root, self.degree))
+    }
+}
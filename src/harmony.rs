@@ -0,0 +1,238 @@
+//! Scales and harmony on MIDI note numbers, for playing chords from single notes and
+//! keeping live input in key.
+
+/// A musical scale: a tonic and the semitones of each degree above it.
+///
+/// Example:
+/// ```no_run
+/// // D dorian
+/// let scale = Scale::new(2, Scale::DORIAN);
+/// assert!(scale.contains(71)); // B4
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scale<'a> {
+    tonic: u8,
+    steps: &'a [u8],
+}
+
+impl<'a> Scale<'a> {
+    pub const MAJOR: &'static [u8] = &[0, 2, 4, 5, 7, 9, 11];
+    pub const NATURAL_MINOR: &'static [u8] = &[0, 2, 3, 5, 7, 8, 10];
+    pub const HARMONIC_MINOR: &'static [u8] = &[0, 2, 3, 5, 7, 8, 11];
+    pub const DORIAN: &'static [u8] = &[0, 2, 3, 5, 7, 9, 10];
+    pub const MAJOR_PENTATONIC: &'static [u8] = &[0, 2, 4, 7, 9];
+    pub const MINOR_PENTATONIC: &'static [u8] = &[0, 3, 5, 7, 10];
+
+    /// A scale on `tonic` (pitch class, 0 for C to 11 for B), with `steps` in semitones
+    /// above it, in increasing order within an octave.
+    pub const fn new(tonic: u8, steps: &'a [u8]) -> Self {
+        Self {
+            tonic: tonic % 12,
+            steps,
+        }
+    }
+
+    /// A major scale on `tonic`.
+    pub const fn major(tonic: u8) -> Self {
+        Self::new(tonic, Self::MAJOR)
+    }
+
+    /// A natural minor scale on `tonic`.
+    pub const fn minor(tonic: u8) -> Self {
+        Self::new(tonic, Self::NATURAL_MINOR)
+    }
+
+    /// Whether a MIDI note is in the scale.
+    pub fn contains(&self, key: u8) -> bool {
+        let class = (key as i32 - self.tonic as i32).rem_euclid(12);
+        self.steps.iter().any(|&step| step as i32 == class)
+    }
+
+    /// The degree of the highest note of the scale at or below a MIDI note, counted from
+    /// the tonic below MIDI note 0. 0 for an empty scale.
+    pub fn degree(&self, key: u8) -> i32 {
+        let len = self.steps.len() as i32;
+        if len == 0 {
+            return 0;
+        }
+        let relative = key as i32 - self.tonic as i32;
+        let octave = relative.div_euclid(12);
+        let class = relative.rem_euclid(12);
+        match self.steps.iter().rposition(|&step| step as i32 <= class) {
+            Some(index) => octave * len + index as i32,
+            // Below the first step, so the last one of the octave below
+            None => octave * len - 1,
+        }
+    }
+
+    /// The MIDI note of a degree, `None` if it's out of the MIDI range or the scale is empty.
+    pub fn key(&self, degree: i32) -> Option<u8> {
+        let len = self.steps.len() as i32;
+        if len == 0 {
+            return None;
+        }
+        let step = *self.steps.get(degree.rem_euclid(len) as usize)? as i32;
+        let key = self.tonic as i32 + degree.div_euclid(len) * 12 + step;
+        u8::try_from(key).ok().filter(|&key| key <= 127)
+    }
+}
+
+/// Expands single notes into chords following a [Scale] (diatonic harmonization), for
+/// one-finger playing.
+///
+/// Each harmony note is a number of scale degrees above (or below) the played note, so
+/// chords change quality along the scale like they would on paper: in C major, C gives
+/// C major, D gives D minor, B gives B diminished.
+///
+/// Example:
+/// ```no_run
+/// let harmonizer = Harmonizer::new(Scale::major(0), Harmonizer::TRIAD);
+/// let mut voices = VoiceAllocator::new(StealPolicy::Oldest);
+///
+/// // MIDI note-on
+/// for key in harmonizer.harmonize(key) {
+///     if let Some(allocation) = voices.note_on(key) {
+///         chip.play_note(allocation.channel(), Note::from_midi(key));
+///     }
+/// }
+/// // MIDI note-off
+/// for key in harmonizer.harmonize(key) {
+///     if let Some(voice) = voices.note_off(key) {
+///         chip.volume(voice.channel(), Level::OFF);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Harmonizer<'a> {
+    scale: Scale<'a>,
+    intervals: &'a [i8],
+}
+
+impl<'a> Harmonizer<'a> {
+    /// Third and fifth above the note.
+    pub const TRIAD: &'static [i8] = &[2, 4];
+    /// Third, fifth and seventh above the note.
+    pub const SEVENTH: &'static [i8] = &[2, 4, 6];
+    /// Third below the note, a classic second voice.
+    pub const THIRD_BELOW: &'static [i8] = &[-2];
+
+    /// A harmonizer adding, to each note, the notes `intervals` scale degrees away from it.
+    pub const fn new(scale: Scale<'a>, intervals: &'a [i8]) -> Self {
+        Self { scale, intervals }
+    }
+
+    pub fn scale(&self) -> Scale<'a> {
+        self.scale
+    }
+
+    /// Change the key, e.g. on a chord change.
+    pub fn set_scale(&mut self, scale: Scale<'a>) {
+        self.scale = scale;
+    }
+
+    /// The played note followed by its harmony notes, as MIDI notes.
+    ///
+    /// Notes out of the scale are harmonized as the scale note just below them. Harmony
+    /// notes out of the MIDI range are left out.
+    pub fn harmonize(&self, key: u8) -> impl Iterator<Item = u8> + 'a {
+        let scale = self.scale;
+        let degree = scale.degree(key);
+        core::iter::once(key).chain(
+            self.intervals
+                .iter()
+                .filter_map(move |&interval| scale.key(degree + interval as i32)),
+        )
+    }
+}
+
+/// Snaps incoming pitches (a pot, a pitch CV, a generative engine) to the nearest note
+/// of a [Scale].
+///
+/// Pitches are in cents above MIDI note 0, i.e. a MIDI note number times 100
+/// (`Note::cents_from_a4() + 6900`). Once on a note, the pitch has to go `hysteresis`
+/// cents past the halfway point to the next one before the quantizer switches to it,
+/// so a noisy input sitting on a boundary doesn't flap between two notes.
+///
+/// Example:
+/// ```no_run
+/// let mut quantizer = ScaleQuantizer::new(Scale::new(9, Scale::MINOR_PENTATONIC), 15);
+///
+/// // Pot on an ADC, 12 bits, over 4 octaves from C2
+/// let pitch = 3600 + adc.read(&mut pot)? as i32 * 4800 / 4096;
+/// let key = quantizer.quantize(pitch);
+/// if quantizer.changed() {
+///     chip.play_note(AudioChannel::A, Note::from_midi(key));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleQuantizer<'a> {
+    scale: Scale<'a>,
+    hysteresis: u16,
+    current: Option<u8>,
+    changed: bool,
+}
+
+impl<'a> ScaleQuantizer<'a> {
+    pub const fn new(scale: Scale<'a>, hysteresis: u16) -> Self {
+        Self {
+            scale,
+            hysteresis,
+            current: None,
+            changed: false,
+        }
+    }
+
+    /// Change the scale. The next pitch snaps to it without hysteresis.
+    pub fn set_scale(&mut self, scale: Scale<'a>) {
+        self.scale = scale;
+        self.current = None;
+    }
+
+    /// The MIDI note of the scale for a pitch, in cents above MIDI note 0.
+    ///
+    /// Pitches out of the MIDI range are clamped to it. With an empty scale, the
+    /// pitch is rounded to the nearest semitone.
+    pub fn quantize(&mut self, pitch: i32) -> u8 {
+        let pitch = pitch.clamp(0, 12700);
+        // Either may be out of the MIDI range, e.g. under the lowest note of the scale
+        let degree = self.scale.degree((pitch / 100) as u8);
+        let below = self.scale.key(degree);
+        let above = self.scale.key(degree + 1);
+        let nearest = match (below, above) {
+            (Some(below), Some(above)) => {
+                if pitch - below as i32 * 100 <= above as i32 * 100 - pitch {
+                    below
+                } else {
+                    above
+                }
+            }
+            (Some(key), None) | (None, Some(key)) => key,
+            (None, None) => ((pitch + 50) / 100).min(127) as u8,
+        };
+
+        let key = match self.current {
+            Some(current) if current != nearest => {
+                let halfway = (current as i32 + nearest as i32) * 50;
+                if (pitch - halfway).abs() > self.hysteresis as i32 {
+                    nearest
+                } else {
+                    current
+                }
+            }
+            _ => nearest,
+        };
+        self.changed = self.current != Some(key);
+        self.current = Some(key);
+        key
+    }
+
+    /// Whether the last [quantize](ScaleQuantizer::quantize) moved to another note.
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// The note the quantizer is on, if any pitch came in yet.
+    pub fn current(&self) -> Option<u8> {
+        self.current
+    }
+}
@@ -0,0 +1,177 @@
+//! Control surfaces: rotary encoders and buttons mapped to the chip and to the player,
+//! whatever reads them.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::{AudioChannel, OutputBus, YM2149};
+
+/// A parameter of `T` moved by an encoder or a pair of up/down buttons, e.g. the volume
+/// of the chip or the tempo of a player.
+///
+/// The application decodes its own hardware into detents and presses, and hands them to
+/// whichever parameter is selected, without knowing what it drives.
+///
+/// Example:
+/// ```no_run
+/// let mut volume = Volume;
+/// let mut mute = Mute(AudioChannel::B);
+///
+/// // When the encoder moves or its switch is pressed
+/// volume.turn(&mut chip, encoder.take_detents());
+/// if button.was_pressed() {
+///     mute.press(&mut chip);
+/// }
+/// ```
+pub trait ParamControl<T: ?Sized> {
+    /// Move the parameter by `steps` detents or presses, up if positive.
+    fn turn(&mut self, target: &mut T, steps: i8);
+
+    /// The encoder's push switch or a dedicated button, if the parameter has a use for it.
+    fn press(&mut self, _target: &mut T) {}
+}
+
+/// What a control surface needs from a player: song selection, play/stop and tempo.
+///
+/// Implemented by the application's player, so [SongSelect] and [Tempo] can drive it
+/// like any other [ParamControl].
+pub trait TransportControl {
+    /// Start playing if stopped, stop if playing.
+    fn toggle_play(&mut self);
+
+    /// Move `offset` songs forward (backward if negative).
+    fn select_song(&mut self, offset: i8);
+
+    /// Change the tempo by `steps`, in whatever unit the player uses.
+    fn nudge_tempo(&mut self, steps: i8);
+}
+
+/// The chip's volume, as its [master attenuation](YM2149::set_master_attenuation): one
+/// level step (about 3 dB) per detent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Volume;
+
+impl<DATABUS, BC1, BDIR> ParamControl<YM2149<DATABUS, BC1, BDIR>> for Volume
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    fn turn(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>, steps: i8) {
+        let attenuation = chip
+            .master_attenuation()
+            .saturating_add_signed(steps.saturating_neg());
+        chip.set_master_attenuation(attenuation);
+    }
+}
+
+/// Mutes a channel of the chip, see [YM2149::set_muted].
+///
+/// A press toggles it. Turning up unmutes, turning down mutes, like a switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mute(pub AudioChannel);
+
+impl<DATABUS, BC1, BDIR> ParamControl<YM2149<DATABUS, BC1, BDIR>> for Mute
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    fn turn(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>, steps: i8) {
+        if steps != 0 {
+            chip.set_muted(self.0, steps < 0);
+        }
+    }
+
+    fn press(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>) {
+        chip.set_muted(self.0, !chip.is_muted(self.0));
+    }
+}
+
+/// Transposes every channel of the chip, one semitone per detent, see
+/// [YM2149::set_transpose]. A press goes back to 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transpose {
+    semitones: i8,
+    range: i8,
+}
+
+impl Transpose {
+    /// Transposition up or down to `range` semitones.
+    pub const fn new(range: i8) -> Self {
+        Self {
+            semitones: 0,
+            range: range.saturating_abs(),
+        }
+    }
+
+    /// The current transposition, in semitones.
+    pub const fn semitones(&self) -> i8 {
+        self.semitones
+    }
+
+    fn set<DATABUS, BC1, BDIR>(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>, semitones: i8)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        self.semitones = semitones.clamp(-self.range, self.range);
+        for channel in AudioChannel::ALL {
+            chip.set_transpose(channel, self.semitones);
+        }
+    }
+}
+
+impl Default for Transpose {
+    /// Up to an octave either way.
+    fn default() -> Self {
+        Self::new(12)
+    }
+}
+
+impl<DATABUS, BC1, BDIR> ParamControl<YM2149<DATABUS, BC1, BDIR>> for Transpose
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    fn turn(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>, steps: i8) {
+        self.set(chip, self.semitones.saturating_add(steps));
+    }
+
+    fn press(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>) {
+        self.set(chip, 0);
+    }
+}
+
+/// Picks the song of a [TransportControl], one per detent. A press plays or stops it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SongSelect;
+
+impl<T: TransportControl + ?Sized> ParamControl<T> for SongSelect {
+    fn turn(&mut self, player: &mut T, steps: i8) {
+        if steps != 0 {
+            player.select_song(steps);
+        }
+    }
+
+    fn press(&mut self, player: &mut T) {
+        player.toggle_play();
+    }
+}
+
+/// The tempo of a [TransportControl]. A press plays or stops it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tempo;
+
+impl<T: TransportControl + ?Sized> ParamControl<T> for Tempo {
+    fn turn(&mut self, player: &mut T, steps: i8) {
+        if steps != 0 {
+            player.nudge_tempo(steps);
+        }
+    }
+
+    fn press(&mut self, player: &mut T) {
+        player.toggle_play();
+    }
+}
@@ -0,0 +1,118 @@
+//! Ring buffer of the last register writes, for post-mortem debugging.
+//!
+//! Only available with the `journal` feature, since it costs [JOURNAL_LEN] entries of RAM.
+//!
+//! Every register write made by [YM2149](crate::YM2149) is recorded, with a timestamp
+//! taken from the clock set by [set_clock]. When the chip ends up stuck on a loud note
+//! after a fault, [dump] the journal from your panic handler to see what happened:
+//! ```no_run
+//! ym2149::journal::set_clock(|| timer_ticks());
+//!
+//! #[panic_handler]
+//! fn panic(_info: &core::panic::PanicInfo) -> ! {
+//!     ym2149::journal::dump();
+//!     loop {}
+//! }
+//! ```
+
+use core::cell::{Cell, RefCell};
+
+use cortex_m::interrupt::{self, Mutex};
+
+/// Number of writes kept in the journal.
+pub const JOURNAL_LEN: usize = 32;
+
+/// A single recorded register write.
+#[derive(Debug, Clone, Copy, Default, defmt::Format)]
+pub struct Entry {
+    pub timestamp: u32,
+    pub register: u8,
+    pub value: u8,
+}
+
+struct Journal {
+    entries: [Entry; JOURNAL_LEN],
+    /// Index the next entry will be written to
+    next: usize,
+    len: usize,
+}
+
+static JOURNAL: Mutex<RefCell<Journal>> = Mutex::new(RefCell::new(Journal {
+    entries: [Entry {
+        timestamp: 0,
+        register: 0,
+        value: 0,
+    }; JOURNAL_LEN],
+    next: 0,
+    len: 0,
+}));
+
+/// Source of the journal's timestamps.
+type Clock = fn() -> u32;
+
+static CLOCK: Mutex<Cell<Option<Clock>>> = Mutex::new(Cell::new(None));
+
+/// Set the function timestamps are read from (e.g. a timer's tick counter).
+///
+/// Until it's set, all timestamps are `0`.
+pub fn set_clock(clock: Clock) {
+    interrupt::free(|cs| CLOCK.borrow(cs).set(Some(clock)));
+}
+
+pub(crate) fn record(register: u8, value: u8) {
+    interrupt::free(|cs| {
+        let timestamp = CLOCK.borrow(cs).get().map_or(0, |clock| clock());
+        // Skip the entry rather than panic if we're somehow re-entered
+        if let Ok(mut journal) = JOURNAL.borrow(cs).try_borrow_mut() {
+            let next = journal.next;
+            if let Some(entry) = journal.entries.get_mut(next) {
+                *entry = Entry {
+                    timestamp,
+                    register,
+                    value,
+                };
+            }
+            journal.next = (next + 1) % JOURNAL_LEN;
+            journal.len = (journal.len + 1).min(JOURNAL_LEN);
+        }
+    });
+}
+
+/// Call `f` on every recorded write, oldest first.
+pub fn for_each(mut f: impl FnMut(&Entry)) {
+    interrupt::free(|cs| {
+        if let Ok(journal) = JOURNAL.borrow(cs).try_borrow() {
+            let start = (journal.next + JOURNAL_LEN - journal.len) % JOURNAL_LEN;
+            journal
+                .entries
+                .iter()
+                .cycle()
+                .skip(start)
+                .take(journal.len)
+                .for_each(&mut f);
+        }
+    });
+}
+
+/// Print the journal over defmt, oldest write first.
+pub fn dump() {
+    defmt::error!("ym2149: last register writes (oldest first)");
+    for_each(|entry| {
+        defmt::error!(
+            "[{=u32}] R{=u8} <- {=u8:#04x}",
+            entry.timestamp,
+            entry.register,
+            entry.value
+        );
+    });
+}
+
+/// Forget every recorded write.
+pub fn clear() {
+    interrupt::free(|cs| {
+        if let Ok(mut journal) = JOURNAL.borrow(cs).try_borrow_mut() {
+            journal.next = 0;
+            journal.len = 0;
+        }
+    });
+}
@@ -20,14 +20,103 @@
 //! See `examples/*.rs` for full usage.
 //!
 //! **When in doubt, check the specsheet!**
+//!
+//! # Concurrency
+//! Apart from the types meant for sharing listed below, the driver and its helpers hold
+//! their state directly, so they're [Send] whenever what they hold is (pins, buses,
+//! closures): a [YM2149] can be moved into an RTIC or Embassy task as is. They aren't
+//! [Sync], as every call needs `&mut`; to drive a chip from several contexts, use one of
+//! these:
+//! - [SharedChip]: the chip in a `static`, in a `RefCell` behind a `cortex_m` critical
+//!   section `Mutex`, for interrupts and panic handlers. Every call runs with interrupts
+//!   off, and a call nested in another one gets `None` instead of a second `&mut`. Its
+//!   one `unsafe` function, [panic_silence](SharedChip::panic_silence), skips that check
+//!   to silence the chip from a panic handler, even if the panic struck mid-call: that's
+//!   only sound if the interrupted code never runs again.
+//! - [ChipMonitor]: read-only stats and registers for another core or an interrupt,
+//!   published by the side driving the chip. Lock-free, never blocks the publisher, and
+//!   reads give up with `None` rather than wait on a publish they interrupted.
+//! - [AudioFailsafe]: fed and checked from different contexts with atomic loads and stores.
+//!
+//! The `journal` feature records writes behind a critical section, so it's safe from
+//! interrupts too.
+//!
+//! # Panics
+//! The driver ends up in interrupt handlers, where a panic is a silent lockup. With the
+//! `panic-free` feature, the crate is built with `panic!`, `unwrap` and indexing denied, so
+//! failures come back as [Error]s instead. What's left are the documented checks of
+//! constructors (e.g. [DataBus::with_mapping]), meant to fail at startup.
 #![no_std]
 #![no_main]
+#![cfg_attr(
+    feature = "panic-free",
+    deny(clippy::panic, clippy::unwrap_used, clippy::indexing_slicing)
+)]
 use core::convert::{From, Into};
 
+use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::{OutputPin, PinState};
 use rp2040_hal::gpio::{DynPinId, FunctionSio, Pin, PullDown, SioOutput};
 use PinState::{High, Low};
 
+pub mod backend;
+pub use backend::{MirrorBackend, PsgBackend};
+pub mod bus;
+pub use bus::{Expander, I2cExpanderBus, ShiftRegisterBus, TeeBus, TransceiverBus};
+pub mod control;
+pub use control::{AdcMap, Curve, Ducker, Theremin};
+pub mod diagnostics;
+pub use diagnostics::TestSignal;
+pub mod dispatch;
+pub use dispatch::Dispatcher;
+pub mod dynamics;
+pub use dynamics::VelocityCurve;
+pub mod echo;
+pub use echo::Echo;
+pub mod envelope;
+pub use envelope::{EnvelopeFrequency, EnvelopeShape, ShapeFlags, ShapeValue};
+pub mod export;
+pub mod failsafe;
+pub use failsafe::AudioFailsafe;
+pub mod frame;
+pub use frame::{Downmix, Downmixer, Frame, Stereo};
+pub mod generative;
+pub use generative::{RandomWalk, Rng};
+pub mod harmony;
+pub use harmony::{Harmonizer, Scale, ScaleQuantizer};
+pub mod input;
+pub use input::{Mute, ParamControl, SongSelect, Tempo, TransportControl, Transpose, Volume};
+#[cfg(feature = "journal")]
+pub mod journal;
+pub mod modulation;
+pub use modulation::{Destination, ModMatrix, Modulation, Modulator, Route, Source};
+pub mod monitor;
+pub use monitor::ChipMonitor;
+pub mod note;
+pub use note::{Accidental, Note, NoteName, NoteParseError, Tuning};
+pub mod ornament;
+pub use ornament::{Mix, MixerMacro, Ornament, RawEnvelope};
+pub mod regmap;
+pub use regmap::{Field, RegisterInfo};
+pub mod scheduler;
+pub use scheduler::Scheduler;
+pub mod settings;
+pub use settings::{FlashStore, FlashStoreError, Settings, SettingsStore};
+pub mod sfx;
+pub use sfx::{Sfx, SfxManager, Sweep};
+pub mod shared;
+pub use shared::SharedChip;
+pub mod stats;
+pub use stats::{Shedding, Stats};
+pub mod synth;
+pub use synth::{Instrument, MonoSynth, ParaSynth};
+pub mod texture;
+pub use texture::{NoiseModulator, NoiseTexture};
+pub mod trigger;
+pub use trigger::{PortTrigger, TriggerOut};
+pub mod voice;
+pub use voice::{Allocation, Chips, Role, StealPolicy, VoiceAllocator, VoiceId, Voices};
+
 /// Helper trait that lets you configure any sort of output bus.
 /// It abstracts writing 8-bit values to various bus implementations.
 ///
@@ -49,39 +138,193 @@ use PinState::{High, Low};
 ///     }
 /// }
 /// ```
+///
+/// The trait is object safe, and `&mut` references to buses are buses too, so the backend
+/// can be picked at runtime with a single driver behind `&mut dyn OutputBus`:
+/// ```no_run
+/// let mut parallel = ym2149_data_bus;
+/// let mut expander = I2cExpanderBus::new(i2c, Expander::Pcf8574, 0x20)?;
+/// let bus: &mut dyn OutputBus = if jumper.is_low()? {
+///     &mut parallel
+/// } else {
+///     &mut expander
+/// };
+/// let mut chip = YM2149::new(bus, master_clock_freq, bc1, bdir);
+/// ```
 pub trait OutputBus {
     fn write_u8(&mut self, data: u8);
+
+    /// Write a byte, reporting whether it went through (e.g. an I²C expander NAKed).
+    ///
+    /// Buses that can fail should implement this, the driver uses it to leave the chip in
+    /// a known state when a write is lost. Calls [write_u8](OutputBus::write_u8) and
+    /// succeeds by default.
+    fn try_write_u8(&mut self, data: u8) -> Result<(), BusError> {
+        self.write_u8(data);
+        Ok(())
+    }
+
+    /// Called by [YM2149::set_mode] when data starts flowing the other way, for buses that
+    /// need to turn around (e.g. release their pins, or flip a transceiver).
+    ///
+    /// It's called *before* the chip starts driving the bus in [Mode::READ], and *after*
+    /// it stops. Does nothing by default.
+    fn set_direction(&mut self, _direction: BusDirection) {}
+
+    /// Whether the driver may skip the ADDRESS phase when writing to the same register
+    /// twice in a row, relying on the chip's address latch. Halves the traffic of code
+    /// hammering one register (samples, envelope streaming).
+    ///
+    /// Only return `true` if nothing else latches an address on the chip between the
+    /// driver's writes. `false` by default.
+    fn cache_address(&self) -> bool {
+        false
+    }
+}
+
+impl<B: OutputBus + ?Sized> OutputBus for &mut B {
+    fn write_u8(&mut self, data: u8) {
+        (**self).write_u8(data);
+    }
+
+    fn try_write_u8(&mut self, data: u8) -> Result<(), BusError> {
+        (**self).try_write_u8(data)
+    }
+
+    fn set_direction(&mut self, direction: BusDirection) {
+        (**self).set_direction(direction);
+    }
+
+    fn cache_address(&self) -> bool {
+        (**self).cache_address()
+    }
+}
+
+/// A byte didn't make it to the chip, see [OutputBus::try_write_u8].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusError;
+
+/// Which way data flows on the data bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusDirection {
+    /// MCU to chip
+    Write,
+    /// Chip to MCU
+    Read,
 }
 
 /// This struct makes an array of length 8 for any type that implements OutputPin.
 pub struct DataBus<T> {
     pins: [T; 8],
+    cache_address: bool,
 }
 
 impl<T> DataBus<T>
 where
     T: OutputPin,
 {
+    /// Build a bus from pins ordered from D0 (LSB) to D7 (MSB).
     pub fn new(pins: [T; 8]) -> Self {
-        Self { pins }
+        Self {
+            pins,
+            cache_address: false,
+        }
+    }
+
+    /// Build a bus from pins in any order, with `mapping[n]` being the index in `pins`
+    /// of the pin wired to D`n`.
+    ///
+    /// The permutation is resolved once here, so writes cost the same as with [DataBus::new].
+    ///
+    /// Panics if `mapping` isn't a permutation of `0..8`.
+    ///
+    /// Example:
+    /// ```no_run
+    /// // GPIO1..GPIO8 are routed to D7..D0 on the PCB
+    /// let data_bus = DataBus::with_mapping(data_pins, [7, 6, 5, 4, 3, 2, 1, 0]);
+    /// ```
+    pub fn with_mapping(pins: [T; 8], mapping: [u8; 8]) -> Self {
+        let mut pins = pins.map(Some);
+        Self {
+            pins: mapping.map(|index| {
+                pins.get_mut(index as usize)
+                    .and_then(Option::take)
+                    .expect("DataBus mapping must be a permutation of 0..8")
+            }),
+            cache_address: false,
+        }
+    }
+
+    /// Build a bus from pins ordered from D7 (MSB) to D0 (LSB).
+    pub fn reversed(pins: [T; 8]) -> Self {
+        Self::with_mapping(pins, [7, 6, 5, 4, 3, 2, 1, 0])
+    }
+
+    /// Enable or disable [address caching](OutputBus::cache_address) (off by default).
+    pub fn with_address_caching(mut self, enabled: bool) -> Self {
+        self.cache_address = enabled;
+        self
     }
 }
 
+/// Build a [YM2149] on a [DataBus] straight from the fields of `rp2040_hal::gpio::Pins`.
+///
+/// The data pins go from D0 to D7. Mistakes in the pin list are caught at compile time:
+/// anything but exactly 8 data pins fails an assertion, and a pin used twice is a
+/// "use of moved value" error. The bus is zeroed before the chip is built.
+///
+/// Example:
+/// ```no_run
+/// let pins = hal::gpio::Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+///
+/// let mut chip = ym2149_pins!(
+///     pins,
+///     data: [gpio1, gpio2, gpio3, gpio4, gpio5, gpio6, gpio7, gpio8],
+///     bc1: gpio9,
+///     bdir: gpio10,
+///     master_clock: 2_000_000,
+/// );
+/// // The other pins are still available
+/// let mut reset_pin = pins.gpio11.into_push_pull_output();
+/// ```
+#[macro_export]
+macro_rules! ym2149_pins {
+    (
+        $pins:ident,
+        data: [$($data:ident),* $(,)?],
+        bc1: $bc1:ident,
+        bdir: $bdir:ident,
+        master_clock: $master_clock_frequency:expr $(,)?
+    ) => {{
+        const _: () = assert!(
+            [$(stringify!($data)),*].len() == 8,
+            "ym2149_pins! needs exactly 8 data pins, D0 to D7"
+        );
+        let mut data_bus = $crate::DataBus::new([
+            $($pins.$data.into_push_pull_output().into_dyn_pin()),*
+        ]);
+        $crate::OutputBus::write_u8(&mut data_bus, 0);
+
+        $crate::YM2149::new(
+            data_bus,
+            $master_clock_frequency,
+            $pins.$bc1.into_push_pull_output(),
+            $pins.$bdir.into_push_pull_output(),
+        )
+    }};
+}
+
 impl OutputBus for DataBus<Pin<DynPinId, FunctionSio<SioOutput>, PullDown>> {
     fn write_u8(&mut self, data: u8) {
-        for bit in 0..8 {
+        for (bit, pin) in self.pins.iter_mut().enumerate() {
             let state = if (data >> bit) & 1 == 1 { High } else { Low };
-            let _ = self.pins[bit].set_state(state);
+            let _ = pin.set_state(state);
         }
     }
-}
 
-/// An error related to note parsing.
-pub enum NoteParseError {
-    InvalidLength,
-    InvalidAccidental,
-    InvalidNote,
-    OctaveOutOfRange,
+    fn cache_address(&self) -> bool {
+        self.cache_address
+    }
 }
 
 /// A YM2149 chip struct.
@@ -127,6 +370,43 @@ where
     master_clock_frequency: u32,
     bc1: BC1,
     bdir: BDIR,
+    polarity: ControlPolarity,
+    variant: Variant,
+    safe_mode: bool,
+    retrigger_limit: bool,
+    /// Whether R13 was written since the last tick
+    shape_written: bool,
+    /// R13 write held back until the next tick by the retrigger limit
+    pending_shape: Option<u8>,
+    /// Output each logical channel is wired to
+    channel_map: [AudioChannel; 3],
+    /// Tone periods of the semitones, see [note::build_period_table]
+    period_table: Option<&'static [u16; note::PERIOD_TABLE_NOTES]>,
+    /// Last value written to each register
+    registers: [u8; 16],
+    /// Register address currently latched by the chip, if known
+    latched: Option<u8>,
+    /// Registers whose value on the chip is unknown after a failed write, one bit each
+    unknown: u16,
+    notes: [NoteState; 3],
+    /// Pitch shift applied to every channel, in cents
+    pitch_shift: i32,
+    /// Level steps every channel is lowered by on its way to the chip
+    master_attenuation: u8,
+    /// Outputs muted on their way to the chip, one bit each
+    muted: u8,
+    /// Channel in percussion mode, and its mixer bits from before
+    percussion: Option<(AudioChannel, u8)>,
+    /// Channels played live, that frames leave alone, one bit each
+    live: u8,
+    /// Channels taken over by a [SfxManager], whose note effects are suspended, one bit each
+    borrowed: u8,
+    stats: Stats,
+    /// Longest a tick may take before effects are shed, 0 for no limit
+    tick_budget: u32,
+    shedding: Shedding,
+    /// Ticks in a row well within the budget
+    calm_ticks: u8,
 }
 
 /// One of the 16 registers (0-15) of the YM2149 sound chip.
@@ -136,6 +416,7 @@ where
 /// amplitude, and envelope.
 ///
 /// Check the datasheet / docs for detailed information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Register {
     /// Frequency of channel A: 8 bit fine tone adjustment
@@ -183,7 +464,7 @@ pub enum Register {
     /// chip.write_register(
     ///     Registers::IoPortMixerSettings,
     ///     0b11111110
-    /// );
+    /// )?;
     /// ```
     IoPortMixerSettings,
 
@@ -223,6 +504,28 @@ pub enum Register {
     DataIoB,
 }
 
+impl Register {
+    /// All 16 registers, in order, so `Register::ALL[n]` is R`n`.
+    pub const ALL: [Register; 16] = [
+        Self::AFreq8bitFinetone,
+        Self::AFreq4bitRoughtone,
+        Self::BFreq8bitFinetone,
+        Self::BFreq4bitRoughtone,
+        Self::CFreq8bitFinetone,
+        Self::CFreq4bitRoughtone,
+        Self::NoiseFreq5bit,
+        Self::IoPortMixerSettings,
+        Self::ALevel,
+        Self::BLevel,
+        Self::CLevel,
+        Self::EFreq8bitFineAdj,
+        Self::EFreq8bitRoughAdj,
+        Self::EShape,
+        Self::DataIoA,
+        Self::DataIoB,
+    ];
+}
+
 impl From<Register> for u8 {
     fn from(value: Register) -> Self {
         value as u8
@@ -239,6 +542,7 @@ impl From<Register> for u8 {
 /// | **READ**     |  0   |  1  |  1  |
 /// | **WRITE**    |  1   |  1  |  0  |
 /// | **ADDRESS**  |  1   |  1  |  1  |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Mode {
     /// DA7~DA0 has high impedance.
@@ -267,12 +571,142 @@ impl Mode {
 
     /// Returns an appropriate array of `PinState`s.
     fn pin_states(self) -> (PinState, PinState, PinState) {
-        Self::STATES[self as usize]
+        let [inactive, read, write, address] = Self::STATES;
+        match self {
+            Self::INACTIVE => inactive,
+            Self::READ => read,
+            Self::WRITE => write,
+            Self::ADDRESS => address,
+        }
+    }
+}
+
+/// Electrical polarity of a control pin.
+///
+/// Boards driving BC1, BDIR or RESET through transistors or inverting level shifters
+/// need the MCU to output the opposite of the level the chip should see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Polarity {
+    /// The pin is wired straight to the chip.
+    #[default]
+    Normal,
+    /// The level is inverted between the pin and the chip.
+    Inverted,
+}
+
+impl Polarity {
+    /// The level to output so the chip sees `state`.
+    pub fn apply(self, state: PinState) -> PinState {
+        match self {
+            Self::Normal => state,
+            Self::Inverted => !state,
+        }
+    }
+}
+
+/// Polarity of each control pin, see [YM2149::with_polarity].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ControlPolarity {
+    pub bc1: Polarity,
+    pub bdir: Polarity,
+    pub reset: Polarity,
+}
+
+/// A member of the YM2149 family, see [YM2149::with_variant].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    /// Yamaha YM2149 (40 pin DIP / 28 pin SOP)
+    #[default]
+    YM2149,
+    /// Yamaha YM3439, the CMOS version of the YM2149 (40 pin DIP / 44 pin PLCC)
+    YM3439,
+    /// Yamaha YMZ284, 16 pin CMOS version without I/O ports
+    YMZ284,
+    /// Yamaha YMZ294, 18 pin CMOS version without I/O ports
+    YMZ294,
+}
+
+impl Variant {
+    /// Highest frequency the master clock pin accepts, in Hz.
+    ///
+    /// For the YM2149 and YM3439 this is with SEL low (clock divided by 2 internally).
+    pub const fn max_clock_frequency(self) -> u32 {
+        match self {
+            Self::YM2149 | Self::YM3439 => 8_000_000,
+            Self::YMZ284 => 4_000_000,
+            Self::YMZ294 => 6_000_000,
+        }
+    }
+
+    /// Whether the chip has the two 8-bit I/O ports (R14 and R15).
+    pub const fn has_io_ports(self) -> bool {
+        matches!(self, Self::YM2149 | Self::YM3439)
+    }
+}
+
+/// One of the 2 general purpose I/O ports of the YM2149.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPort {
+    /// I/O port A, IOA7~IOA0 (R14)
+    A,
+    /// I/O port B, IOB7~IOB0 (R15)
+    B,
+}
+
+/// An error returned by the chip's API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The chip [Variant] doesn't have what the call needs (e.g. I/O ports).
+    Unsupported,
+    /// The write would touch the I/O ports while [safe mode](YM2149::with_safe_mode) is on.
+    IoProtected,
+    /// The master clock didn't start in time, see [power_up_sequence](YM2149::power_up_sequence).
+    ClockNotRunning,
+    /// The bus failed during the write, the register's value on the chip is unknown.
+    Bus,
+    /// A control pin (BC1, BDIR or RESET) couldn't be set.
+    Pin,
+}
+
+/// The level of a channel (R8 - R10): either fixed, or driven by the hardware envelope.
+///
+/// Example:
+/// ```no_run
+/// chip.volume(AudioChannel::A, Level::fixed(12));
+/// chip.volume(AudioChannel::B, Level::envelope());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Level(u8);
+
+impl Level {
+    /// Silence.
+    pub const OFF: Level = Level(0);
+    /// The loudest fixed level.
+    pub const MAX: Level = Level(15);
+
+    /// A fixed level, from 0 (silent) to 15. Higher values are clamped to 15.
+    pub const fn fixed(level: u8) -> Self {
+        Self(if level > 15 { 15 } else { level })
+    }
+
+    /// The level of the hardware envelope (M = 1).
+    pub const fn envelope() -> Self {
+        Self(0x10)
+    }
+
+    /// Whether the level follows the envelope.
+    pub const fn is_envelope(self) -> bool {
+        self.0 & 0x10 != 0
+    }
+
+    /// The value of the level register, M in B4 and the level in B3 - B0.
+    pub const fn bits(self) -> u8 {
+        self.0
     }
 }
 
 /// One of the 3 analog audio channels (A, B, C) of the YM2149.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AudioChannel {
     /// ANALOG CHANNEL A (Pin 4)
     A,
@@ -282,6 +716,102 @@ pub enum AudioChannel {
     C,
 }
 
+impl AudioChannel {
+    /// All three channels, in register order.
+    pub const ALL: [AudioChannel; 3] = [Self::A, Self::B, Self::C];
+
+    /// Tone and noise bits of the channel in [Register::IoPortMixerSettings].
+    const fn mixer_mask(self) -> u8 {
+        (1 << self as u8) | (1 << (self as u8 + 3))
+    }
+
+    /// Channel `index % 3`, in register order.
+    pub(crate) const fn wrapping_from_index(index: usize) -> Self {
+        match index % 3 {
+            0 => Self::A,
+            1 => Self::B,
+            _ => Self::C,
+        }
+    }
+
+    /// The entry of the channel in a per-channel array.
+    pub(crate) fn of<T>(self, values: &[T; 3]) -> &T {
+        let [a, b, c] = values;
+        match self {
+            Self::A => a,
+            Self::B => b,
+            Self::C => c,
+        }
+    }
+
+    /// The entry of the channel in a per-channel array, mutably.
+    pub(crate) fn of_mut<T>(self, values: &mut [T; 3]) -> &mut T {
+        let [a, b, c] = values;
+        match self {
+            Self::A => a,
+            Self::B => b,
+            Self::C => c,
+        }
+    }
+}
+
+/// `values[index]` without a panic path, usable in const fns.
+pub(crate) const fn nth<T>(values: &[T], index: usize) -> Option<&T> {
+    match values.split_at_checked(index) {
+        Some((_, [value, ..])) => Some(value),
+        _ => None,
+    }
+}
+
+/// Note tracking for a single [AudioChannel](#AudioChannel), used for legato / glide and effects.
+#[derive(Debug, Clone, Copy, Default)]
+struct NoteState {
+    /// Pitch currently being output, in cents from A4
+    pitch: i32,
+    /// Pitch the channel is gliding towards, in cents from A4
+    target: i32,
+    /// Glide speed in cents per [tick](YM2149::tick)
+    glide: u16,
+    legato: bool,
+    sounding: bool,
+    /// Vibrato depth in cents, 0 when off
+    vibrato_depth: u16,
+    /// Vibrato LFO phase increment per tick (256 = one cycle)
+    vibrato_speed: u8,
+    vibrato_phase: u8,
+    /// Transposition of the channel, in semitones
+    transpose: i8,
+    /// Offset from a modulation source, in cents
+    pitch_modulation: i16,
+    ornament: Option<Ornament>,
+    ornament_position: usize,
+    mixer_macro: Option<MixerMacro>,
+    mixer_position: usize,
+    software_envelope: Option<RawEnvelope>,
+    software_envelope_position: usize,
+}
+
+impl NoteState {
+    /// Current vibrato offset in cents (triangle LFO).
+    fn vibrato(&self) -> i32 {
+        let phase = self.vibrato_phase as i32;
+        let triangle = if phase < 128 { phase } else { 255 - phase };
+        (triangle * 2 - 127) * self.vibrato_depth as i32 / 127
+    }
+
+    /// Current ornament offset in cents.
+    fn ornament(&self) -> i32 {
+        self.ornament.map_or(0, |ornament| {
+            ornament.offset(self.ornament_position) as i32 * 100
+        })
+    }
+
+    /// Pitch to output, in cents from A4, with vibrato and ornament.
+    fn output(&self) -> i32 {
+        self.pitch + self.vibrato() + self.ornament()
+    }
+}
+
 impl<DATABUS, BC1, BDIR> YM2149<DATABUS, BC1, BDIR>
 where
     DATABUS: OutputBus,
@@ -295,9 +825,193 @@ where
             master_clock_frequency,
             bc1,
             bdir,
+            polarity: ControlPolarity::default(),
+            variant: Variant::default(),
+            safe_mode: false,
+            retrigger_limit: false,
+            shape_written: false,
+            pending_shape: None,
+            channel_map: AudioChannel::ALL,
+            period_table: None,
+            registers: [0; 16],
+            latched: None,
+            unknown: 0,
+            notes: [NoteState::default(); 3],
+            pitch_shift: 0,
+            master_attenuation: 0,
+            muted: 0,
+            percussion: None,
+            live: 0,
+            borrowed: 0,
+            stats: Stats::default(),
+            tick_budget: 0,
+            shedding: Shedding::Off,
+            calm_ticks: 0,
         }
     }
 
+    /// Set the polarity of the control pins, for boards that invert some of them.
+    ///
+    /// Example:
+    /// ```no_run
+    /// // BDIR goes through an inverting transistor stage
+    /// let mut chip = YM2149::new(data_bus, master_clock_freq, bc1, bdir).with_polarity(
+    ///     ControlPolarity {
+    ///         bdir: Polarity::Inverted,
+    ///         ..Default::default()
+    ///     },
+    /// );
+    /// ```
+    pub fn with_polarity(mut self, polarity: ControlPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Set which [Variant] of the chip is used (YM2149 by default).
+    ///
+    /// Calls to features the variant lacks then return [Error::Unsupported].
+    ///
+    /// Example:
+    /// ```no_run
+    /// let mut chip = YM2149::new(data_bus, master_clock_freq, bc1, bdir).with_variant(Variant::YMZ294);
+    /// assert_eq!(chip.write_io_port(IoPort::A, 0xFF), Err(Error::Unsupported));
+    /// ```
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        if self.master_clock_frequency > variant.max_clock_frequency() {
+            defmt::warn!(
+                "ym2149: {=u32} Hz master clock is above the chip's {=u32} Hz maximum",
+                self.master_clock_frequency,
+                variant.max_clock_frequency()
+            );
+        }
+        self.variant = variant;
+        self
+    }
+
+    /// Enable or disable safe mode (off by default).
+    ///
+    /// A stray write to the I/O ports can drive their pins against signals from the
+    /// outside and damage the chip or what it's connected to. With safe mode on, only
+    /// [write_io_port](#method.write_io_port) and [set_io_direction](#method.set_io_direction)
+    /// may touch them, and [write_register](#method.write_register) returns [Error::IoProtected] instead.
+    pub fn with_safe_mode(mut self, enabled: bool) -> Self {
+        self.safe_mode = enabled;
+        self
+    }
+
+    /// Allow at most one write to [Register::EShape] per [tick](#method.tick) (off by default).
+    ///
+    /// Every write to R13 restarts the envelope, so code writing it more than once per tick
+    /// (e.g. a song and an effect) makes audible clicks. With the limit on, later writes are
+    /// held back and only the last one is written on the next tick. [register](#method.register)
+    /// already reports it. [force_retrigger](#method.force_retrigger) restarts the envelope
+    /// anyway, and [reset](#method.reset) and [reinitialize](#method.reinitialize) aren't
+    /// limited.
+    pub fn with_retrigger_limit(mut self, enabled: bool) -> Self {
+        self.retrigger_limit = enabled;
+        self
+    }
+
+    /// Shed effects when ticks take longer than `budget`, as reported to
+    /// [report_tick_duration](#method.report_tick_duration), 0 to never shed (the default).
+    ///
+    /// Each tick over budget sheds one more level of [Shedding]. After 50 ticks in a row
+    /// under 3/4 of the budget, one level comes back.
+    pub fn with_tick_budget(mut self, budget: u32) -> Self {
+        self.tick_budget = budget;
+        self
+    }
+
+    /// Restart the envelope now, writing R13 (or the write held back by the
+    /// [retrigger limit](#method.with_retrigger_limit)) even if it was written this tick.
+    pub fn force_retrigger(&mut self) {
+        // A held back write is already in the shadow
+        self.pending_shape = None;
+        self.shape_written = false;
+        self.write(Register::EShape, self.register(Register::EShape));
+    }
+
+    /// Take the tone periods of semitones from a table baked for the master clock, instead
+    /// of working them out on every note. Detuned notes and semitones outside the table
+    /// are still worked out.
+    ///
+    /// The table must match the master clock, this isn't checked.
+    ///
+    /// Example:
+    /// ```no_run
+    /// const PERIODS: [u16; note::PERIOD_TABLE_NOTES] = note::build_period_table(2_000_000);
+    ///
+    /// let mut chip = YM2149::new(data_bus, 2_000_000, bc1, bdir).with_period_table(&PERIODS);
+    /// ```
+    pub fn with_period_table(mut self, table: &'static [u16; note::PERIOD_TABLE_NOTES]) -> Self {
+        self.period_table = Some(table);
+        self
+    }
+
+    /// Route channels to different outputs, with `map[n]` being the output that plays channel `n`.
+    ///
+    /// For boards where the analog outputs don't go where the music expects them, e.g. B and
+    /// C swapped in a stereo mix. Every channel-based method and [apply_frame](#method.apply_frame)
+    /// go through the map, raw [register](#method.write_register) writes don't.
+    ///
+    /// Panics if `map` isn't a permutation of the 3 channels.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use AudioChannel::*;
+    /// let mut chip = YM2149::new(data_bus, master_clock_freq, bc1, bdir).with_channel_map([A, C, B]);
+    /// chip.note(B, "A4")?; // Plays on output C
+    /// ```
+    pub fn with_channel_map(mut self, map: [AudioChannel; 3]) -> Self {
+        assert!(
+            AudioChannel::ALL
+                .iter()
+                .all(|channel| map.contains(channel)),
+            "channel map must be a permutation of A, B and C"
+        );
+        self.channel_map = map;
+        self
+    }
+
+    /// The output playing a channel, see [with_channel_map](#method.with_channel_map).
+    pub fn output_channel(&self, channel: AudioChannel) -> AudioChannel {
+        *channel.of(&self.channel_map)
+    }
+
+    /// The frequency of the master clock, in Hz.
+    pub fn master_clock_frequency(&self) -> u32 {
+        self.master_clock_frequency
+    }
+
+    /// Correct the master clock frequency from a measurement, as crystals and oscillators
+    /// are often off by a few hundred ppm.
+    ///
+    /// Play a tone with a known `period` (TP), measure its frequency at the output with a
+    /// frequency counter, and pass it in millihertz. The true clock is worked back from it
+    /// and used for every later note. Returns the new master clock frequency.
+    ///
+    /// A [period table](#method.with_period_table) no longer matches the clock, so it's dropped.
+    ///
+    /// Example:
+    /// ```no_run
+    /// // Test signal 3: the slowest square on output A, TP 0xFFF (30.52 Hz at 2 MHz)
+    /// TestSignal::from_index(3).unwrap().play(&mut chip);
+    /// // The counter reads 30.5375 Hz
+    /// let clock = chip.calibrate_master_clock(0x0FFF, 30_538);
+    /// ```
+    pub fn calibrate_master_clock(&mut self, period: u16, measured_millihertz: u32) -> u32 {
+        // f = fMaster / (16 * TP)
+        let master = measured_millihertz as u64 * 16 * period.max(1) as u64;
+        self.master_clock_frequency = ((master + 500) / 1000).min(u32::MAX as u64) as u32;
+        self.period_table = None;
+        self.master_clock_frequency
+    }
+
+    /// The [Variant] of the chip.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
     /// Set the [mode](#Mode) of the chip.
     ///
     /// Example:
@@ -311,12 +1025,158 @@ where
     /// );
     ///
     /// // Set the chip's mode to `Inactive`
-    /// chip.set_mode(Mode::INACTIVE);
+    /// chip.set_mode(Mode::INACTIVE)?;
     /// ```
-    pub fn set_mode(&mut self, mode: Mode) {
+    ///
+    /// Returns [Error::Pin] if BC1 or BDIR couldn't be set.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), Error> {
+        if mode == Mode::ADDRESS {
+            // Latched by the caller, `write` sets it again for its own addresses
+            self.latched = None;
+        }
+        if mode == Mode::READ {
+            self.data_bus.set_direction(BusDirection::Read);
+        }
+
         let (bdir, _, bc1) = mode.pin_states();
-        self.bdir.set_state(bdir).unwrap();
-        self.bc1.set_state(bc1).unwrap();
+        let bdir = self.bdir.set_state(self.polarity.bdir.apply(bdir));
+        let bc1 = self.bc1.set_state(self.polarity.bc1.apply(bc1));
+
+        if mode != Mode::READ {
+            self.data_bus.set_direction(BusDirection::Write);
+        }
+        if bdir.is_err() || bc1.is_err() {
+            defmt::warn!("ym2149: couldn't set BC1 / BDIR");
+            return Err(Error::Pin);
+        }
+        Ok(())
+    }
+
+    /// Reset the chip by pulsing its RESET pin (Pin 23) low, clearing all registers to 0.
+    ///
+    /// Timing comes from `delay`, so it stays right whatever the system clock is.
+    ///
+    /// Example:
+    /// ```no_run
+    /// let mut reset_pin = pins.gpio11.into_push_pull_output();
+    /// let mut timer = rp2040_hal::Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+    ///
+    /// chip.reset(&mut reset_pin, &mut timer)?;
+    /// ```
+    ///
+    /// Returns [Error::Pin] if a control pin couldn't be set, the driver's state is then
+    /// left as it was.
+    pub fn reset<RESET: OutputPin, DELAY: DelayNs>(
+        &mut self,
+        reset: &mut RESET,
+        delay: &mut DELAY,
+    ) -> Result<(), Error> {
+        self.pulse_reset(reset, delay)?;
+
+        self.registers = [0; 16];
+        self.notes = [NoteState::default(); 3];
+        self.pitch_shift = 0;
+        self.percussion = None;
+        self.live = 0;
+        self.borrowed = 0;
+        Ok(())
+    }
+
+    /// Bring the chip up without the random screech of registers written before its clock
+    /// is stable.
+    ///
+    /// Holds the bus inactive while polling `clock_ready` every millisecond, then waits for
+    /// the clock to settle and mutes every channel. Call it before anything else writes to
+    /// the chip. Pass `|| true` if the clock can't be checked, to only wait for it to settle.
+    ///
+    /// Returns [Error::ClockNotRunning] if `clock_ready` is still `false` after a second,
+    /// leaving the chip untouched.
+    ///
+    /// Example:
+    /// ```no_run
+    /// // The clock generator signals lock on a GPIO
+    /// let clock_lock = pins.gpio12.into_pull_down_input();
+    /// chip.power_up_sequence(&mut timer, || clock_lock.is_high().unwrap())?;
+    /// ```
+    pub fn power_up_sequence<DELAY: DelayNs>(
+        &mut self,
+        delay: &mut DELAY,
+        mut clock_ready: impl FnMut() -> bool,
+    ) -> Result<(), Error> {
+        self.set_mode(Mode::INACTIVE)?;
+
+        let mut waited = 0;
+        while !clock_ready() {
+            if waited == 1000 {
+                defmt::warn!("ym2149: master clock not running");
+                return Err(Error::ClockNotRunning);
+            }
+            delay.delay_ms(1);
+            waited += 1;
+        }
+        delay.delay_ms(10);
+
+        self.silence();
+        Ok(())
+    }
+
+    /// Reset the chip like [reset](#method.reset), then write every register back as it was.
+    ///
+    /// For recovering from a latch-up (e.g. an old socketed chip gone silent or stuck on one
+    /// sound) without losing track of what was playing: notes, vibratos and the like carry on.
+    /// The chip can't be read back, so noticing the fault is up to the firmware. Each call is
+    /// logged and counted in [Stats::reinitializations].
+    ///
+    /// A failure doesn't stop the other registers from being written, the first one is
+    /// returned.
+    ///
+    /// Example:
+    /// ```no_run
+    /// if button.is_low()? {
+    ///     chip.reinitialize(&mut reset_pin, &mut timer)?;
+    /// }
+    /// ```
+    pub fn reinitialize<RESET: OutputPin, DELAY: DelayNs>(
+        &mut self,
+        reset: &mut RESET,
+        delay: &mut DELAY,
+    ) -> Result<(), Error> {
+        defmt::warn!("ym2149: reinitializing the chip");
+        self.stats.record_reinitialization();
+        self.pulse_reset(reset, delay)?;
+
+        // Not held back by the retrigger limit: the chip must get every register now
+        let mut result = Ok(());
+        for (register, value) in self.registers.into_iter().enumerate() {
+            result = result.and(self.write_now(register as u8, value));
+        }
+        result
+    }
+
+    /// Pulse the RESET pin, leaving the bus inactive.
+    fn pulse_reset<RESET: OutputPin, DELAY: DelayNs>(
+        &mut self,
+        reset: &mut RESET,
+        delay: &mut DELAY,
+    ) -> Result<(), Error> {
+        self.set_mode(Mode::INACTIVE)?;
+
+        reset
+            .set_state(self.polarity.reset.apply(Low))
+            .map_err(|_| Error::Pin)?;
+        delay.delay_ms(10);
+        reset
+            .set_state(self.polarity.reset.apply(High))
+            .map_err(|_| Error::Pin)?;
+        delay.delay_ms(10);
+
+        // The chip forgot the latched address too, and holds 0 everywhere
+        self.latched = None;
+        self.unknown = 0;
+        // The envelope starts over with whatever is written next
+        self.shape_written = false;
+        self.pending_shape = None;
+        Ok(())
     }
 
     /// Write to one of the chip's 16 registers.
@@ -331,17 +1191,120 @@ where
     /// Example:
     /// ```no_run
     /// // Configure the mixer according to the datasheet
-    /// chip.write_register(Register::IoPortMixerSettings, 0b11111110);
+    /// chip.write_register(Register::IoPortMixerSettings, 0b11111110)?;
     /// ```
-    pub fn write_register<T: Into<u8>>(&mut self, register: T, value: u8) {
+    ///
+    /// With [safe mode](#method.with_safe_mode) on, writes to the I/O port registers
+    /// (R14, R15) and to the I/O direction bits of R7 (B6, B7) return [Error::IoProtected].
+    ///
+    /// If the bus fails ([OutputBus::try_write_u8]), the bus is left inactive and
+    /// [Error::Bus] is returned. The register then counts as unknown (see
+    /// [is_register_known](#method.is_register_known)) until it's written successfully.
+    pub fn write_register<T: Into<u8>>(&mut self, register: T, value: u8) -> Result<(), Error> {
         let r: u8 = register.into().clamp(0, 15);
 
-        self.set_mode(Mode::ADDRESS);
-        self.data_bus.write_u8(r);
-        self.set_mode(Mode::INACTIVE);
-        self.set_mode(Mode::WRITE);
-        self.data_bus.write_u8(value);
-        self.set_mode(Mode::INACTIVE);
+        if self.safe_mode {
+            let protected = match r {
+                7 => (value ^ self.registers[7]) & 0xC0 != 0,
+                14 | 15 => true,
+                _ => false,
+            };
+            if protected {
+                return Err(Error::IoProtected);
+            }
+        }
+
+        self.try_write(r, value)
+    }
+
+    /// Write a register, bypassing the checks of [write_register](#method.write_register).
+    ///
+    /// Bus failures are logged, and left for [apply_frame](#method.apply_frame) or the
+    /// next write of the register to repair.
+    fn write<T: Into<u8>>(&mut self, register: T, value: u8) {
+        let _ = self.try_write(register, value);
+    }
+
+    fn try_write<T: Into<u8>>(&mut self, register: T, value: u8) -> Result<(), Error> {
+        let r: u8 = register.into().clamp(0, 15);
+        if r == Register::EShape as u8 && self.retrigger_limit {
+            if self.shape_written {
+                // Written on the next tick, the shadow already tells what the chip will hold
+                self.pending_shape = Some(value);
+                if let Some(shadow) = self.registers.get_mut(r as usize) {
+                    *shadow = value;
+                }
+                return Ok(());
+            }
+            self.shape_written = true;
+        }
+        self.write_now(r, value)
+    }
+
+    /// Write a register to the chip right away, whatever the retrigger limit.
+    fn write_now(&mut self, r: u8, value: u8) -> Result<(), Error> {
+        if let Some(shadow) = self.registers.get_mut(r as usize) {
+            *shadow = value;
+        }
+        // The shadow keeps the level as written, only the chip hears the master attenuation
+        let value = match r {
+            8..=10 if self.muted & (1 << (r - 8)) != 0 => 0,
+            8..=10 => frame::attenuate_level(value, self.master_attenuation),
+            _ => value,
+        };
+        #[cfg(feature = "journal")]
+        journal::record(r, value);
+        self.stats.record_write();
+
+        if !(self.data_bus.cache_address() && self.latched == Some(r)) {
+            if let Err(error) = self.send(Mode::ADDRESS, r) {
+                return Err(self.write_failed(r, error));
+            }
+            self.latched = Some(r);
+        }
+        if let Err(error) = self.send(Mode::WRITE, value) {
+            return Err(self.write_failed(r, error));
+        }
+
+        self.unknown &= !(1 << r);
+        Ok(())
+    }
+
+    /// Put a byte on the bus in `mode`, then go back to inactive whatever happened.
+    fn send(&mut self, mode: Mode, byte: u8) -> Result<(), Error> {
+        let sent = self.set_mode(mode).and_then(|()| {
+            self.data_bus
+                .try_write_u8(byte)
+                .map_err(|BusError| Error::Bus)
+        });
+        let inactive = self.set_mode(Mode::INACTIVE);
+        sent.and(inactive)
+    }
+
+    /// Forget what the chip has latched and holds in `register`, after the bus failed.
+    fn write_failed(&mut self, register: u8, error: Error) -> Error {
+        defmt::warn!("ym2149: bus failed writing R{}", register);
+        // Whatever reached the chip, if anything, may have latched another address
+        self.latched = None;
+        self.unknown |= 1 << register;
+        error
+    }
+
+    /// Whether the chip is known to hold the [last value written](#method.register) to a
+    /// register, i.e. the last write to it didn't fail on the bus.
+    pub fn is_register_known<T: Into<u8>>(&self, register: T) -> bool {
+        self.unknown & (1 << register.into().clamp(0, 15)) == 0
+    }
+
+    /// The last value written to a register.
+    ///
+    /// The chip can't be read back yet, so this is the driver's own copy: it's 0 for
+    /// registers that weren't written since the driver was created or the chip [reset](#method.reset).
+    /// An R13 write held back by the [retrigger limit](#method.with_retrigger_limit) is
+    /// included.
+    pub fn register<T: Into<u8>>(&self, register: T) -> u8 {
+        let r = register.into().clamp(0, 15) as usize;
+        self.registers.get(r).copied().unwrap_or(0)
     }
 
     /// Play a tone with a TP of `period` on an [AudioChannel](#AudioChannel).
@@ -353,10 +1316,10 @@ where
     ///     - TP: tone period
     pub fn tone(&mut self, channel: AudioChannel, period: u16) {
         let bytes: [u8; 2] = period.to_le_bytes();
-        let register_pair_index = channel as u8 * 2;
+        let register_pair_index = self.output_channel(channel) as u8 * 2;
 
-        self.write_register(register_pair_index, bytes[0]); // Fine tone, 8 bits
-        self.write_register(register_pair_index + 1, bytes[1]); // Rough tone, 4 bits
+        self.write(register_pair_index, bytes[0]); // Fine tone, 8 bits
+        self.write(register_pair_index + 1, bytes[1]); // Rough tone, 4 bits
     }
 
     /// Play a tone of a given frequency in Hz on an [AudioChannel](#AudioChannel).
@@ -365,9 +1328,436 @@ where
         self.tone(channel, tp as u16); // Take lowest 16 bits
     }
 
+    /// Play a [Note] on an [AudioChannel](#AudioChannel).
+    ///
+    /// Example:
+    /// ```no_run
+    /// chip.play_note(AudioChannel::A, Note::new(NoteName::A, Accidental::Natural, 4));
+    /// // Microtones: a 24-TET quarter-tone above A4
+    /// chip.play_note(AudioChannel::B, Tuning::Equal(24).note(a4, 1));
+    /// ```
+    ///
+    /// If [legato](#method.set_legato) is enabled on the channel and a note is still sounding,
+    /// the channel glides to the new note instead of jumping to it, and its
+    /// [software envelope](#method.set_software_envelope) isn't restarted.
+    pub fn play_note(&mut self, channel: AudioChannel, note: Note) {
+        let pitch = note.cents_from_a4();
+        let state = channel.of_mut(&mut self.notes);
+        let legato = state.legato && state.sounding;
+        let glide = legato && state.glide != 0;
+
+        state.target = pitch;
+        state.sounding = true;
+        state.ornament_position = 0;
+        state.mixer_position = 0;
+        // A legato note carries on the envelope of the previous one
+        if !legato {
+            state.software_envelope_position = 0;
+        }
+        if !glide {
+            state.pitch = pitch;
+        }
+        self.refresh_pitch(channel);
+        self.refresh_mixer();
+        self.refresh_level(channel);
+    }
+
+    /// Mark the note playing on an [AudioChannel](#AudioChannel) as released.
+    ///
+    /// The next [play_note](#method.play_note) starts fresh instead of gliding.
+    /// This doesn't touch the channel's level.
+    pub fn release_note(&mut self, channel: AudioChannel) {
+        channel.of_mut(&mut self.notes).sounding = false;
+    }
+
+    /// Start a note right away, for live input (MIDI, keys), without waiting for the next
+    /// frame of a song.
+    ///
+    /// Only the channel's tone period and level are written, and its tone enabled if it
+    /// wasn't. Until [note_off_now](#method.note_off_now), [apply_frame](#method.apply_frame)
+    /// leaves the channel alone, so a song can keep playing on the others. From an
+    /// interrupt, go through [SharedChip::lock].
+    ///
+    /// Example:
+    /// ```no_run
+    /// // MIDI UART interrupt
+    /// CHIP.lock(|chip| match message {
+    ///     NoteOn(key, _) => chip.note_on_now(AudioChannel::C, Note::from_midi(key), Level::MAX),
+    ///     NoteOff(..) => chip.note_off_now(AudioChannel::C),
+    /// });
+    /// ```
+    pub fn note_on_now(&mut self, channel: AudioChannel, note: Note, level: Level) {
+        self.live |= 1 << channel as u8;
+        self.play_note(channel, note);
+
+        let output = self.output_channel(channel) as u8;
+        let mixer = self.register(Register::IoPortMixerSettings);
+        if mixer & (1 << output) != 0 {
+            self.write(Register::IoPortMixerSettings, mixer & !(1 << output));
+        }
+        self.write(self.level_register(channel), level.bits());
+    }
+
+    /// Stop a note started with [note_on_now](#method.note_on_now), muting the channel and
+    /// handing it back to the frames.
+    pub fn note_off_now(&mut self, channel: AudioChannel) {
+        self.release_note(channel);
+        self.write(self.level_register(channel), 0);
+        self.live &= !(1 << channel as u8);
+    }
+
+    /// Suspend the vibrato, ornament, glide, mixer macro and software envelope of a
+    /// channel while something else (a [SfxManager]) writes to it. They pick up where they
+    /// were after [give_back](#method.give_back).
+    pub(crate) fn borrow_channel(&mut self, channel: AudioChannel) {
+        self.borrowed |= 1 << channel as u8;
+    }
+
+    /// End a [borrow_channel](#method.borrow_channel).
+    pub(crate) fn give_back(&mut self, channel: AudioChannel) {
+        self.borrowed &= !(1 << channel as u8);
+    }
+
+    fn is_borrowed(&self, channel: AudioChannel) -> bool {
+        self.borrowed & (1 << channel as u8) != 0
+    }
+
+    /// Enable or disable legato on an [AudioChannel](#AudioChannel).
+    ///
+    /// With legato on, consecutive [play_note](#method.play_note) calls slide from one
+    /// note to the next (at the [glide](#method.set_glide) speed) as long as the previous
+    /// note wasn't [released](#method.release_note), like "fingered portamento" on synths.
+    ///
+    /// Example:
+    /// ```no_run
+    /// chip.set_legato(AudioChannel::A, true);
+    /// chip.set_glide(AudioChannel::A, 20); // 20 cents per tick
+    ///
+    /// chip.note(AudioChannel::A, "C4")?;
+    /// chip.note(AudioChannel::A, "G4")?; // Slides up from C4, call chip.tick() periodically
+    /// ```
+    pub fn set_legato(&mut self, channel: AudioChannel, legato: bool) {
+        channel.of_mut(&mut self.notes).legato = legato;
+    }
+
+    /// Set the glide speed of an [AudioChannel](#AudioChannel), in cents per [tick](#method.tick).
+    ///
+    /// `0` disables gliding.
+    pub fn set_glide(&mut self, channel: AudioChannel, cents_per_tick: u16) {
+        channel.of_mut(&mut self.notes).glide = cents_per_tick;
+    }
+
+    /// Set the vibrato of an [AudioChannel](#AudioChannel).
+    ///
+    /// The pitch swings `depth_cents` up and down around the note, advancing `speed`/256 of a
+    /// cycle every [tick](#method.tick). A depth of `0` turns vibrato off.
+    pub fn set_vibrato(&mut self, channel: AudioChannel, depth_cents: u16, speed: u8) {
+        let state = channel.of_mut(&mut self.notes);
+        let was_on = state.vibrato_depth != 0;
+        state.vibrato_depth = depth_cents;
+        state.vibrato_speed = speed;
+
+        // Don't leave the channel stuck wherever the LFO was
+        if was_on && depth_cents == 0 {
+            self.refresh_pitch(channel);
+        }
+    }
+
+    /// Play a note on all 3 channels at once, B on pitch and A / C detuned by
+    /// `-spread_cents` / `+spread_cents`, for a thick lead sound.
+    ///
+    /// Vibrato phases are spread out across the channels so they don't move in lockstep.
+    /// Calling it again moves all three channels to the new note.
+    ///
+    /// Example:
+    /// ```no_run
+    /// for channel in AudioChannel::ALL {
+    ///     chip.set_vibrato(channel, 10, 12);
+    /// }
+    /// chip.unison("A3".parse()?, 8);
+    /// ```
+    pub fn unison(&mut self, note: Note, spread_cents: i16) {
+        let detunes = [spread_cents.saturating_neg(), 0, spread_cents];
+        for ((channel, detune), phase) in
+            AudioChannel::ALL.into_iter().zip(detunes).zip([0, 85, 170])
+        {
+            self.play_note(channel, note.detune(detune));
+            channel.of_mut(&mut self.notes).vibrato_phase = phase;
+        }
+    }
+
+    /// Set the [Ornament] of an [AudioChannel](#AudioChannel), or remove it with `None`.
+    ///
+    /// The ornament restarts from its first offset on every [play_note](#method.play_note).
+    pub fn set_ornament(&mut self, channel: AudioChannel, ornament: Option<Ornament>) {
+        let state = channel.of_mut(&mut self.notes);
+        state.ornament = ornament;
+        state.ornament_position = 0;
+        self.refresh_pitch(channel);
+    }
+
+    /// Set the [MixerMacro] of an [AudioChannel](#AudioChannel), or remove it with `None`.
+    ///
+    /// While set, the macro drives the channel's tone and noise bits every
+    /// [tick](#method.tick), overriding [set_mixer](#method.set_mixer). Removing it leaves the
+    /// bits as the last step set them. The macro restarts on every [play_note](#method.play_note).
+    pub fn set_mixer_macro(&mut self, channel: AudioChannel, mixer_macro: Option<MixerMacro>) {
+        let state = channel.of_mut(&mut self.notes);
+        state.mixer_macro = mixer_macro;
+        state.mixer_position = 0;
+        self.refresh_mixer();
+    }
+
+    /// Set the [RawEnvelope] of an [AudioChannel](#AudioChannel), or remove it with `None`.
+    ///
+    /// While set, the envelope drives the channel's level every [tick](#method.tick),
+    /// overriding [volume](#method.volume). Removing it leaves the level as the last step
+    /// set it. The envelope restarts on every [play_note](#method.play_note).
+    pub fn set_software_envelope(&mut self, channel: AudioChannel, envelope: Option<RawEnvelope>) {
+        let state = channel.of_mut(&mut self.notes);
+        state.software_envelope = envelope;
+        state.software_envelope_position = 0;
+        self.refresh_level(channel);
+    }
+
+    /// Write the level of a channel playing a [RawEnvelope], if it changed.
+    fn refresh_level(&mut self, channel: AudioChannel) {
+        let state = channel.of(&self.notes);
+        let Some(envelope) = state.software_envelope else {
+            return;
+        };
+        if self.is_borrowed(channel) {
+            return;
+        }
+        let level = envelope.level(state.software_envelope_position);
+        let register = self.level_register(channel);
+        if self.register(register) != level {
+            self.write(register, level);
+        }
+    }
+
+    /// Write the mixer bits of every channel playing a [MixerMacro], in a single R7 write.
+    fn refresh_mixer(&mut self) {
+        let mut mixer = self.register(Register::IoPortMixerSettings);
+        for channel in AudioChannel::ALL {
+            let state = channel.of(&self.notes);
+            let Some(mix) = state.mixer_macro.and_then(|m| m.step(state.mixer_position)) else {
+                continue;
+            };
+            if self.is_borrowed(channel) {
+                continue;
+            }
+            let output = self.output_channel(channel) as u8;
+            // 0 enables the output
+            let bits = ((!mix.tone() as u8) << output) | ((!mix.noise() as u8) << (output + 3));
+            mixer = (mixer & !self.mixer_mask(channel)) | bits;
+        }
+        if mixer != self.register(Register::IoPortMixerSettings) {
+            self.write(Register::IoPortMixerSettings, mixer);
+        }
+    }
+
+    /// Advance glides, vibratos, ornaments, mixer macros and software envelopes by one
+    /// step, and write the envelope shape held back by the
+    /// [retrigger limit](#method.with_retrigger_limit), if any.
+    ///
+    /// Glides, vibratos and ornaments of released channels stay where they are until the
+    /// next note, and so do all the effects of channels playing a [Sfx].
+    ///
+    /// Call this at a steady rate, e.g. from a 50 Hz timer.
+    pub fn tick(&mut self) {
+        self.shape_written = false;
+        if let Some(shape) = self.pending_shape.take() {
+            self.write(Register::EShape, shape);
+        }
+
+        for channel in AudioChannel::ALL {
+            if self.is_borrowed(channel) {
+                continue;
+            }
+            let state = channel.of_mut(&mut self.notes);
+            if let Some(mixer_macro) = state.mixer_macro {
+                state.mixer_position = mixer_macro.next(state.mixer_position);
+            }
+        }
+        self.refresh_mixer();
+
+        if self.shedding < Shedding::SoftwareEnvelopes {
+            for channel in AudioChannel::ALL {
+                if self.is_borrowed(channel) {
+                    continue;
+                }
+                let state = channel.of_mut(&mut self.notes);
+                if let Some(envelope) = state.software_envelope {
+                    state.software_envelope_position =
+                        envelope.next(state.software_envelope_position);
+                    self.refresh_level(channel);
+                }
+            }
+        }
+
+        for channel in AudioChannel::ALL {
+            if self.is_borrowed(channel) {
+                continue;
+            }
+            let state = channel.of_mut(&mut self.notes);
+            let vibrato = state.vibrato_depth != 0 && self.shedding < Shedding::Vibrato;
+            // A released note keeps its period, writing it would only cost bus time
+            if !state.sounding
+                || (state.pitch == state.target && !vibrato && state.ornament.is_none())
+            {
+                continue;
+            }
+
+            let step = state.glide.max(1) as i32;
+            state.pitch = if state.pitch < state.target {
+                (state.pitch + step).min(state.target)
+            } else {
+                (state.pitch - step).max(state.target)
+            };
+            if vibrato {
+                state.vibrato_phase = state.vibrato_phase.wrapping_add(state.vibrato_speed);
+            }
+            if let Some(ornament) = state.ornament {
+                state.ornament_position = ornament.next(state.ornament_position);
+            }
+
+            let pitch = state.output();
+            self.output_pitch(channel, pitch);
+        }
+    }
+
+    /// Transpose an [AudioChannel](#AudioChannel) by a number of semitones.
+    ///
+    /// Applies to notes played with [play_note](#method.play_note), [note](#method.note)
+    /// and [unison](#method.unison), on top of the [pitch shift](#method.set_pitch_shift).
+    /// A sounding note is moved right away.
+    pub fn set_transpose(&mut self, channel: AudioChannel, semitones: i8) {
+        channel.of_mut(&mut self.notes).transpose = semitones;
+        self.refresh_pitch(channel);
+    }
+
+    /// Offset the pitch of an [AudioChannel](#AudioChannel) by some cents, e.g. from a
+    /// [Modulator]. A sounding note is moved right away.
+    ///
+    /// Like [set_transpose](#method.set_transpose), it's kept across notes and applies on
+    /// top of the pitch shift.
+    pub fn set_pitch_modulation(&mut self, channel: AudioChannel, cents: i16) {
+        let state = channel.of_mut(&mut self.notes);
+        if state.pitch_modulation != cents {
+            state.pitch_modulation = cents;
+            self.refresh_pitch(channel);
+        }
+    }
+
+    /// Shift the pitch of all channels, in cents.
+    ///
+    /// Like [set_transpose](#method.set_transpose), but global and finer, so it can be
+    /// moved a bit every frame.
+    ///
+    /// Example:
+    /// ```no_run
+    /// // Tape stop: drag everything down 2 octaves over ~1 s at 50 Hz
+    /// for i in 0..50 {
+    ///     chip.set_pitch_shift(-i * 48);
+    ///     timer.delay_ms(20);
+    /// }
+    /// ```
+    pub fn set_pitch_shift(&mut self, cents: i32) {
+        self.pitch_shift = cents;
+        for channel in AudioChannel::ALL {
+            self.refresh_pitch(channel);
+        }
+    }
+
+    /// Lower every channel by `steps` level steps (about 3 dB each, 15 or more silences
+    /// the chip), e.g. from a [Ducker], without changing what's played.
+    ///
+    /// Levels are lowered on their way to the chip, whoever writes them:
+    /// [volume](#method.volume), software envelopes, [apply_frame](#method.apply_frame)...
+    /// [register](#method.register) still reports them as written, so nothing else needs to
+    /// know. Envelope-driven channels can't be lowered, and only go silent at 15.
+    ///
+    /// Example:
+    /// ```no_run
+    /// // Fade out over ~1 s at 50 Hz
+    /// for steps in 0..=15 {
+    ///     chip.set_master_attenuation(steps);
+    ///     timer.delay_ms(66);
+    /// }
+    /// ```
+    pub fn set_master_attenuation(&mut self, steps: u8) {
+        let steps = steps.min(15);
+        if steps == self.master_attenuation {
+            return;
+        }
+        self.master_attenuation = steps;
+        for channel in AudioChannel::ALL {
+            let register = self.level_register(channel);
+            let _ = self.write_now(register, self.register(register));
+        }
+    }
+
+    /// Level steps every channel is lowered by, see
+    /// [set_master_attenuation](#method.set_master_attenuation).
+    pub fn master_attenuation(&self) -> u8 {
+        self.master_attenuation
+    }
+
+    /// Mute or unmute an [AudioChannel](#AudioChannel), without changing what it plays.
+    ///
+    /// Like the [master attenuation](#method.set_master_attenuation), this happens on the
+    /// way to the chip: the channel keeps playing silently, envelope-driven or not, and
+    /// picks up where the music is when unmuted.
+    pub fn set_muted(&mut self, channel: AudioChannel, muted: bool) {
+        let register = self.level_register(channel);
+        let bit = 1 << (register - Register::ALevel as u8);
+        if muted == (self.muted & bit != 0) {
+            return;
+        }
+        self.muted ^= bit;
+        let _ = self.write_now(register, self.register(register));
+    }
+
+    /// Whether an [AudioChannel](#AudioChannel) is [muted](#method.set_muted).
+    pub fn is_muted(&self, channel: AudioChannel) -> bool {
+        let register = self.level_register(channel);
+        self.muted & (1 << (register - Register::ALevel as u8)) != 0
+    }
+
+    /// Write the tone period for `pitch` (in cents from A4), with the channel's transposition.
+    fn output_pitch(&mut self, channel: AudioChannel, pitch: i32) {
+        let state = channel.of(&self.notes);
+        let shift = self.pitch_shift + state.transpose as i32 * 100 + state.pitch_modulation as i32;
+        let cents = pitch + shift;
+        let period = self
+            .period_table
+            .and_then(|table| note::table_period(table, cents))
+            .unwrap_or_else(|| note::period_for_cents(self.master_clock_frequency, cents));
+        self.tone(channel, period);
+    }
+
+    /// Output the current pitch of a sounding channel again.
+    fn refresh_pitch(&mut self, channel: AudioChannel) {
+        let state = channel.of(&self.notes);
+        if state.sounding && !self.is_borrowed(channel) {
+            let pitch = state.output();
+            self.output_pitch(channel, pitch);
+        }
+    }
+
+    /// Play a note parsed from a string (`"A4"`, `"C#3"`, `"Eb5"`, `"F+2"`) on an [AudioChannel](#AudioChannel).
+    pub fn note(&mut self, channel: AudioChannel, note_s: &str) -> Result<(), NoteParseError> {
+        self.play_note(channel, note_s.parse()?);
+        Ok(())
+    }
+
     /// Set the frequency of the noise generator.
+    ///
+    /// This is the noise period (NP, 0 - 31): higher is duller. See
+    /// [noise_period_for_cutoff_hz](texture::noise_period_for_cutoff_hz) to pick it in Hz.
     pub fn set_noise_freq(&mut self, frequency: u8) {
-        self.write_register(6, frequency & 0x1F);
+        self.write(6, frequency & 0x1F);
     }
 
     /// Set the volume of an [AudioChannel](#AudioChannel).
@@ -384,8 +1774,360 @@ where
     /// | B7 (MSB)  | B6  | B5  | B4  | B3  | B2  | B1  | B0  |
     /// |-----------|-----|-----|-----|-----|-----|-----|-----|
     /// | N/A       | N/A | N/A |  M  | L3  | L2  | L1  | L0  |
-    pub fn volume(&mut self, channel: AudioChannel, volume: u8) {
-        self.write_register(8 + channel as u8, volume & 0x1F);
+    ///
+    /// The [Level] type sets both at once, so M can't be flipped by accident.
+    ///
+    /// On a channel set up with [use_hardware_envelope](#method.use_hardware_envelope),
+    /// fixed levels are refused (with a warning), use
+    /// [release_hardware_envelope](#method.release_hardware_envelope) first.
+    ///
+    /// Example:
+    /// ```no_run
+    /// chip.volume(AudioChannel::A, Level::MAX);
+    /// ```
+    pub fn volume(&mut self, channel: AudioChannel, level: Level) {
+        if self.is_envelope_driven(channel) && !level.is_envelope() {
+            defmt::warn!(
+                "ym2149: volume() would clear the envelope mode of channel {=u8}, ignored",
+                channel as u8
+            );
+            return;
+        }
+        self.write(self.level_register(channel), level.bits());
+    }
+
+    /// Drive the level of some channels with the hardware envelope.
+    ///
+    /// Sets the M bit of each channel's level register and programs the envelope
+    /// frequency (R11, R12) and shape (R13) once for all of them. Writing R13 restarts the envelope.
+    ///
+    /// Example:
+    /// ```no_run
+    /// chip.use_hardware_envelope(
+    ///     &[AudioChannel::A, AudioChannel::B],
+    ///     EnvelopeFrequency::Hz(4),
+    ///     EnvelopeShape::TriangleUp,
+    /// );
+    /// ```
+    pub fn use_hardware_envelope(
+        &mut self,
+        channels: &[AudioChannel],
+        frequency: EnvelopeFrequency,
+        shape: EnvelopeShape,
+    ) {
+        let period = frequency
+            .period(self.master_clock_frequency, shape)
+            .to_le_bytes();
+        self.write(Register::EFreq8bitFineAdj, period[0]);
+        self.write(Register::EFreq8bitRoughAdj, period[1]);
+        self.write(Register::EShape, shape as u8);
+
+        for &channel in channels {
+            let level = self.register(self.level_register(channel));
+            self.write(self.level_register(channel), level | 0x10);
+        }
+    }
+
+    /// Play a bass note with the hardware envelope as the oscillator ("envelope bass").
+    ///
+    /// Programs the envelope period so a repeating `shape` (saw or triangle) plays `note`,
+    /// and drives the channel's level with it. The tone and noise of the channel are left
+    /// as they are: with both [off](#method.set_mixer) you hear the bare envelope waveform,
+    /// with the tone on, the square wave gates it. One-shot shapes are refused with a warning.
+    ///
+    /// As the envelope is shared, so is the note: every envelope-driven channel follows it.
+    ///
+    /// Example:
+    /// ```no_run
+    /// chip.set_mixer(AudioChannel::C, false, false);
+    /// chip.envelope_bass(AudioChannel::C, "E1".parse()?, EnvelopeShape::SawDown);
+    /// ```
+    pub fn envelope_bass(&mut self, channel: AudioChannel, note: Note, shape: EnvelopeShape) {
+        let Some(period) = envelope::period_for_note(self.master_clock_frequency, note, shape)
+        else {
+            defmt::warn!("ym2149: envelope_bass() needs a repeating envelope shape, ignored");
+            return;
+        };
+        self.use_hardware_envelope(&[channel], EnvelopeFrequency::Period(period), shape);
+    }
+
+    /// Go back to fixed levels on some channels, at the level they had before
+    /// [use_hardware_envelope](#method.use_hardware_envelope).
+    pub fn release_hardware_envelope(&mut self, channels: &[AudioChannel]) {
+        for &channel in channels {
+            let level = self.register(self.level_register(channel));
+            self.write(self.level_register(channel), level & 0x0F);
+        }
+    }
+
+    /// Whether the level of a channel follows the hardware envelope (M bit set).
+    pub fn is_envelope_driven(&self, channel: AudioChannel) -> bool {
+        self.register(self.level_register(channel)) & 0x10 != 0
+    }
+
+    /// Level register of the output playing a channel.
+    pub(crate) fn level_register(&self, channel: AudioChannel) -> u8 {
+        Register::ALevel as u8 + self.output_channel(channel) as u8
+    }
+
+    /// Tone and noise bits in [Register::IoPortMixerSettings] of the output playing a channel.
+    pub(crate) fn mixer_mask(&self, channel: AudioChannel) -> u8 {
+        self.output_channel(channel).mixer_mask()
+    }
+
+    /// The current state of the sound registers, as last written, seen through the
+    /// [channel map](#method.with_channel_map).
+    ///
+    /// [retrigger](Frame::retrigger) is always `false`.
+    pub fn frame(&self) -> Frame {
+        let mut inverse = AudioChannel::ALL;
+        for channel in AudioChannel::ALL {
+            *self.output_channel(channel).of_mut(&mut inverse) = channel;
+        }
+        self.output_frame().remap(inverse)
+    }
+
+    /// The sound registers as they are on the chip.
+    fn output_frame(&self) -> Frame {
+        let mut registers = [0; frame::FRAME_REGISTERS];
+        for (frame, &shadow) in registers.iter_mut().zip(&self.registers) {
+            *frame = shadow;
+        }
+        Frame::new(registers, false)
+    }
+
+    /// Bring the sound registers to the state of a [Frame], writing only the ones that
+    /// changed (see [Frame::diff]).
+    ///
+    /// The I/O direction bits of R7 are kept as they are, whatever the frame says, use
+    /// [set_io_direction](#method.set_io_direction) for those. As the diff is against the
+    /// driver's copy of the registers, [reset](#method.reset) the chip before the first frame.
+    /// Channels playing a [live note](#method.note_on_now) or a sound effect of a
+    /// [SfxManager] are left as they are.
+    pub fn apply_frame(&mut self, frame: &Frame) {
+        // Registers a failed write left unknown are written again on the next frame
+        let _ = self.write_frame(frame);
+    }
+
+    /// [apply_frame](#method.apply_frame), returning the first bus failure. The registers
+    /// after it are still written.
+    pub(crate) fn write_frame(&mut self, frame: &Frame) -> Result<(), Error> {
+        let io_bits = self.register(Register::IoPortMixerSettings) & 0xC0;
+        let mut next = frame.remap(self.channel_map);
+        next.registers[frame::MIXER] = (next.registers[frame::MIXER] & 0x3F) | io_bits;
+        for channel in AudioChannel::ALL {
+            if (self.live | self.borrowed) & (1 << channel as u8) == 0 {
+                continue;
+            }
+            // Keep what the live note or the effect set
+            let output = self.output_channel(channel) as u8;
+            for r in [output * 2, output * 2 + 1, self.level_register(channel)] {
+                next.set_register(r as usize, self.register(r));
+            }
+            let mask = self.mixer_mask(channel);
+            next.registers[frame::MIXER] = (next.registers[frame::MIXER] & !mask)
+                | (self.register(Register::IoPortMixerSettings) & mask);
+        }
+
+        let mut current = self.output_frame();
+        for (r, (value, next)) in current
+            .registers
+            .iter_mut()
+            .zip(&next.registers)
+            .enumerate()
+        {
+            // Registers a failed write left unknown are written again, whatever they hold
+            if !self.is_register_known(r as u8) {
+                *value = !next;
+            }
+        }
+
+        let mut result = Ok(());
+        for (register, value) in Frame::diff(&current, &next) {
+            let written = self.try_write(register, value);
+            result = result.and(written);
+        }
+        result
+    }
+
+    /// Write all 14 sound registers (R0 - R13), e.g. to restore a state saved with
+    /// [register](#method.register) after a seek or waking the chip up.
+    ///
+    /// Unlike [apply_frame](#method.apply_frame), every register is written whatever the
+    /// driver thinks the chip holds, and the envelope restarts. Values are for the outputs
+    /// as wired, the [channel map](#method.with_channel_map) isn't applied. The I/O direction
+    /// bits of R7 are kept as they are.
+    ///
+    /// The chip has no burst mode, so each register still takes an address and a value
+    /// phase; R13 goes last so the envelope restarts with its period already set. A bus
+    /// failure doesn't stop the others from being written, the first one is returned.
+    ///
+    /// Example:
+    /// ```no_run
+    /// let saved: [u8; 14] = core::array::from_fn(|r| chip.register(r as u8));
+    /// chip.reset(&mut reset_pin, &mut timer)?;
+    /// chip.write_all(&saved)?;
+    /// ```
+    pub fn write_all(&mut self, registers: &[u8; frame::FRAME_REGISTERS]) -> Result<(), Error> {
+        let io_bits = self.register(Register::IoPortMixerSettings) & 0xC0;
+        let mut result = Ok(());
+        for (r, &value) in registers.iter().enumerate() {
+            let value = if r == frame::MIXER {
+                (value & 0x3F) | io_bits
+            } else {
+                value
+            };
+            result = result.and(self.try_write(r as u8, value));
+        }
+        result
+    }
+
+    /// Profiling counters, see [Stats].
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Profiling counters, for the replay loop to update.
+    pub fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+
+    /// Report how long a tick took (see [Stats::record_tick_duration]), shedding effects
+    /// or bringing them back to keep within the [tick budget](#method.with_tick_budget).
+    ///
+    /// Example:
+    /// ```no_run
+    /// let mut chip = YM2149::new(data_bus, master_clock_freq, bc1, bdir).with_tick_budget(2000);
+    ///
+    /// // In the timer interrupt, measuring in microseconds
+    /// let start = timer.get_counter_low();
+    /// chip.tick();
+    /// sfx.tick(&mut chip);
+    /// chip.report_tick_duration(timer.get_counter_low().wrapping_sub(start));
+    /// ```
+    pub fn report_tick_duration(&mut self, duration: u32) {
+        self.stats.record_tick_duration(duration);
+        if self.tick_budget == 0 {
+            return;
+        }
+
+        if duration > self.tick_budget {
+            self.calm_ticks = 0;
+            if self.shedding != Shedding::SoftwareEnvelopes {
+                self.shedding = self.shedding.more();
+                defmt::warn!(
+                    "ym2149: tick took {} over a budget of {}, shedding {}",
+                    duration,
+                    self.tick_budget,
+                    self.shedding
+                );
+            }
+        } else if duration < self.tick_budget / 4 * 3 && self.shedding != Shedding::Off {
+            self.calm_ticks += 1;
+            if self.calm_ticks == 50 {
+                self.calm_ticks = 0;
+                self.shedding = self.shedding.less();
+                defmt::info!("ym2149: back to shedding {}", self.shedding);
+            }
+        } else {
+            self.calm_ticks = 0;
+        }
+    }
+
+    /// What [tick](#method.tick) currently leaves out to keep within the
+    /// [tick budget](#method.with_tick_budget).
+    pub fn shedding(&self) -> Shedding {
+        self.shedding
+    }
+
+    /// Reset all profiling counters to 0.
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    /// Enable or disable tone and noise on an [AudioChannel](#AudioChannel), leaving
+    /// the rest of [Register::IoPortMixerSettings] as it was.
+    pub fn set_mixer(&mut self, channel: AudioChannel, tone: bool, noise: bool) {
+        let output = self.output_channel(channel) as u8;
+        // 0 enables the output
+        let bits = ((!tone as u8) << output) | ((!noise as u8) << (output + 3));
+
+        let mixer =
+            (self.register(Register::IoPortMixerSettings) & !self.mixer_mask(channel)) | bits;
+        self.write(Register::IoPortMixerSettings, mixer);
+    }
+
+    /// Turn an [AudioChannel](#AudioChannel) into a noise-only percussion channel.
+    ///
+    /// Tone is disabled and noise enabled on the channel, until
+    /// [exit_percussion_mode](#method.exit_percussion_mode) puts its mixer settings back.
+    /// Only one channel can be in percussion mode: calling this again moves it.
+    ///
+    /// Example:
+    /// ```no_run
+    /// chip.percussion_mode(AudioChannel::C);
+    /// chip.set_noise_freq(0x0A);
+    /// chip.volume(AudioChannel::C, Level::MAX); // Snare-ish hit
+    ///
+    /// chip.exit_percussion_mode();
+    /// ```
+    pub fn percussion_mode(&mut self, channel: AudioChannel) {
+        self.exit_percussion_mode();
+
+        let saved = self.register(Register::IoPortMixerSettings) & self.mixer_mask(channel);
+        self.percussion = Some((channel, saved));
+        self.set_mixer(channel, false, true);
+    }
+
+    /// Leave percussion mode, restoring the channel's previous mixer settings.
+    pub fn exit_percussion_mode(&mut self) {
+        if let Some((channel, saved)) = self.percussion.take() {
+            let mixer =
+                (self.register(Register::IoPortMixerSettings) & !self.mixer_mask(channel)) | saved;
+            self.write(Register::IoPortMixerSettings, mixer);
+        }
+    }
+
+    /// The channel currently in [percussion mode](#method.percussion_mode), if any.
+    pub fn percussion_channel(&self) -> Option<AudioChannel> {
+        self.percussion.map(|(channel, _)| channel)
+    }
+
+    /// Make one of the [I/O ports](IoPort) an output (`true`) or an input (`false`).
+    ///
+    /// This sets bit B6 (port A) or B7 (port B) of [Register::IoPortMixerSettings],
+    /// leaving the mixer bits alone.
+    pub fn set_io_direction(&mut self, port: IoPort, output: bool) -> Result<(), Error> {
+        if !self.variant.has_io_ports() {
+            return Err(Error::Unsupported);
+        }
+
+        let bit = 1 << (6 + port as u8);
+        let mixer = self.register(Register::IoPortMixerSettings);
+        let mixer = if output { mixer | bit } else { mixer & !bit };
+        self.write(Register::IoPortMixerSettings, mixer);
+        Ok(())
+    }
+
+    /// Write a value to one of the [I/O ports](IoPort).
+    ///
+    /// The port must be set as an output (bits B6/B7 of [Register::IoPortMixerSettings])
+    /// for the value to show up on its pins.
+    pub fn write_io_port(&mut self, port: IoPort, value: u8) -> Result<(), Error> {
+        if !self.variant.has_io_ports() {
+            return Err(Error::Unsupported);
+        }
+        self.write(Register::DataIoA as u8 + port as u8, value);
+        Ok(())
+    }
+
+    /// Mute every channel by setting all levels to 0.
+    ///
+    /// This also clears the envelope mode bits, so nothing keeps sounding.
+    pub fn silence(&mut self) {
+        for channel in AudioChannel::ALL {
+            self.write(Register::ALevel as u8 + channel as u8, 0);
+        }
     }
 
     // ============================================================
@@ -408,18 +2150,4 @@ where
     fn read(&mut self, register: Register) -> u8 {
         unimplemented!("Mode::READ and .read() are not yet usable.");
     }
-
-    #[allow(unused)]
-    /// Play a note `(note_s: &'static str)` on an [AudioChannel](#AudioChannel).
-    fn note(&mut self, channel: AudioChannel, note_s: &'static str) -> Result<(), NoteParseError> {
-        todo!(".note() is not yet implemented: Unfinished code");
-        // Code below is unreachable
-        if note_s.len() < 2 || note_s.len() > 3 {
-            return Err(NoteParseError::InvalidLength);
-        }
-        let semitones_from_a4: u32; // NOTE TO SELF: f = f0 * 2 ^ (n / 12) | f0 - reference pitch, n - semitones away from ref.
-        Ok(())
-    }
-
-    // TODO: Envelope & I/O control
 }
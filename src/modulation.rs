@@ -0,0 +1,267 @@
+//! A small modulation matrix: velocity, aftertouch, mod wheel, LFOs and an envelope routed
+//! to pitch, level, noise and envelope rate, with a depth per route.
+
+use crate::{Level, RawEnvelope};
+
+/// Where a modulation comes from.
+///
+/// Velocity, aftertouch, mod wheel and envelope go from 0 to full scale, LFOs swing from
+/// minus to plus full scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// Velocity of the last [note_on](Modulator::note_on), 0 - 127
+    Velocity,
+    /// Channel aftertouch, 0 - 127
+    Aftertouch,
+    /// Mod wheel (CC 1), 0 - 127
+    ModWheel,
+    Lfo1,
+    Lfo2,
+    /// The level of the matrix's [envelope](ModMatrix::with_envelope), level 15 being full scale
+    Envelope,
+}
+
+/// What a modulation acts on, and the unit of its depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    /// In cents
+    Pitch,
+    /// In level steps
+    Level,
+    /// In noise period steps (higher is duller)
+    NoisePeriod,
+    /// In 1/256 of the envelope speed: 256 plays the hardware envelope twice as fast.
+    /// See [ParaSynth::with_hardware_envelope](crate::ParaSynth::with_hardware_envelope)
+    EnvelopeRate,
+}
+
+/// One connection of a [ModMatrix]: `depth` is how much the destination moves with the
+/// source at full scale, negative to invert it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    pub source: Source,
+    pub destination: Destination,
+    pub depth: i16,
+}
+
+impl Route {
+    pub const fn new(source: Source, destination: Destination, depth: i16) -> Self {
+        Self {
+            source,
+            destination,
+            depth,
+        }
+    }
+}
+
+/// The modulation setup of an instrument: its routes, the speed of its two LFOs and an
+/// optional envelope, played by a [Modulator].
+///
+/// Example:
+/// ```no_run
+/// use Destination::*;
+/// use Source::*;
+///
+/// const LEAD: ModMatrix = ModMatrix::new(&[
+///     // Vibrato of up to ±30 cents, brought in by the mod wheel (see below)
+///     Route::new(Lfo1, Pitch, 30),
+///     // Up to 6 steps over the base level on the hardest notes
+///     Route::new(Velocity, Level, 6),
+///     // Pressing harder brightens the noise
+///     Route::new(Aftertouch, NoisePeriod, -8),
+/// ])
+/// .with_lfo(0, 10)
+/// .with_depth_control(Lfo1, ModWheel);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModMatrix<'a> {
+    routes: &'a [Route],
+    /// Phase increment of each LFO per tick (256 = one cycle)
+    lfos: [u8; 2],
+    envelope: Option<RawEnvelope>,
+    /// Source scaling the depth of another source's routes
+    depth_control: Option<(Source, Source)>,
+}
+
+impl<'a> ModMatrix<'a> {
+    /// A matrix with `routes`, LFOs stopped and no envelope.
+    pub const fn new(routes: &'a [Route]) -> Self {
+        Self {
+            routes,
+            lfos: [0; 2],
+            envelope: None,
+            depth_control: None,
+        }
+    }
+
+    /// Set the speed of LFO `lfo` (0 or 1), advancing `speed`/256 of a cycle every tick.
+    /// Other values of `lfo` are ignored.
+    pub const fn with_lfo(mut self, lfo: usize, speed: u8) -> Self {
+        match lfo {
+            0 => self.lfos[0] = speed,
+            1 => self.lfos[1] = speed,
+            _ => {}
+        }
+        self
+    }
+
+    /// The [Envelope](Source::Envelope) source, restarted on every note.
+    pub const fn with_envelope(mut self, envelope: RawEnvelope) -> Self {
+        self.envelope = Some(envelope);
+        self
+    }
+
+    /// Scale the routes of `source` by `control` (from 0 to full scale), e.g. to bring in
+    /// an LFO with the mod wheel.
+    pub const fn with_depth_control(mut self, source: Source, control: Source) -> Self {
+        self.depth_control = Some((source, control));
+        self
+    }
+
+    pub const fn routes(&self) -> &'a [Route] {
+        self.routes
+    }
+}
+
+/// How far each destination is moved on a tick, see [Modulator::tick].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modulation {
+    /// In cents, for [YM2149::set_pitch_modulation](crate::YM2149::set_pitch_modulation)
+    pub pitch: i16,
+    /// In level steps
+    pub level: i16,
+    /// In noise period steps
+    pub noise_period: i16,
+    /// In 1/256 of the envelope speed
+    pub envelope_rate: i16,
+}
+
+impl Modulation {
+    /// `base` moved by the level modulation.
+    pub fn level(&self, base: u8) -> Level {
+        Level::fixed((base as i16 + self.level).clamp(0, 15) as u8)
+    }
+
+    /// `base` moved by the noise period modulation.
+    pub fn noise_period(&self, base: u8) -> u8 {
+        (base as i16 + self.noise_period).clamp(0, 31) as u8
+    }
+
+    /// The envelope period (EP) `base` sped up or slowed down by the envelope rate
+    /// modulation. The envelope can't be slowed to a standstill, only to 1/256 of its speed.
+    pub fn envelope_period(&self, base: u16) -> u16 {
+        let speed = (256 + self.envelope_rate as i32).max(1) as u32;
+        (base as u32 * 256 / speed).clamp(1, u16::MAX as u32) as u16
+    }
+}
+
+/// Plays a [ModMatrix] for one voice: keeps the values of the sources and runs the LFOs
+/// and envelope.
+///
+/// Example:
+/// ```no_run
+/// let mut modulator = Modulator::new(LEAD);
+///
+/// // MIDI
+/// modulator.note_on(velocity);
+/// chip.play_note(AudioChannel::A, Note::from_midi(key));
+/// modulator.set_mod_wheel(value);
+///
+/// // Every tick
+/// let modulation = modulator.tick();
+/// chip.set_pitch_modulation(AudioChannel::A, modulation.pitch);
+/// chip.volume(AudioChannel::A, modulation.level(9));
+/// chip.set_noise_freq(modulation.noise_period(12));
+/// chip.tick();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Modulator<'a> {
+    matrix: ModMatrix<'a>,
+    velocity: u8,
+    aftertouch: u8,
+    mod_wheel: u8,
+    lfo_phases: [u8; 2],
+    envelope_position: usize,
+}
+
+impl<'a> Modulator<'a> {
+    /// A modulator with every source at 0.
+    pub fn new(matrix: ModMatrix<'a>) -> Self {
+        Self {
+            matrix,
+            velocity: 0,
+            aftertouch: 0,
+            mod_wheel: 0,
+            lfo_phases: [0; 2],
+            envelope_position: 0,
+        }
+    }
+
+    /// Switch to another instrument, keeping the source values.
+    pub fn set_matrix(&mut self, matrix: ModMatrix<'a>) {
+        self.matrix = matrix;
+    }
+
+    /// Start a note: set the velocity and restart the envelope.
+    pub fn note_on(&mut self, velocity: u8) {
+        self.velocity = velocity.min(127);
+        self.envelope_position = 0;
+    }
+
+    pub fn set_aftertouch(&mut self, pressure: u8) {
+        self.aftertouch = pressure.min(127);
+    }
+
+    pub fn set_mod_wheel(&mut self, value: u8) {
+        self.mod_wheel = value.min(127);
+    }
+
+    /// The current value of a source, out of 127 (or ±127 for LFOs).
+    pub fn source(&self, source: Source) -> i32 {
+        match source {
+            Source::Velocity => self.velocity as i32,
+            Source::Aftertouch => self.aftertouch as i32,
+            Source::ModWheel => self.mod_wheel as i32,
+            Source::Lfo1 => triangle(self.lfo_phases[0]),
+            Source::Lfo2 => triangle(self.lfo_phases[1]),
+            Source::Envelope => self.matrix.envelope.map_or(0, |envelope| {
+                envelope.level(self.envelope_position) as i32 * 127 / 15
+            }),
+        }
+    }
+
+    /// The modulation for this tick, then advance the LFOs and the envelope.
+    pub fn tick(&mut self) -> Modulation {
+        let mut modulation = Modulation::default();
+        for route in self.matrix.routes {
+            let mut amount = route.depth as i32 * self.source(route.source) / 127;
+            if let Some((source, control)) = self.matrix.depth_control {
+                if source == route.source {
+                    amount = amount * self.source(control) / 127;
+                }
+            }
+            let target = match route.destination {
+                Destination::Pitch => &mut modulation.pitch,
+                Destination::Level => &mut modulation.level,
+                Destination::NoisePeriod => &mut modulation.noise_period,
+                Destination::EnvelopeRate => &mut modulation.envelope_rate,
+            };
+            *target = (*target as i32 + amount).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        }
+
+        for (phase, speed) in self.lfo_phases.iter_mut().zip(self.matrix.lfos) {
+            *phase = phase.wrapping_add(speed);
+        }
+        if let Some(envelope) = self.matrix.envelope {
+            self.envelope_position = envelope.next(self.envelope_position);
+        }
+        modulation
+    }
+}
+
+/// Triangle wave from -127 to 127 over a phase of 256.
+fn triangle(phase: u8) -> i32 {
+    let phase = phase as i32;
+    let triangle = if phase < 128 { phase } else { 255 - phase };
+    triangle * 2 - 127
+}
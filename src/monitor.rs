@@ -0,0 +1,143 @@
+//! Watching the chip from the other core, without stalling the one driving it.
+
+use core::sync::atomic::{fence, AtomicU32, Ordering};
+
+use embedded_hal::digital::OutputPin;
+
+use crate::{OutputBus, Stats, YM2149};
+
+/// Tries of a read before giving up, far more than a publish from the other core takes.
+const READ_ATTEMPTS: u32 = 1000;
+
+/// A copy of a chip's [Stats] and registers that another core (or an interrupt) can read
+/// at any time, e.g. for a UI on core 0 while core 1 plays.
+///
+/// The side driving the chip [publishes](ChipMonitor::publish) its state, say once per
+/// tick. Readers never block it: reads are retried if a publish happened meanwhile
+/// (a seqlock). Only atomic loads and stores are used, the M0+ having no compare-and-swap,
+/// so there must be a single publisher.
+///
+/// Retries are bounded: an interrupt that preempted [publish](ChipMonitor::publish) on
+/// the same core would wait for it forever. Reads give `None` instead, try again later.
+///
+/// Example:
+/// ```no_run
+/// static MONITOR: ChipMonitor = ChipMonitor::new();
+///
+/// // Core 1, audio
+/// chip.tick();
+/// MONITOR.publish(&chip);
+///
+/// // Core 0, UI
+/// if let Some(registers) = MONITOR.registers() {
+///     let levels = &registers[8..11];
+/// }
+/// ```
+pub struct ChipMonitor {
+    /// Odd while a publish is in progress
+    sequence: AtomicU32,
+    stats: [AtomicU32; 6],
+    /// The 16 registers, 4 per word
+    registers: [AtomicU32; 4],
+}
+
+impl ChipMonitor {
+    pub const fn new() -> Self {
+        Self {
+            sequence: AtomicU32::new(0),
+            stats: [const { AtomicU32::new(0) }; 6],
+            registers: [const { AtomicU32::new(0) }; 4],
+        }
+    }
+
+    /// Copy the state of the chip, for readers to pick up.
+    ///
+    /// Must only be called from one place at a time.
+    pub fn publish<DATABUS, BC1, BDIR>(&self, chip: &YM2149<DATABUS, BC1, BDIR>)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let stats = chip.stats();
+        let values = [
+            stats.register_writes,
+            stats.frames_applied,
+            stats.frames_skipped,
+            stats.underruns,
+            stats.max_tick_duration,
+            stats.reinitializations,
+        ];
+
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        self.sequence
+            .store(sequence.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        for (slot, value) in self.stats.iter().zip(values) {
+            slot.store(value, Ordering::Relaxed);
+        }
+        for (word, slot) in self.registers.iter().enumerate() {
+            let bytes = core::array::from_fn(|i| chip.register((word * 4 + i) as u8));
+            slot.store(u32::from_le_bytes(bytes), Ordering::Relaxed);
+        }
+
+        self.sequence
+            .store(sequence.wrapping_add(2), Ordering::Release);
+    }
+
+    /// The [Stats] as last published, `None` if a publish kept getting in the way.
+    pub fn stats(&self) -> Option<Stats> {
+        let values = self.read(|| {
+            self.stats
+                .each_ref()
+                .map(|slot| slot.load(Ordering::Relaxed))
+        })?;
+        Some(Stats {
+            register_writes: values[0],
+            frames_applied: values[1],
+            frames_skipped: values[2],
+            underruns: values[3],
+            max_tick_duration: values[4],
+            reinitializations: values[5],
+        })
+    }
+
+    /// The registers as last published (the driver's copy, see [YM2149::register]),
+    /// `None` if a publish kept getting in the way.
+    pub fn registers(&self) -> Option<[u8; 16]> {
+        let words = self.read(|| {
+            self.registers
+                .each_ref()
+                .map(|slot| slot.load(Ordering::Relaxed))
+        })?;
+        let mut registers = [0; 16];
+        for (bytes, word) in registers.chunks_exact_mut(4).zip(words) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        Some(registers)
+    }
+
+    /// Run `read` until it sees a consistent state, up to [READ_ATTEMPTS] times.
+    fn read<T>(&self, read: impl Fn() -> T) -> Option<T> {
+        for _ in 0..READ_ATTEMPTS {
+            let before = self.sequence.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                core::hint::spin_loop();
+                continue;
+            }
+            let value = read();
+            fence(Ordering::Acquire);
+            if self.sequence.load(Ordering::Relaxed) == before {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+impl Default for ChipMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
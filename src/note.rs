@@ -0,0 +1,369 @@
+//! Musical notes, tunings and the fixed-point math turning them into tone periods.
+//!
+//! Everything here is integer-only, since the RP2040's M0+ cores have no FPU.
+
+use core::str::FromStr;
+
+/// Reference pitch (A4) in Hz.
+pub const A4_HZ: u32 = 440;
+
+/// `2^(k/12)` for `k = 0..12` in Q16 fixed-point.
+const SEMITONE_RATIOS: [u32; 12] = [
+    65536, 69433, 73562, 77936, 82570, 87480, 92682, 98193, 104032, 110218, 116772, 123715,
+];
+
+/// An error related to note parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteParseError {
+    InvalidLength,
+    InvalidAccidental,
+    InvalidNote,
+    OctaveOutOfRange,
+}
+
+/// One of the 7 natural note names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteName {
+    C,
+    D,
+    E,
+    F,
+    G,
+    A,
+    B,
+}
+
+impl NoteName {
+    /// Semitones above C in the same octave.
+    pub const fn semitones(self) -> i32 {
+        match self {
+            Self::C => 0,
+            Self::D => 2,
+            Self::E => 4,
+            Self::F => 5,
+            Self::G => 7,
+            Self::A => 9,
+            Self::B => 11,
+        }
+    }
+}
+
+/// An accidental applied to a [NoteName].
+///
+/// Besides the usual sharp and flat, quarter-tone (± half a semitone) accidentals are available.
+/// For anything finer, use [Note::detune].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Accidental {
+    /// `b`
+    Flat,
+    /// `-`, a quarter-tone flat
+    HalfFlat,
+    Natural,
+    /// `+`, a quarter-tone sharp
+    HalfSharp,
+    /// `#`
+    Sharp,
+}
+
+impl Accidental {
+    /// Offset of the accidental in cents.
+    pub const fn cents(self) -> i32 {
+        match self {
+            Self::Flat => -100,
+            Self::HalfFlat => -50,
+            Self::Natural => 0,
+            Self::HalfSharp => 50,
+            Self::Sharp => 100,
+        }
+    }
+}
+
+/// A note in scientific pitch notation (A4 = 440 Hz), with an optional offset in cents.
+///
+/// Example:
+/// ```no_run
+/// let a4: Note = "A4".parse().unwrap();
+/// let c_sharp = Note::new(NoteName::C, Accidental::Sharp, 3);
+/// // A4, 14 cents flat
+/// let a4_flat = a4.detune(-14);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Note {
+    pub name: NoteName,
+    pub accidental: Accidental,
+    pub octave: i8,
+    /// Arbitrary offset in cents, on top of the accidental.
+    pub cents: i16,
+}
+
+impl Note {
+    pub const fn new(name: NoteName, accidental: Accidental, octave: i8) -> Self {
+        Self {
+            name,
+            accidental,
+            octave,
+            cents: 0,
+        }
+    }
+
+    /// The note of a MIDI note number (60 is C4, 69 is A4), spelled with sharps.
+    pub const fn from_midi(key: u8) -> Self {
+        const NAMES: [(NoteName, Accidental); 12] = [
+            (NoteName::C, Accidental::Natural),
+            (NoteName::C, Accidental::Sharp),
+            (NoteName::D, Accidental::Natural),
+            (NoteName::D, Accidental::Sharp),
+            (NoteName::E, Accidental::Natural),
+            (NoteName::F, Accidental::Natural),
+            (NoteName::F, Accidental::Sharp),
+            (NoteName::G, Accidental::Natural),
+            (NoteName::G, Accidental::Sharp),
+            (NoteName::A, Accidental::Natural),
+            (NoteName::A, Accidental::Sharp),
+            (NoteName::B, Accidental::Natural),
+        ];
+        let (name, accidental) = match crate::nth(&NAMES, (key % 12) as usize) {
+            Some(&name) => name,
+            None => (NoteName::C, Accidental::Natural),
+        };
+        Self::new(name, accidental, (key / 12) as i8 - 1)
+    }
+
+    /// Returns the same note shifted by `cents` (saturating).
+    pub const fn detune(self, cents: i16) -> Self {
+        Self {
+            cents: self.cents.saturating_add(cents),
+            ..self
+        }
+    }
+
+    /// Distance from A4 in cents.
+    pub const fn cents_from_a4(&self) -> i32 {
+        (self.octave as i32 - 4) * 1200
+            + (self.name.semitones() - 9) * 100
+            + self.accidental.cents()
+            + self.cents as i32
+    }
+
+    /// Tone period (TP) producing this note with a given master clock frequency.
+    ///
+    /// The result is clamped to the 12 bits the tone registers can hold.
+    pub fn period(&self, master_clock_frequency: u32) -> u16 {
+        period_for_cents(master_clock_frequency, self.cents_from_a4())
+    }
+}
+
+impl FromStr for Note {
+    type Err = NoteParseError;
+
+    /// Parses notes like `"A4"`, `"C#3"`, `"Eb5"` or `"F+2"` (`+`/`-` being quarter-tones).
+    fn from_str(note_s: &str) -> Result<Self, Self::Err> {
+        let (name, accidental, octave) = match *note_s.as_bytes() {
+            [name, octave] => (name, None, octave),
+            [name, accidental, octave] => (name, Some(accidental), octave),
+            _ => return Err(NoteParseError::InvalidLength),
+        };
+
+        let name = match name.to_ascii_uppercase() {
+            b'C' => NoteName::C,
+            b'D' => NoteName::D,
+            b'E' => NoteName::E,
+            b'F' => NoteName::F,
+            b'G' => NoteName::G,
+            b'A' => NoteName::A,
+            b'B' => NoteName::B,
+            _ => return Err(NoteParseError::InvalidNote),
+        };
+
+        let accidental = match accidental {
+            None => Accidental::Natural,
+            Some(b'b') => Accidental::Flat,
+            Some(b'-') => Accidental::HalfFlat,
+            Some(b'+') => Accidental::HalfSharp,
+            Some(b'#') => Accidental::Sharp,
+            Some(_) => return Err(NoteParseError::InvalidAccidental),
+        };
+
+        let octave = match octave {
+            digit @ b'0'..=b'9' => (digit - b'0') as i8,
+            _ => return Err(NoteParseError::OctaveOutOfRange),
+        };
+
+        Ok(Self::new(name, accidental, octave))
+    }
+}
+
+/// A division of the octave (or any other period), used to reach pitches
+/// outside of 12-tone equal temperament.
+///
+/// Example:
+/// ```no_run
+/// // The 5th step of 19-TET above C4
+/// let note = Tuning::Equal(19).note(Note::new(NoteName::C, Accidental::Natural, 4), 5);
+///
+/// // Scala-like table: just intonation major scale, last entry is the period
+/// const JUST: [u16; 7] = [204, 386, 498, 702, 884, 1088, 1200];
+/// let third = Tuning::Table(&JUST).note(root, 2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tuning<'a> {
+    /// `n` equal steps per octave (12 is regular tuning, 19-TET, 24-TET, ...).
+    Equal(u16),
+    /// Cents of each degree above the root, like the pitch lines of a Scala file.
+    /// The unison is implied and the last entry is the period (usually `1200`).
+    Table(&'a [u16]),
+}
+
+impl Tuning<'_> {
+    /// Distance in cents of `degree` steps above (or below, if negative) the root.
+    pub fn cents(&self, degree: i32) -> i32 {
+        match *self {
+            Self::Equal(0) => 0,
+            Self::Equal(divisions) => {
+                let divisions = divisions as i32;
+                // Round to the nearest cent
+                (degree * 2400 + divisions).div_euclid(2 * divisions)
+            }
+            Self::Table(table) => match table.last() {
+                None => 0,
+                Some(&period) => {
+                    let len = table.len() as i32;
+                    let index = degree.rem_euclid(len) as usize;
+                    let base = index
+                        .checked_sub(1)
+                        .and_then(|index| table.get(index))
+                        .copied()
+                        .unwrap_or(0);
+                    degree.div_euclid(len) * period as i32 + base as i32
+                }
+            },
+        }
+    }
+
+    /// The note `degree` steps away from `root`.
+    pub fn note(&self, root: Note, degree: i32) -> Note {
+        let cents =
+            (root.cents as i32 + self.cents(degree)).clamp(i16::MIN as i32, i16::MAX as i32);
+        Note {
+            cents: cents as i16,
+            ..root
+        }
+    }
+}
+
+/// `2^(cents/1200)` for `cents` in `0..100`, in Q16 fixed-point.
+///
+/// Uses the first terms of the Taylor series of `e^x`, which stays well
+/// under a tenth of a cent off in this range.
+const fn fine_ratio(cents: u32) -> u32 {
+    // x = cents * ln(2) / 1200, in Q24
+    let x = cents as u64 * 9691;
+    let ratio = (1 << 24) + x + ((x * x) >> 25) + ((x * x * x / 6) >> 48);
+    (ratio >> 8) as u32
+}
+
+/// Tone period for a pitch `cents_from_a4` cents away from A4.
+pub(crate) const fn period_for_cents(master_clock_frequency: u32, cents_from_a4: i32) -> u16 {
+    let period = (period_q8(master_clock_frequency, cents_from_a4, 16) + 0x80) >> 8;
+    if period < 1 {
+        1
+    } else if period > 0x0FFF {
+        0x0FFF
+    } else {
+        period as u16
+    }
+}
+
+/// `fMaster / (divider * f)` for a pitch `cents_from_a4` cents away from A4, in Q8 fixed-point.
+///
+/// With a `divider` of 16 this is the tone period, the envelope uses 256.
+pub(crate) const fn period_q8(
+    master_clock_frequency: u32,
+    cents_from_a4: i32,
+    divider: u64,
+) -> u64 {
+    let octaves = cents_from_a4.div_euclid(1200);
+    let rest = cents_from_a4.rem_euclid(1200) as u32;
+    // rest is under 1200, so there's always a semitone
+    let semitone = match crate::nth(&SEMITONE_RATIOS, (rest / 100) as usize) {
+        Some(&ratio) => ratio,
+        None => 1 << 16,
+    };
+    let ratio = (semitone as u64 * fine_ratio(rest % 100) as u64) >> 16;
+
+    // f = A4_HZ * ratio * 2^octaves
+    let mut numerator = (master_clock_frequency as u64) << 24;
+    let mut denominator = divider * A4_HZ as u64 * ratio;
+    // Past these shifts the period is out of the 16 bit range anyway
+    if octaves < 0 {
+        numerator <<= if -octaves < 12 { -octaves } else { 12 };
+    } else {
+        denominator <<= if octaves < 24 { octaves } else { 24 };
+    }
+
+    numerator / denominator
+}
+
+/// Number of notes in a [period table](build_period_table): 8 octaves, C1 to B8.
+pub const PERIOD_TABLE_NOTES: usize = 96;
+
+/// Pitch of the first note of a [period table](build_period_table), C1, in cents from A4.
+const PERIOD_TABLE_FIRST: i32 = -4500;
+
+/// The tone period (TP) of every semitone from C1 to B8 for a master clock, worked out at
+/// compile time when used in a `const`.
+///
+/// Set it on the chip with [YM2149::with_period_table](crate::YM2149::with_period_table)
+/// to skip the division on every note.
+///
+/// Example:
+/// ```no_run
+/// // Atari ST and ZX Spectrum 128 clocks
+/// const ST_PERIODS: [u16; PERIOD_TABLE_NOTES] = build_period_table(2_000_000);
+/// const ZX_PERIODS: [u16; PERIOD_TABLE_NOTES] = build_period_table(1_773_400);
+/// ```
+pub const fn build_period_table(master_clock_frequency: u32) -> [u16; PERIOD_TABLE_NOTES] {
+    let mut table = [0; PERIOD_TABLE_NOTES];
+    let mut periods: &mut [u16] = &mut table;
+    let mut cents = PERIOD_TABLE_FIRST;
+    while let [period, rest @ ..] = periods {
+        *period = period_for_cents(master_clock_frequency, cents);
+        cents += 100;
+        periods = rest;
+    }
+    table
+}
+
+/// How far off each note of a period table plays, in ppm of its frequency (positive is sharp).
+///
+/// Periods are whole numbers, so high notes (short periods) are the furthest off: a cent is
+/// about 578 ppm.
+pub const fn period_table_error_ppm(
+    master_clock_frequency: u32,
+    table: &[u16; PERIOD_TABLE_NOTES],
+) -> [i32; PERIOD_TABLE_NOTES] {
+    let mut errors = [0; PERIOD_TABLE_NOTES];
+    let mut slots: &mut [i32] = &mut errors;
+    let mut periods: &[u16] = table;
+    let mut cents = PERIOD_TABLE_FIRST;
+    while let ([error, slots_rest @ ..], [period, periods_rest @ ..]) = (slots, periods) {
+        let exact = period_q8(master_clock_frequency, cents, 16) as i64;
+        let actual = (*period as i64) << 8;
+        if actual != 0 {
+            // f is inversely proportional to the period
+            *error = ((exact - actual) * 1_000_000 / actual) as i32;
+        }
+        cents += 100;
+        (slots, periods) = (slots_rest, periods_rest);
+    }
+    errors
+}
+
+/// The period of `cents_from_a4` in `table`, if it's a semitone the table covers.
+pub(crate) fn table_period(table: &[u16; PERIOD_TABLE_NOTES], cents_from_a4: i32) -> Option<u16> {
+    let offset = cents_from_a4 - PERIOD_TABLE_FIRST;
+    if offset % 100 != 0 {
+        return None;
+    }
+    table.get(usize::try_from(offset / 100).ok()?).copied()
+}
@@ -0,0 +1,282 @@
+//! Ornaments: looping tables of semitone offsets, the usual way to get arpeggios
+//! and chords out of a single AY channel, and their mixer and level counterparts.
+
+/// A table of semitone offsets applied to a channel's note, one entry per
+/// [tick](crate::YM2149::tick), as in Vortex Tracker and other PT3 / STC trackers.
+///
+/// When the end of the table is reached, playback continues from the loop position.
+/// Set one on a channel with [YM2149::set_ornament](crate::YM2149::set_ornament).
+///
+/// Example:
+/// ```no_run
+/// // Major chord arpeggio
+/// const MAJOR: Ornament = Ornament::new(&[0, 4, 7]);
+/// // Octave jump on the attack, then stay on the note
+/// const PLUCK: Ornament = Ornament::new(&[12, 12, 0]).with_loop(2);
+///
+/// chip.set_ornament(AudioChannel::A, Some(MAJOR));
+/// chip.note(AudioChannel::A, "C4")?; // C4, E4, G4, C4, E4... every tick
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ornament {
+    offsets: &'static [i8],
+    loop_start: usize,
+}
+
+impl Ornament {
+    /// An ornament looping over the whole table.
+    pub const fn new(offsets: &'static [i8]) -> Self {
+        Self {
+            offsets,
+            loop_start: 0,
+        }
+    }
+
+    /// Set the position playback jumps back to after the last offset.
+    ///
+    /// Positions past the end of the table loop on the last offset.
+    pub const fn with_loop(mut self, loop_start: usize) -> Self {
+        self.loop_start = if loop_start < self.offsets.len() {
+            loop_start
+        } else {
+            self.offsets.len().saturating_sub(1)
+        };
+        self
+    }
+
+    /// The table of semitone offsets.
+    pub const fn offsets(&self) -> &'static [i8] {
+        self.offsets
+    }
+
+    pub const fn loop_start(&self) -> usize {
+        self.loop_start
+    }
+
+    /// Offset in semitones at `position`, 0 for an empty table.
+    pub fn offset(&self, position: usize) -> i8 {
+        self.offsets.get(position).copied().unwrap_or(0)
+    }
+
+    /// The position following `position`, looping at the end of the table.
+    pub fn next(&self, position: usize) -> usize {
+        if position + 1 < self.offsets.len() {
+            position + 1
+        } else {
+            self.loop_start
+        }
+    }
+}
+
+/// What a channel outputs: its tone, its noise, both or neither.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mix {
+    Off,
+    Tone,
+    Noise,
+    Both,
+}
+
+impl Mix {
+    pub const fn tone(self) -> bool {
+        matches!(self, Self::Tone | Self::Both)
+    }
+
+    pub const fn noise(self) -> bool {
+        matches!(self, Self::Noise | Self::Both)
+    }
+}
+
+/// A looping table of [Mix] settings for a channel, one entry per
+/// [tick](crate::YM2149::tick), like duty macros in Famitracker.
+///
+/// Loops like an [Ornament]. Set one on a channel with
+/// [YM2149::set_mixer_macro](crate::YM2149::set_mixer_macro).
+///
+/// Example:
+/// ```no_run
+/// use Mix::*;
+/// // Snare: a noisy attack, then the tone alone
+/// const SNARE: MixerMacro = MixerMacro::new(&[Both, Noise, Noise, Tone]).with_loop(3);
+/// // "Snarl" bass: noise flickering on the tone
+/// const SNARL: MixerMacro = MixerMacro::new(&[Both, Tone]);
+///
+/// chip.set_mixer_macro(AudioChannel::B, Some(SNARL));
+/// chip.note(AudioChannel::B, "E2")?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MixerMacro {
+    steps: &'static [Mix],
+    loop_start: usize,
+}
+
+impl MixerMacro {
+    /// A macro looping over the whole table.
+    pub const fn new(steps: &'static [Mix]) -> Self {
+        Self {
+            steps,
+            loop_start: 0,
+        }
+    }
+
+    /// Set the position playback jumps back to after the last step.
+    ///
+    /// Positions past the end of the table loop on the last step.
+    pub const fn with_loop(mut self, loop_start: usize) -> Self {
+        self.loop_start = if loop_start < self.steps.len() {
+            loop_start
+        } else {
+            self.steps.len().saturating_sub(1)
+        };
+        self
+    }
+
+    /// The table of steps.
+    pub const fn steps(&self) -> &'static [Mix] {
+        self.steps
+    }
+
+    pub const fn loop_start(&self) -> usize {
+        self.loop_start
+    }
+
+    /// Step at `position`, `None` for an empty table.
+    pub fn step(&self, position: usize) -> Option<Mix> {
+        self.steps.get(position).copied()
+    }
+
+    /// The position following `position`, looping at the end of the table.
+    pub fn next(&self, position: usize) -> usize {
+        if position + 1 < self.steps.len() {
+            position + 1
+        } else {
+            self.loop_start
+        }
+    }
+}
+
+/// A looping table of levels (0 - 15) for a channel, one entry per
+/// [tick](crate::YM2149::tick): a software volume envelope, for shapes the hardware
+/// envelope can't make, or different ones on each channel.
+///
+/// Loops like an [Ornament]; loop on the last step to hold it. Unlike ornaments the table
+/// is kept inline, so it can be built or reshaped at run time. Set one on a channel with
+/// [YM2149::set_software_envelope](crate::YM2149::set_software_envelope).
+///
+/// Example:
+/// ```no_run
+/// // Pluck: fast attack, decay to a sustain of 6
+/// const PLUCK: RawEnvelope = RawEnvelope::from_slice(&[15, 13, 11, 9, 8, 7, 6]).with_loop(6);
+/// // Tremolo, built from a function
+/// let tremolo = RawEnvelope::from_fn(16, |i| if i < 8 { 12 } else { 8 });
+///
+/// chip.set_software_envelope(AudioChannel::A, Some(PLUCK.scale(192)));
+/// chip.note(AudioChannel::A, "G3")?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawEnvelope {
+    levels: [u8; RawEnvelope::CAPACITY],
+    len: usize,
+    loop_start: usize,
+}
+
+impl RawEnvelope {
+    /// Most levels an envelope holds, longer tables are cut.
+    pub const CAPACITY: usize = 64;
+
+    /// An envelope looping over `levels`. Levels over 15 are clamped.
+    pub const fn from_slice(levels: &[u8]) -> Self {
+        let len = if levels.len() < Self::CAPACITY {
+            levels.len()
+        } else {
+            Self::CAPACITY
+        };
+        let mut table = [0; Self::CAPACITY];
+        let mut slots: &mut [u8] = &mut table;
+        let mut levels = levels;
+        while let ([slot, slots_rest @ ..], [level, levels_rest @ ..]) = (slots, levels) {
+            *slot = if *level > 15 { 15 } else { *level };
+            (slots, levels) = (slots_rest, levels_rest);
+        }
+        Self {
+            levels: table,
+            len,
+            loop_start: 0,
+        }
+    }
+
+    /// An envelope of `len` levels, `level(i)` giving the level at step `i`.
+    /// Levels over 15 are clamped.
+    pub fn from_fn(len: usize, mut level: impl FnMut(usize) -> u8) -> Self {
+        let mut envelope = Self::from_slice(&[]);
+        envelope.len = len.min(Self::CAPACITY);
+        let slots = envelope.levels.iter_mut().take(envelope.len);
+        for (i, slot) in slots.enumerate() {
+            *slot = level(i).min(15);
+        }
+        envelope
+    }
+
+    /// Set the position playback jumps back to after the last level.
+    ///
+    /// Positions past the end of the table loop on the last level.
+    pub const fn with_loop(mut self, loop_start: usize) -> Self {
+        self.loop_start = if loop_start < self.len {
+            loop_start
+        } else {
+            self.len.saturating_sub(1)
+        };
+        self
+    }
+
+    /// The table of levels.
+    pub fn levels(&self) -> &[u8] {
+        self.levels.get(..self.len).unwrap_or(&[])
+    }
+
+    pub const fn loop_start(&self) -> usize {
+        self.loop_start
+    }
+
+    /// Level at `position`, 0 for an empty table.
+    pub fn level(&self, position: usize) -> u8 {
+        self.levels().get(position).copied().unwrap_or(0)
+    }
+
+    /// The position following `position`, looping at the end of the table.
+    pub fn next(&self, position: usize) -> usize {
+        if position + 1 < self.len {
+            position + 1
+        } else {
+            self.loop_start
+        }
+    }
+
+    /// The same envelope, `steps` levels louder (or quieter), staying within 0 - 15.
+    pub fn offset(mut self, steps: i8) -> Self {
+        for level in self.levels.iter_mut().take(self.len) {
+            *level = (*level as i16 + steps as i16).clamp(0, 15) as u8;
+        }
+        self
+    }
+
+    /// The same envelope with every level multiplied by `factor`/256, rounded and capped
+    /// at 15.
+    pub fn scale(mut self, factor: u16) -> Self {
+        for level in self.levels.iter_mut().take(self.len) {
+            *level = ((*level as u32 * factor as u32 + 0x80) >> 8).min(15) as u8;
+        }
+        self
+    }
+
+    /// The same envelope stretched or squeezed to `len` steps (at most
+    /// [CAPACITY](RawEnvelope::CAPACITY)), moving the loop along.
+    pub fn resample(self, len: usize) -> Self {
+        if self.len == 0 {
+            return self;
+        }
+        let len = len.min(Self::CAPACITY);
+        Self::from_fn(len, |i| self.level(i * self.len / len))
+            .with_loop(self.loop_start * len / self.len)
+    }
+}
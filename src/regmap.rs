@@ -0,0 +1,210 @@
+//! The register map as data, for debug UIs and tools that pretty-print or check raw writes.
+
+use crate::Register;
+
+/// A group of bits of a register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    /// Bits of the field in the register
+    pub mask: u8,
+}
+
+impl Field {
+    const fn new(name: &'static str, mask: u8) -> Self {
+        Self { name, mask }
+    }
+
+    /// The value of the field in a register value, shifted down.
+    pub const fn get(&self, value: u8) -> u8 {
+        (value & self.mask) >> self.mask.trailing_zeros()
+    }
+}
+
+/// What a register holds, see [Register::info].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterInfo {
+    pub register: Register,
+    /// Short name, as in the datasheet (`R0`... `R15` aside)
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Bits the chip keeps, the others read back as 0
+    pub mask: u8,
+    /// Fields, from the most significant bits down
+    pub fields: &'static [Field],
+}
+
+impl RegisterInfo {
+    /// Whether `value` only uses bits the register has.
+    pub const fn is_valid(&self, value: u8) -> bool {
+        value & !self.mask == 0
+    }
+}
+
+const FINE_TONE: &[Field] = &[Field::new("TP7-0", 0xFF)];
+const ROUGH_TONE: &[Field] = &[Field::new("TP11-8", 0x0F)];
+const LEVEL: &[Field] = &[Field::new("M", 0x10), Field::new("L3-0", 0x0F)];
+
+const fn info(
+    register: Register,
+    name: &'static str,
+    description: &'static str,
+    mask: u8,
+    fields: &'static [Field],
+) -> RegisterInfo {
+    RegisterInfo {
+        register,
+        name,
+        description,
+        mask,
+        fields,
+    }
+}
+
+/// Every register, in order, so `REGISTERS[n]` describes R`n`.
+pub const REGISTERS: [RegisterInfo; 16] = [
+    info(
+        Register::AFreq8bitFinetone,
+        "A fine tone",
+        "Tone period of channel A, lower 8 bits",
+        0xFF,
+        FINE_TONE,
+    ),
+    info(
+        Register::AFreq4bitRoughtone,
+        "A rough tone",
+        "Tone period of channel A, upper 4 bits",
+        0x0F,
+        ROUGH_TONE,
+    ),
+    info(
+        Register::BFreq8bitFinetone,
+        "B fine tone",
+        "Tone period of channel B, lower 8 bits",
+        0xFF,
+        FINE_TONE,
+    ),
+    info(
+        Register::BFreq4bitRoughtone,
+        "B rough tone",
+        "Tone period of channel B, upper 4 bits",
+        0x0F,
+        ROUGH_TONE,
+    ),
+    info(
+        Register::CFreq8bitFinetone,
+        "C fine tone",
+        "Tone period of channel C, lower 8 bits",
+        0xFF,
+        FINE_TONE,
+    ),
+    info(
+        Register::CFreq4bitRoughtone,
+        "C rough tone",
+        "Tone period of channel C, upper 4 bits",
+        0x0F,
+        ROUGH_TONE,
+    ),
+    info(
+        Register::NoiseFreq5bit,
+        "Noise period",
+        "Period of the noise generator",
+        0x1F,
+        &[Field::new("NP4-0", 0x1F)],
+    ),
+    info(
+        Register::IoPortMixerSettings,
+        "Mixer",
+        "Tone and noise of each channel (0 enables), I/O port directions (1 is output)",
+        0xFF,
+        &[
+            Field::new("IOB", 0x80),
+            Field::new("IOA", 0x40),
+            Field::new("Noise C", 0x20),
+            Field::new("Noise B", 0x10),
+            Field::new("Noise A", 0x08),
+            Field::new("Tone C", 0x04),
+            Field::new("Tone B", 0x02),
+            Field::new("Tone A", 0x01),
+        ],
+    ),
+    info(
+        Register::ALevel,
+        "A level",
+        "Level of channel A, or the envelope if M is set",
+        0x1F,
+        LEVEL,
+    ),
+    info(
+        Register::BLevel,
+        "B level",
+        "Level of channel B, or the envelope if M is set",
+        0x1F,
+        LEVEL,
+    ),
+    info(
+        Register::CLevel,
+        "C level",
+        "Level of channel C, or the envelope if M is set",
+        0x1F,
+        LEVEL,
+    ),
+    info(
+        Register::EFreq8bitFineAdj,
+        "Envelope fine",
+        "Envelope period, lower 8 bits",
+        0xFF,
+        &[Field::new("EP7-0", 0xFF)],
+    ),
+    info(
+        Register::EFreq8bitRoughAdj,
+        "Envelope rough",
+        "Envelope period, upper 8 bits",
+        0xFF,
+        &[Field::new("EP15-8", 0xFF)],
+    ),
+    info(
+        Register::EShape,
+        "Envelope shape",
+        "Shape of the envelope, writing it restarts the envelope",
+        0x0F,
+        &[
+            Field::new("CONT", 0x08),
+            Field::new("ATT", 0x04),
+            Field::new("ALT", 0x02),
+            Field::new("HOLD", 0x01),
+        ],
+    ),
+    info(
+        Register::DataIoA,
+        "I/O port A",
+        "Data of I/O port A",
+        0xFF,
+        &[Field::new("IOA7-0", 0xFF)],
+    ),
+    info(
+        Register::DataIoB,
+        "I/O port B",
+        "Data of I/O port B",
+        0xFF,
+        &[Field::new("IOB7-0", 0xFF)],
+    ),
+];
+
+impl Register {
+    /// Name, bit fields and valid bits of the register.
+    ///
+    /// Example:
+    /// ```no_run
+    /// let info = Register::IoPortMixerSettings.info();
+    /// for field in info.fields {
+    ///     defmt::info!("{}: {}", field.name, field.get(chip.register(info.register)));
+    /// }
+    /// ```
+    pub const fn info(self) -> &'static RegisterInfo {
+        match crate::nth(&REGISTERS, self as usize) {
+            Some(info) => info,
+            None => &REGISTERS[0],
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! One-shot events scheduled on a tick counter, e.g. "play this jingle in 500 ms".
+
+/// A bounded queue of events of type `E`, each due at a given tick.
+///
+/// Call [tick](Scheduler::tick) from the same steady timer as [YM2149::tick](crate::YM2149::tick),
+/// then take the events that are due with [pop_due](Scheduler::pop_due).
+/// Events due at the same tick come out in the order they were scheduled.
+///
+/// Example:
+/// ```no_run
+/// enum Event {
+///     Fanfare,
+///     Silence,
+/// }
+///
+/// // 50 Hz ticks, up to 8 pending events
+/// let mut scheduler: Scheduler<Event, 8> = Scheduler::new(50);
+/// scheduler.after(500, Event::Fanfare).ok();
+///
+/// // In the 50 Hz timer interrupt
+/// scheduler.tick();
+/// while let Some(event) = scheduler.pop_due() {
+///     match event {
+///         Event::Fanfare => chip.note(AudioChannel::C, "C5")?,
+///         Event::Silence => chip.silence(),
+///     }
+/// }
+/// ```
+pub struct Scheduler<E, const N: usize> {
+    tick_rate: u32,
+    now: u32,
+    /// Scheduling order, to break ties between events due at the same tick
+    sequence: u32,
+    events: [Option<Scheduled<E>>; N],
+}
+
+struct Scheduled<E> {
+    tick: u32,
+    sequence: u32,
+    event: E,
+}
+
+impl<E, const N: usize> Scheduler<E, N> {
+    /// An empty scheduler for a timer ticking `tick_rate` times per second.
+    pub const fn new(tick_rate: u32) -> Self {
+        Self {
+            tick_rate,
+            now: 0,
+            sequence: 0,
+            events: [const { None }; N],
+        }
+    }
+
+    /// The current tick. Wraps around on overflow.
+    pub fn now(&self) -> u32 {
+        self.now
+    }
+
+    /// Advance the clock by one tick.
+    pub fn tick(&mut self) {
+        self.now = self.now.wrapping_add(1);
+    }
+
+    /// Schedule `event` at an absolute `tick`, giving it back if the queue is full.
+    ///
+    /// Ticks up to 2^31 in the past are due right away.
+    pub fn at(&mut self, tick: u32, event: E) -> Result<(), E> {
+        let Some(slot) = self.events.iter_mut().find(|slot| slot.is_none()) else {
+            return Err(event);
+        };
+        *slot = Some(Scheduled {
+            tick,
+            sequence: self.sequence,
+            event,
+        });
+        self.sequence = self.sequence.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Schedule `event` `ms` milliseconds from now, rounded to the nearest tick.
+    pub fn after(&mut self, ms: u32, event: E) -> Result<(), E> {
+        let ticks = (ms as u64 * self.tick_rate as u64 + 500) / 1000;
+        self.at(self.now.wrapping_add(ticks as u32), event)
+    }
+
+    /// Take the next event that is due, if any.
+    pub fn pop_due(&mut self) -> Option<E> {
+        let (now, sequence) = (self.now, self.sequence);
+        // Relative to now, so wrapping around doesn't matter
+        let lateness = |scheduled: &Scheduled<E>| now.wrapping_sub(scheduled.tick) as i32;
+
+        let slot = self
+            .events
+            .iter_mut()
+            .filter(|slot| slot.as_ref().is_some_and(|s| lateness(s) >= 0))
+            .max_by_key(|slot| {
+                slot.as_ref()
+                    .map(|s| (lateness(s), sequence.wrapping_sub(s.sequence)))
+            })?;
+        slot.take().map(|scheduled| scheduled.event)
+    }
+
+    /// Number of pending events.
+    pub fn len(&self) -> usize {
+        self.events.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every pending event.
+    pub fn clear(&mut self) {
+        self.events.iter_mut().for_each(|slot| *slot = None);
+    }
+}
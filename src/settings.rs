@@ -0,0 +1,212 @@
+//! Settings surviving power cycles, e.g. for jukebox builds.
+
+use embedded_storage::nor_flash::NorFlash;
+
+/// Where [Settings] are kept: anything that can load and save a small blob.
+///
+/// [FlashStore] keeps them in a sector of NOR flash, e.g. the RP2040's own.
+pub trait SettingsStore {
+    type Error;
+
+    /// Fill `blob` with the saved bytes. Whatever was never saved may be anything.
+    fn load(&mut self, blob: &mut [u8]) -> Result<(), Self::Error>;
+
+    fn save(&mut self, blob: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Player settings worth keeping between power cycles.
+///
+/// Only the [pitch shift](crate::YM2149::set_pitch_shift) means something to the driver,
+/// the rest is kept for the application.
+///
+/// Example:
+/// ```no_run
+/// let mut store = FlashStore::new(flash, 0x1F_F000);
+/// let mut settings = Settings::load(&mut store)?.unwrap_or_default();
+/// chip.set_pitch_shift(settings.pitch_shift);
+///
+/// // When the user picks another song
+/// settings.song = 4;
+/// settings.save(&mut store)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    /// Master volume, 0 - 15
+    pub volume: u8,
+    /// Tuning reference, in cents away from A4 = 440 Hz
+    pub pitch_shift: i32,
+    /// Tempo, in whatever unit the application uses (e.g. BPM or ticks per row)
+    pub tempo: u16,
+    /// Last song played
+    pub song: u16,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 15,
+            pitch_shift: 0,
+            tempo: 125,
+            song: 0,
+        }
+    }
+}
+
+const MAGIC: [u8; 2] = *b"YM";
+const VERSION: u8 = 1;
+
+impl Settings {
+    /// Size of the saved blob, in bytes.
+    pub const SIZE: usize = 13;
+
+    /// Load the settings, `None` if none were saved yet (or they were damaged).
+    pub fn load<S: SettingsStore>(store: &mut S) -> Result<Option<Self>, S::Error> {
+        let mut blob = [0; Self::SIZE];
+        store.load(&mut blob)?;
+        Ok(Self::from_bytes(&blob))
+    }
+
+    pub fn save<S: SettingsStore>(&self, store: &mut S) -> Result<(), S::Error> {
+        store.save(&self.to_bytes())
+    }
+
+    /// The blob saved to the store: magic, version, fields in little endian, checksum.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut blob = [0; Self::SIZE];
+        blob[..2].copy_from_slice(&MAGIC);
+        blob[2] = VERSION;
+        blob[3] = self.volume;
+        blob[4..8].copy_from_slice(&self.pitch_shift.to_le_bytes());
+        blob[8..10].copy_from_slice(&self.tempo.to_le_bytes());
+        blob[10..12].copy_from_slice(&self.song.to_le_bytes());
+        blob[12] = checksum(&blob[..12]);
+        blob
+    }
+
+    /// Settings from a saved blob, `None` if it isn't valid.
+    pub fn from_bytes(blob: &[u8; Self::SIZE]) -> Option<Self> {
+        if blob[..2] != MAGIC || blob[2] != VERSION || blob[12] != checksum(&blob[..12]) {
+            return None;
+        }
+        Some(Self {
+            volume: blob[3],
+            pitch_shift: i32::from_le_bytes([blob[4], blob[5], blob[6], blob[7]]),
+            tempo: u16::from_le_bytes([blob[8], blob[9]]),
+            song: u16::from_le_bytes([blob[10], blob[11]]),
+        })
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0xA5u8, |sum, &byte| sum.rotate_left(1) ^ byte)
+}
+
+/// A [SettingsStore] in one erase sector of NOR flash.
+///
+/// `offset` is where the sector starts, counted from the start of the flash: on the RP2040
+/// that's the address minus `0x1000_0000` (where the flash is mapped), and sectors are
+/// 4 KiB, so e.g. `0x1F_F000` for the last one of a 2 MB flash. It must be a multiple of
+/// [ERASE_SIZE](NorFlash::ERASE_SIZE), in a sector the firmware doesn't use. Blobs of any
+/// length up to a sector are padded to whole [WRITE_SIZE](NorFlash::WRITE_SIZE)s (up to
+/// 256 bytes, a page on the RP2040).
+///
+/// Every save erases the sector, so keep saves to user actions: flash only takes so many
+/// erase cycles. On the RP2040 the [NorFlash] implementation must also run erases and
+/// writes from RAM, with interrupts off and the other core parked, since the code runs
+/// from the flash being written.
+pub struct FlashStore<F> {
+    flash: F,
+    offset: u32,
+}
+
+/// An error of a [FlashStore].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashStoreError<E> {
+    /// The flash failed
+    Flash(E),
+    /// The offset isn't the start of a sector
+    Misaligned,
+    /// The blob doesn't fit in a sector
+    TooLarge,
+    /// The flash reads or writes more than 256 bytes at a time
+    Unsupported,
+}
+
+/// Largest read or write size of the flash a [FlashStore] can pad to.
+const MAX_ACCESS_SIZE: usize = 256;
+
+impl<F: NorFlash> FlashStore<F> {
+    pub fn new(flash: F, offset: u32) -> Self {
+        Self { flash, offset }
+    }
+
+    /// Get back the flash.
+    pub fn release(self) -> F {
+        self.flash
+    }
+
+    fn check(&self, blob: &[u8]) -> Result<(), FlashStoreError<F::Error>> {
+        if F::READ_SIZE > MAX_ACCESS_SIZE || F::WRITE_SIZE > MAX_ACCESS_SIZE {
+            return Err(FlashStoreError::Unsupported);
+        }
+        if !(self.offset as usize).is_multiple_of(F::ERASE_SIZE) {
+            return Err(FlashStoreError::Misaligned);
+        }
+        if blob.len() > F::ERASE_SIZE {
+            return Err(FlashStoreError::TooLarge);
+        }
+        Ok(())
+    }
+}
+
+impl<F: NorFlash> SettingsStore for FlashStore<F> {
+    type Error = FlashStoreError<F::Error>;
+
+    fn load(&mut self, blob: &mut [u8]) -> Result<(), Self::Error> {
+        self.check(blob)?;
+        // Reads must be whole multiples of READ_SIZE, read the tail through a buffer
+        let whole = blob.len() / F::READ_SIZE * F::READ_SIZE;
+        let (head, tail) = blob.split_at_mut(whole);
+        self.flash
+            .read(self.offset, head)
+            .map_err(FlashStoreError::Flash)?;
+        if !tail.is_empty() {
+            let mut buffer = [0; MAX_ACCESS_SIZE];
+            let chunk = buffer.get_mut(..F::READ_SIZE).unwrap_or_default();
+            self.flash
+                .read(self.offset + whole as u32, chunk)
+                .map_err(FlashStoreError::Flash)?;
+            for (byte, &value) in tail.iter_mut().zip(chunk.iter()) {
+                *byte = value;
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&mut self, blob: &[u8]) -> Result<(), Self::Error> {
+        self.check(blob)?;
+        self.flash
+            .erase(self.offset, self.offset + F::ERASE_SIZE as u32)
+            .map_err(FlashStoreError::Flash)?;
+
+        // Writes must be whole multiples of WRITE_SIZE, pad the tail with erased bytes
+        let whole = blob.len() / F::WRITE_SIZE * F::WRITE_SIZE;
+        let (head, tail) = blob.split_at(whole);
+        self.flash
+            .write(self.offset, head)
+            .map_err(FlashStoreError::Flash)?;
+        if !tail.is_empty() {
+            let mut buffer = [0xFF; MAX_ACCESS_SIZE];
+            for (byte, &value) in buffer.iter_mut().zip(tail) {
+                *byte = value;
+            }
+            let chunk = buffer.get(..F::WRITE_SIZE).unwrap_or_default();
+            self.flash
+                .write(self.offset + whole as u32, chunk)
+                .map_err(FlashStoreError::Flash)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+//! Sound effects for games: short sounds that take a channel over from the music,
+//! then give it back as it was.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::voice::{Chips, VoiceId, Voices};
+use crate::{AudioChannel, Level, OutputBus, Register, YM2149};
+
+/// A value going linearly from `from` to `to` over the length of a [Sfx], or every
+/// `cycle` ticks if set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sweep {
+    pub from: u16,
+    pub to: u16,
+    /// Restart from `from` every `cycle` ticks, 0 to sweep once over the whole effect
+    pub cycle: u16,
+}
+
+impl Sweep {
+    pub const fn new(from: u16, to: u16) -> Self {
+        Self { from, to, cycle: 0 }
+    }
+
+    /// The same sweep, restarting every `cycle` ticks.
+    pub const fn repeat(self, cycle: u16) -> Self {
+        Self { cycle, ..self }
+    }
+
+    /// A value that doesn't move.
+    pub const fn constant(value: u16) -> Self {
+        Self::new(value, value)
+    }
+
+    /// Value at `tick` out of `duration`.
+    fn at(&self, tick: u16, duration: u16) -> u16 {
+        let (tick, duration) = match self.cycle {
+            0 => (tick, duration),
+            cycle => (tick % cycle, cycle),
+        };
+        if duration == 0 {
+            return self.to;
+        }
+        let delta = (self.to as i32 - self.from as i32) * tick as i32 / duration as i32;
+        (self.from as i32 + delta) as u16
+    }
+}
+
+/// A sound effect, played by a [SfxManager].
+///
+/// Every [tick](SfxManager::tick), the tone period, noise period and level of the channel
+/// follow their [Sweep]. Leave `tone` or `noise` out to disable it on the channel.
+///
+/// A few classic ones are ready to use, e.g. [Sfx::laser] or [Sfx::explosion]. Their tone
+/// periods are picked for a 2 MHz master clock, pitches scale with it.
+///
+/// Example:
+/// ```no_run
+/// // Short rising blip
+/// const COIN: Sfx = Sfx {
+///     priority: 1,
+///     duration: 6,
+///     tone: Some(Sweep::new(180, 60)),
+///     noise: None,
+///     level: Sweep::new(15, 8),
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sfx {
+    /// An effect only cuts off a playing one of lower or equal priority
+    pub priority: u8,
+    /// Length in ticks
+    pub duration: u16,
+    /// Tone period (TP)
+    pub tone: Option<Sweep>,
+    /// Noise period, 5 bits. The noise generator is shared by all channels
+    pub noise: Option<Sweep>,
+    /// Channel level, 0 - 15
+    pub level: Sweep,
+}
+
+// Presets for the usual game sounds
+impl Sfx {
+    /// The same effect with another priority.
+    pub const fn with_priority(self, priority: u8) -> Self {
+        Self { priority, ..self }
+    }
+
+    /// A falling "pew", `duration` ticks long.
+    pub const fn laser(duration: u16) -> Self {
+        Self {
+            priority: 1,
+            duration,
+            tone: Some(Sweep::new(40, 400)),
+            noise: None,
+            level: Sweep::new(15, 4),
+        }
+    }
+
+    /// A noise burst fading out over `duration` ticks, getting duller as it goes.
+    pub const fn explosion(duration: u16) -> Self {
+        Self {
+            priority: 2,
+            duration,
+            tone: None,
+            noise: Some(Sweep::new(6, 31)),
+            level: Sweep::new(15, 0),
+        }
+    }
+
+    /// A quick rising blip (coins, items), starting from tone period `period`.
+    pub const fn pickup(period: u16) -> Self {
+        Self {
+            priority: 1,
+            duration: 6,
+            tone: Some(Sweep::new(period, period / 3)),
+            noise: None,
+            level: Sweep::new(14, 10),
+        }
+    }
+
+    /// A rising slide, `duration` ticks long.
+    pub const fn jump(duration: u16) -> Self {
+        Self {
+            priority: 1,
+            duration,
+            tone: Some(Sweep::new(400, 120)),
+            noise: None,
+            level: Sweep::new(13, 6),
+        }
+    }
+
+    /// A "whoop" alarm rising every `cycle` ticks, `cycles` times.
+    pub const fn siren(cycle: u16, cycles: u16) -> Self {
+        Self {
+            priority: 0,
+            duration: cycle.saturating_mul(cycles),
+            tone: Some(Sweep::new(280, 110).repeat(cycle)),
+            noise: None,
+            level: Sweep::constant(13),
+        }
+    }
+}
+
+/// What a channel looked like before an effect took it over.
+#[derive(Debug, Clone, Copy)]
+struct Saved {
+    tone: [u8; 2],
+    noise: u8,
+    level: u8,
+    mixer: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Playing {
+    sfx: Sfx,
+    tick: u16,
+    saved: Saved,
+}
+
+/// Plays [Sfx] on top of the music, one per voice at most, on `N` voices (3 by default, a
+/// single chip). See [Chips] for several chips.
+///
+/// Starting an effect saves the channel's registers (tone, level, mixer bits, and the
+/// shared noise period), which are written back when it ends. Meanwhile the channel's
+/// vibrato, ornament, glide, mixer macro and software envelope are on hold, so
+/// [YM2149::tick] doesn't write over the effect, and [YM2149::apply_frame] skips the channel.
+/// Anything else playing music must leave the channel alone too: check
+/// [is_playing](SfxManager::is_playing) before writing to it.
+///
+/// Example:
+/// ```no_run
+/// let mut sfx = SfxManager::new();
+///
+/// // When the player picks up a coin
+/// sfx.play(&mut chip, AudioChannel::C, COIN);
+///
+/// // 50 Hz timer
+/// sfx.tick(&mut chip);
+/// for channel in AudioChannel::ALL {
+///     if !sfx.is_playing(channel) {
+///         play_music(&mut chip, channel);
+///     }
+/// }
+/// ```
+pub struct SfxManager<const N: usize = 3> {
+    playing: Voices<Option<Playing>, N>,
+}
+
+impl SfxManager {
+    /// A manager for the 3 channels of a single chip.
+    pub const fn new() -> Self {
+        Self::with_voices()
+    }
+}
+
+impl<const N: usize> SfxManager<N> {
+    /// A manager for `N` voices.
+    pub const fn with_voices() -> Self {
+        Self {
+            playing: Voices::new(None),
+        }
+    }
+
+    /// Whether an effect is playing on a voice.
+    pub fn is_playing(&self, voice: impl Into<VoiceId>) -> bool {
+        self.playing.get(voice).is_some_and(Option::is_some)
+    }
+
+    /// Start an effect on a voice.
+    ///
+    /// Returns `false` (and does nothing) if an effect of higher priority is playing there,
+    /// or the voice is past the last chip.
+    /// An effect cutting off another one still restores the channel as it was before the first.
+    pub fn play<C: Chips>(&mut self, chips: &mut C, voice: impl Into<VoiceId>, sfx: Sfx) -> bool {
+        let voice = voice.into();
+        let Some(slot) = self.playing.get_mut(voice) else {
+            return false;
+        };
+        let Some((chip, channel)) = chips.voice(voice) else {
+            return false;
+        };
+        let saved = match *slot {
+            Some(playing) if playing.sfx.priority > sfx.priority => return false,
+            Some(playing) => playing.saved,
+            None => {
+                let tone = chip.output_channel(channel) as u8 * 2;
+                Saved {
+                    tone: [chip.register(tone), chip.register(tone + 1)],
+                    noise: chip.register(Register::NoiseFreq5bit),
+                    level: chip.register(chip.level_register(channel)),
+                    mixer: chip.register(Register::IoPortMixerSettings) & chip.mixer_mask(channel),
+                }
+            }
+        };
+
+        chip.borrow_channel(channel);
+        chip.release_hardware_envelope(&[channel]);
+        chip.set_mixer(channel, sfx.tone.is_some(), sfx.noise.is_some());
+        let playing = Playing {
+            sfx,
+            tick: 0,
+            saved,
+        };
+        *slot = Some(playing);
+        output(chip, channel, playing);
+        true
+    }
+
+    /// Advance every playing effect by one step, giving voices back to the music when done.
+    pub fn tick<C: Chips>(&mut self, chips: &mut C) {
+        for (voice, slot) in self.playing.iter_mut() {
+            let Some(playing) = slot else {
+                continue;
+            };
+            let Some((chip, channel)) = chips.voice(voice) else {
+                continue;
+            };
+            playing.tick += 1;
+            if playing.tick >= playing.sfx.duration {
+                if let Some(playing) = slot.take() {
+                    restore(chip, channel, playing);
+                }
+            } else {
+                output(chip, channel, *playing);
+            }
+        }
+    }
+
+    /// Stop the effect playing on a voice, if any, and restore the channel.
+    pub fn stop<C: Chips>(&mut self, chips: &mut C, voice: impl Into<VoiceId>) {
+        let voice = voice.into();
+        let Some((chip, channel)) = chips.voice(voice) else {
+            return;
+        };
+        if let Some(playing) = self.playing.get_mut(voice).and_then(Option::take) {
+            restore(chip, channel, playing);
+        }
+    }
+}
+
+impl Default for SfxManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Give a channel back to the music, as it was before the effect.
+fn restore<DATABUS, BC1, BDIR>(
+    chip: &mut YM2149<DATABUS, BC1, BDIR>,
+    channel: AudioChannel,
+    playing: Playing,
+) where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    let saved = playing.saved;
+    chip.give_back(channel);
+
+    let mixer = chip.register(Register::IoPortMixerSettings) & !chip.mixer_mask(channel);
+    chip.write(Register::IoPortMixerSettings, mixer | saved.mixer);
+    let tone = chip.output_channel(channel) as u8 * 2;
+    chip.write(tone, saved.tone[0]);
+    chip.write(tone + 1, saved.tone[1]);
+    if playing.sfx.noise.is_some() {
+        chip.write(Register::NoiseFreq5bit, saved.noise);
+    }
+    chip.write(chip.level_register(channel), saved.level);
+}
+
+/// Write the registers of an effect at its current tick.
+fn output<DATABUS, BC1, BDIR>(
+    chip: &mut YM2149<DATABUS, BC1, BDIR>,
+    channel: AudioChannel,
+    Playing { sfx, tick, .. }: Playing,
+) where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    if let Some(tone) = sfx.tone {
+        chip.tone(channel, tone.at(tick, sfx.duration));
+    }
+    if let Some(noise) = sfx.noise {
+        chip.set_noise_freq(noise.at(tick, sfx.duration) as u8);
+    }
+    chip.volume(
+        channel,
+        Level::fixed(sfx.level.at(tick, sfx.duration).min(15) as u8),
+    );
+}
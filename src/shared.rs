@@ -0,0 +1,111 @@
+//! Sharing a chip between the main loop, interrupts and the panic handler.
+
+use core::cell::RefCell;
+
+use cortex_m::interrupt::{self, Mutex};
+use embedded_hal::digital::OutputPin;
+
+use crate::{OutputBus, YM2149};
+
+/// A chip that can be stored in a `static`, so it's reachable from interrupts and panics.
+///
+/// Example:
+/// ```no_run
+/// static CHIP: SharedChip<DataBus<DynOutputPin>, Bc1Pin, BdirPin> = SharedChip::new();
+///
+/// // Mute the chip before halting if anything panics
+/// ym2149::on_panic_silence!(CHIP);
+///
+/// #[hal::entry]
+/// fn main() -> ! {
+///     // ...
+///     CHIP.init(YM2149::new(data_bus, master_clock_freq, bc1, bdir));
+///     CHIP.lock(|chip| chip.note(AudioChannel::A, "A4"));
+/// }
+/// ```
+pub struct SharedChip<DATABUS, BC1, BDIR>
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    chip: Mutex<RefCell<Option<YM2149<DATABUS, BC1, BDIR>>>>,
+}
+
+impl<DATABUS, BC1, BDIR> SharedChip<DATABUS, BC1, BDIR>
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    pub const fn new() -> Self {
+        Self {
+            chip: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Move a chip in, replacing (and returning) the previous one.
+    pub fn init(&self, chip: YM2149<DATABUS, BC1, BDIR>) -> Option<YM2149<DATABUS, BC1, BDIR>> {
+        interrupt::free(|cs| self.chip.borrow(cs).replace(Some(chip)))
+    }
+
+    /// Run `f` on the chip with interrupts disabled.
+    ///
+    /// Returns `None` if there's no chip yet, or if it's already in use further up the stack.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut YM2149<DATABUS, BC1, BDIR>) -> R) -> Option<R> {
+        interrupt::free(|cs| {
+            let mut chip = self.chip.borrow(cs).try_borrow_mut().ok()?;
+            chip.as_mut().map(f)
+        })
+    }
+
+    /// [Silence](YM2149::silence) the chip, even if it's currently borrowed.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called when the code holding the chip will never run again,
+    /// i.e. from a panic handler that doesn't return.
+    pub unsafe fn panic_silence(&self) {
+        interrupt::free(|cs| {
+            // SAFETY: Interrupts are off and, per the contract above, whoever
+            // borrowed the chip never resumes, so this is the only reference.
+            let chip = unsafe { &mut *self.chip.borrow(cs).as_ptr() };
+            if let Some(chip) = chip {
+                chip.silence();
+            }
+        });
+    }
+}
+
+impl<DATABUS, BC1, BDIR> Default for SharedChip<DATABUS, BC1, BDIR>
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Define a `#[panic_handler]` that silences a [SharedChip] before halting.
+///
+/// Don't combine it with another panic handler crate such as `panic-halt`.
+/// To keep your own handler, call [SharedChip::panic_silence] from it instead.
+/// ```no_run
+/// static CHIP: SharedChip<MyBus, MyBc1, MyBdir> = SharedChip::new();
+/// ym2149::on_panic_silence!(CHIP);
+/// ```
+#[macro_export]
+macro_rules! on_panic_silence {
+    ($chip:expr) => {
+        #[panic_handler]
+        fn panic(_info: &core::panic::PanicInfo) -> ! {
+            // SAFETY: The handler never returns
+            unsafe { $chip.panic_silence() };
+            loop {
+                core::hint::spin_loop();
+            }
+        }
+    };
+}
@@ -0,0 +1,91 @@
+//! Cheap counters to check a replay loop has enough headroom.
+
+/// Counters kept by the driver, see [YM2149::stats](crate::YM2149::stats).
+///
+/// Register writes are counted by the driver itself. The rest is up to the
+/// replay loop, through the `record_*` methods of [YM2149::stats_mut](crate::YM2149::stats_mut).
+///
+/// All counters wrap around on overflow.
+///
+/// Example:
+/// ```no_run
+/// let start = timer.get_counter_low();
+/// if let Some(frame) = song.next_frame() {
+///     play(&mut chip, frame);
+///     chip.stats_mut().record_frame(true);
+/// } else {
+///     chip.stats_mut().record_underrun();
+/// }
+/// chip.stats_mut().record_tick_duration(timer.get_counter_low() - start);
+///
+/// defmt::info!("{}", chip.stats());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, defmt::Format)]
+pub struct Stats {
+    pub register_writes: u32,
+    pub frames_applied: u32,
+    pub frames_skipped: u32,
+    pub underruns: u32,
+    /// Longest tick reported so far, in whatever unit the replay loop measures it.
+    pub max_tick_duration: u32,
+    /// Calls to [YM2149::reinitialize](crate::YM2149::reinitialize).
+    pub reinitializations: u32,
+}
+
+impl Stats {
+    /// Count a frame, either applied or skipped (e.g. dropped to catch up).
+    pub fn record_frame(&mut self, applied: bool) {
+        if applied {
+            self.frames_applied = self.frames_applied.wrapping_add(1);
+        } else {
+            self.frames_skipped = self.frames_skipped.wrapping_add(1);
+        }
+    }
+
+    /// Count a tick where no data was ready in time.
+    pub fn record_underrun(&mut self) {
+        self.underruns = self.underruns.wrapping_add(1);
+    }
+
+    /// Report how long a tick took, keeping the maximum.
+    pub fn record_tick_duration(&mut self, duration: u32) {
+        self.max_tick_duration = self.max_tick_duration.max(duration);
+    }
+
+    pub(crate) fn record_write(&mut self) {
+        self.register_writes = self.register_writes.wrapping_add(1);
+    }
+
+    pub(crate) fn record_reinitialization(&mut self) {
+        self.reinitializations = self.reinitializations.wrapping_add(1);
+    }
+}
+
+/// What the driver stops doing in [tick](crate::YM2149::tick) when ticks take longer than
+/// their [budget](crate::YM2149::with_tick_budget), from the least to the most audible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, defmt::Format)]
+pub enum Shedding {
+    /// Everything runs
+    #[default]
+    Off,
+    /// Vibratos are frozen
+    Vibrato,
+    /// Vibratos are frozen and software envelopes hold their level
+    SoftwareEnvelopes,
+}
+
+impl Shedding {
+    pub(crate) fn more(self) -> Self {
+        match self {
+            Self::Off => Self::Vibrato,
+            _ => Self::SoftwareEnvelopes,
+        }
+    }
+
+    pub(crate) fn less(self) -> Self {
+        match self {
+            Self::SoftwareEnvelopes => Self::Vibrato,
+            _ => Self::Off,
+        }
+    }
+}
@@ -0,0 +1,693 @@
+//! Ready-made playable synths: MIDI in, sound out, in a few lines.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::modulation::{Destination, ModMatrix, Modulation, Modulator, Route, Source};
+use crate::voice::{Chips, StealPolicy, VoiceAllocator, VoiceId, Voices};
+use crate::{
+    AudioChannel, EnvelopeFrequency, EnvelopeShape, Level, Note, OutputBus, RawEnvelope, Register,
+    VelocityCurve, YM2149,
+};
+
+/// The sound of a synth voice: levels, envelope, modulation and how it glides.
+///
+/// Start from a preset and change what you need:
+/// ```no_run
+/// let bass = Instrument {
+///     glide: 40,
+///     ..Instrument::BASS
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instrument<'a> {
+    /// Level of the hardest notes, 0 - 15
+    pub level: u8,
+    pub velocity: VelocityCurve,
+    /// Software envelope, scaled by the velocity. `None` holds the level until note-off
+    pub envelope: Option<RawEnvelope>,
+    /// Noise period mixed with the tone, `None` for a pure tone
+    pub noise: Option<u8>,
+    /// Pitch, level and noise routes are applied, level ones only without an envelope
+    pub modulation: ModMatrix<'a>,
+    /// Portamento between overlapping notes, in cents per tick, 0 to jump
+    pub glide: u16,
+    /// Pitch bend range, in semitones each way
+    pub bend_range: u8,
+    /// Detune of the outer channels when playing in unison, in cents
+    pub unison_spread: i16,
+}
+
+const VIBRATO: &[Route] = &[Route::new(Source::Lfo1, Destination::Pitch, 25)];
+
+/// Vibrato brought in by the mod wheel, none with the wheel down.
+const WHEEL_VIBRATO: ModMatrix<'static> = ModMatrix::new(VIBRATO)
+    .with_lfo(0, 12)
+    .with_depth_control(Source::Lfo1, Source::ModWheel);
+
+impl Instrument<'static> {
+    /// Plain square lead: vibrato on the mod wheel, light portamento.
+    pub const LEAD: Instrument<'static> = Instrument {
+        level: 14,
+        velocity: VelocityCurve::Linear,
+        envelope: None,
+        noise: None,
+        modulation: WHEEL_VIBRATO,
+        glide: 15,
+        bend_range: 2,
+        unison_spread: 0,
+    };
+
+    /// Fat unison lead, thickest on all three channels.
+    pub const SUPERSAW: Instrument<'static> = Instrument {
+        unison_spread: 10,
+        ..Self::LEAD
+    };
+
+    /// Punchy bass: quick decay to a sustain, no glide.
+    pub const BASS: Instrument<'static> = Instrument {
+        level: 15,
+        velocity: VelocityCurve::Steps(4),
+        envelope: Some(RawEnvelope::from_slice(&[15, 14, 13, 12, 11]).with_loop(4)),
+        noise: None,
+        modulation: ModMatrix::new(&[]),
+        glide: 0,
+        bend_range: 12,
+        unison_spread: 0,
+    };
+
+    /// Plucked string: fades out on its own, much softer on soft hits. Vibrato on the mod wheel.
+    pub const PLUCK: Instrument<'static> = Instrument {
+        level: 15,
+        velocity: VelocityCurve::Exponential,
+        envelope: Some(
+            RawEnvelope::from_slice(&[15, 13, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0]).with_loop(13),
+        ),
+        noise: None,
+        modulation: WHEEL_VIBRATO,
+        glide: 0,
+        bend_range: 2,
+        unison_spread: 0,
+    };
+}
+
+/// Most keys a [MonoSynth] remembers being held.
+const HELD_KEYS: usize = 8;
+
+/// A monophonic synth: one MIDI channel in, one chip channel (or all three in unison) out.
+///
+/// The last key pressed plays. Releasing it goes back to the previous key still held,
+/// gliding if the [instrument](Instrument::glide) has portamento, so trills and legato
+/// runs work as on a real mono synth.
+///
+/// Example:
+/// ```no_run
+/// let mut synth = MonoSynth::new(AudioChannel::A, 0, Instrument::LEAD);
+///
+/// // MIDI UART, one complete message at a time
+/// synth.midi(&mut chip, &message);
+///
+/// // 50 Hz timer, ticks the chip too
+/// synth.tick(&mut chip);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MonoSynth<'a> {
+    channel: AudioChannel,
+    midi_channel: u8,
+    unison: bool,
+    instrument: Instrument<'a>,
+    modulator: Modulator<'a>,
+    /// Held keys, the playing one last
+    held: [u8; HELD_KEYS],
+    held_count: usize,
+    /// Peak level of the current note
+    peak: u8,
+    /// Pitch bend, in cents
+    bend: i16,
+    /// Modulation of the last tick
+    modulation: Modulation,
+}
+
+impl<'a> MonoSynth<'a> {
+    /// A synth playing `instrument` on a chip channel, listening to MIDI channel
+    /// `midi_channel` (0 - 15). Plays in unison if the instrument has a unison spread.
+    pub fn new(channel: AudioChannel, midi_channel: u8, instrument: Instrument<'a>) -> Self {
+        Self {
+            channel,
+            midi_channel: midi_channel & 0x0F,
+            unison: instrument.unison_spread != 0,
+            instrument,
+            modulator: Modulator::new(instrument.modulation),
+            held: [0; HELD_KEYS],
+            held_count: 0,
+            peak: 0,
+            bend: 0,
+            modulation: Modulation::default(),
+        }
+    }
+
+    /// Switch to another instrument, from the next note.
+    ///
+    /// Switching in or out of unison changes the channels the synth plays on, so the note
+    /// playing, if any, is released first.
+    pub fn set_instrument<DATABUS, BC1, BDIR>(
+        &mut self,
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+        instrument: Instrument<'a>,
+    ) where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let unison = instrument.unison_spread != 0;
+        if unison != self.unison {
+            self.silence(chip);
+            for channel in self.channels() {
+                chip.set_pitch_modulation(channel, 0);
+            }
+            self.held_count = 0;
+        }
+        self.instrument = instrument;
+        self.unison = unison;
+        self.modulator.set_matrix(instrument.modulation);
+    }
+
+    pub fn instrument(&self) -> &Instrument<'a> {
+        &self.instrument
+    }
+
+    /// The key playing, if any.
+    pub fn key(&self) -> Option<u8> {
+        self.held().last().copied()
+    }
+
+    /// Handle one complete MIDI message (note on / off, mod wheel, aftertouch, pitch bend),
+    /// ignoring other channels and messages.
+    pub fn midi<DATABUS, BC1, BDIR>(
+        &mut self,
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+        message: &[u8],
+    ) where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let (&status, data) = match message.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        if status & 0x0F != self.midi_channel {
+            return;
+        }
+        let byte = |i: usize| data.get(i).map_or(0, |b| b & 0x7F);
+        match status & 0xF0 {
+            0x90 if byte(1) != 0 => self.note_on(chip, byte(0), byte(1)),
+            0x80 | 0x90 => self.note_off(chip, byte(0)),
+            0xB0 if byte(0) == 1 => self.modulator.set_mod_wheel(byte(1)),
+            0xD0 => self.modulator.set_aftertouch(byte(0)),
+            0xE0 => self.pitch_bend(
+                chip,
+                (byte(0) as u16 | (byte(1) as u16) << 7) as i16 - 0x2000,
+            ),
+            _ => {}
+        }
+    }
+
+    /// Press a key (MIDI note number), velocity 1 - 127.
+    pub fn note_on<DATABUS, BC1, BDIR>(
+        &mut self,
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+        key: u8,
+        velocity: u8,
+    ) where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        // A key pressed again without a note-off isn't legato with itself
+        self.forget(key);
+        let legato = self.held_count != 0;
+        if self.held_count == HELD_KEYS {
+            // Drop the oldest
+            self.held.copy_within(1.., 0);
+            self.held_count -= 1;
+        }
+        if let Some(slot) = self.held.get_mut(self.held_count) {
+            *slot = key;
+            self.held_count += 1;
+        }
+
+        let instrument = self.instrument;
+        let level = instrument.velocity.level(velocity).bits() as u16;
+        self.peak = (level * instrument.level.min(15) as u16 / 15) as u8;
+        if !legato {
+            self.modulator.note_on(velocity);
+        }
+
+        for channel in self.channels() {
+            chip.set_legato(channel, true);
+            chip.set_glide(channel, instrument.glide);
+            chip.set_mixer(channel, true, instrument.noise.is_some());
+            if !legato {
+                // Retrigger the envelope, or set the level
+                let envelope = instrument
+                    .envelope
+                    .map(|envelope| envelope.scale((self.peak as u16 * 256 + 7) / 15));
+                chip.set_software_envelope(channel, envelope);
+                chip.release_note(channel);
+                if envelope.is_none() {
+                    chip.volume(channel, Level::fixed(self.peak));
+                }
+            }
+        }
+        self.play(chip, key);
+    }
+
+    /// Release a key, going back to the previous one still held, or silence.
+    pub fn note_off<DATABUS, BC1, BDIR>(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>, key: u8)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let was_playing = self.key() == Some(key);
+        self.forget(key);
+        match self.key() {
+            Some(previous) if was_playing => self.play(chip, previous),
+            Some(_) => {}
+            None => self.silence(chip),
+        }
+    }
+
+    /// Bend the pitch, from -8192 to 8191 (MIDI pitch bend minus its center) over the
+    /// instrument's bend range.
+    pub fn pitch_bend<DATABUS, BC1, BDIR>(
+        &mut self,
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+        bend: i16,
+    ) where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        self.bend = (bend as i32 * self.instrument.bend_range as i32 * 100 / 0x2000) as i16;
+        self.apply(chip);
+    }
+
+    /// Advance the modulation and the chip by one step, at the rate of [YM2149::tick].
+    pub fn tick<DATABUS, BC1, BDIR>(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        self.modulation = self.modulator.tick();
+        self.apply(chip);
+        chip.tick();
+    }
+
+    fn play<DATABUS, BC1, BDIR>(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>, key: u8)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let note = Note::from_midi(key);
+        if self.unison {
+            chip.unison(note, self.instrument.unison_spread);
+        } else {
+            chip.play_note(self.channel, note);
+        }
+    }
+
+    /// Release the synth's channels.
+    fn silence<DATABUS, BC1, BDIR>(&self, chip: &mut YM2149<DATABUS, BC1, BDIR>)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        for channel in self.channels() {
+            chip.set_software_envelope(channel, None);
+            chip.volume(channel, Level::OFF);
+            chip.release_note(channel);
+        }
+    }
+
+    /// Write the modulation and pitch bend.
+    fn apply<DATABUS, BC1, BDIR>(&self, chip: &mut YM2149<DATABUS, BC1, BDIR>)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let modulation = self.modulation;
+        let pitch = modulation.pitch.saturating_add(self.bend);
+        for channel in self.channels() {
+            chip.set_pitch_modulation(channel, pitch);
+            let level = modulation.level(self.peak);
+            if self.key().is_some()
+                && self.instrument.envelope.is_none()
+                && chip.register(chip.level_register(channel)) != level.bits()
+            {
+                chip.volume(channel, level);
+            }
+        }
+        if let Some(noise) = self.instrument.noise {
+            let period = modulation.noise_period(noise);
+            if chip.register(Register::NoiseFreq5bit) != period {
+                chip.set_noise_freq(period);
+            }
+        }
+    }
+
+    /// The chip channels the synth plays on.
+    fn channels(&self) -> impl Iterator<Item = AudioChannel> {
+        let (first, count) = match self.unison {
+            true => (0, 3),
+            false => (self.channel as usize, 1),
+        };
+        AudioChannel::ALL.into_iter().skip(first).take(count)
+    }
+
+    /// The keys held down, oldest first.
+    fn held(&self) -> &[u8] {
+        self.held.get(..self.held_count).unwrap_or(&[])
+    }
+
+    /// Remove a key from the held ones.
+    fn forget(&mut self, key: u8) {
+        if let Some(i) = self.held().iter().position(|&k| k == key) {
+            self.held.copy_within(i + 1..self.held_count, i);
+            self.held_count -= 1;
+        }
+    }
+}
+
+/// A paraphonic synth: one MIDI channel in, up to `N` notes out (3 by default, one per
+/// channel of a single chip). See [Chips] for several chips.
+///
+/// Notes get a voice from a [VoiceAllocator], and share the instrument's modulation and
+/// pitch bend, restarted when playing starts from silence. With a
+/// [hardware envelope](ParaSynth::with_hardware_envelope), every note follows its chip's
+/// single envelope, restarted the same way, as on paraphonic analog synths.
+///
+/// [Learning a chord](ParaSynth::learn_chord) turns every key into that chord, moved to
+/// start on the key, so one finger plays what three did.
+///
+/// Example:
+/// ```no_run
+/// let mut synth = ParaSynth::new(0, Instrument::PLUCK);
+///
+/// // MIDI UART, one complete message at a time
+/// synth.midi(&mut chip, &message);
+///
+/// // Chord memory button, with a chord held
+/// synth.learn_chord();
+///
+/// // 50 Hz timer, ticks the chip too
+/// synth.tick(&mut chip);
+///
+/// // Six notes on two chips
+/// let mut synth = ParaSynth::<6>::with_voices(0, Instrument::PLUCK);
+/// synth.midi(&mut chips, &message);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParaSynth<'a, const N: usize = 3> {
+    midi_channel: u8,
+    instrument: Instrument<'a>,
+    hardware_envelope: Option<(EnvelopeFrequency, EnvelopeShape)>,
+    voices: VoiceAllocator<N>,
+    modulator: Modulator<'a>,
+    /// Peak level of the note on each voice
+    peaks: Voices<u8, N>,
+    /// Key pressed for the note on each voice, the same for every note of a chord
+    triggers: Voices<u8, N>,
+    /// Intervals of the learned chord from its lowest note, in semitones
+    chord: [u8; N],
+    chord_len: usize,
+    /// Pitch bend, in cents
+    bend: i16,
+    /// Modulation of the last tick
+    modulation: Modulation,
+}
+
+impl<'a> ParaSynth<'a> {
+    /// A synth playing `instrument` on the three channels of a single chip, listening to
+    /// MIDI channel `midi_channel` (0 - 15). The instrument's unison spread is ignored.
+    pub fn new(midi_channel: u8, instrument: Instrument<'a>) -> Self {
+        Self::with_voices(midi_channel, instrument)
+    }
+}
+
+impl<'a, const N: usize> ParaSynth<'a, N> {
+    /// A synth playing `instrument` on `N` voices, see [new](ParaSynth::new).
+    pub fn with_voices(midi_channel: u8, instrument: Instrument<'a>) -> Self {
+        Self {
+            midi_channel: midi_channel & 0x0F,
+            instrument,
+            hardware_envelope: None,
+            voices: VoiceAllocator::with_voices(StealPolicy::Oldest),
+            modulator: Modulator::new(instrument.modulation),
+            peaks: Voices::new(0),
+            triggers: Voices::new(0),
+            chord: [0; N],
+            chord_len: 0,
+            bend: 0,
+            modulation: Modulation::default(),
+        }
+    }
+
+    /// Drive the level of every note with the hardware envelope instead of the instrument's
+    /// levels and envelope. Its [envelope rate](Destination::EnvelopeRate) routes speed the
+    /// envelope up or down.
+    pub fn with_hardware_envelope(
+        mut self,
+        frequency: EnvelopeFrequency,
+        shape: EnvelopeShape,
+    ) -> Self {
+        self.hardware_envelope = Some((frequency, shape));
+        self
+    }
+
+    /// Which note a new one replaces when every voice is playing. The oldest by default.
+    pub fn with_steal_policy(mut self, policy: StealPolicy) -> Self {
+        self.voices.set_policy(policy);
+        self
+    }
+
+    /// Switch to another instrument, from the next note.
+    pub fn set_instrument(&mut self, instrument: Instrument<'a>) {
+        self.instrument = instrument;
+        self.modulator.set_matrix(instrument.modulation);
+    }
+
+    pub fn instrument(&self) -> &Instrument<'a> {
+        &self.instrument
+    }
+
+    /// The key playing on a voice, if any.
+    pub fn key(&self, voice: impl Into<VoiceId>) -> Option<u8> {
+        self.voices.key(voice)
+    }
+
+    /// Remember the keys playing as a chord, played from then on by every key. Learning
+    /// a single key or none forgets the chord.
+    pub fn learn_chord(&mut self) {
+        let mut keys = [0; N];
+        let mut len = 0;
+        let playing = (0..N).filter_map(|voice| self.key(VoiceId(voice)));
+        for (slot, key) in keys.iter_mut().zip(playing) {
+            *slot = key;
+            len += 1;
+        }
+        if let Some(keys) = keys.get_mut(..len) {
+            keys.sort_unstable();
+        }
+        let lowest = keys.first().copied().unwrap_or(0);
+        self.chord = keys.map(|key| key.wrapping_sub(lowest));
+        self.chord_len = if len > 1 { len } else { 0 };
+    }
+
+    /// Forget the learned chord, back to one note per key.
+    pub fn clear_chord(&mut self) {
+        self.chord_len = 0;
+    }
+
+    /// Intervals of the learned chord from its lowest note, in semitones, empty without one.
+    pub fn chord(&self) -> &[u8] {
+        self.chord.get(..self.chord_len).unwrap_or(&[])
+    }
+
+    /// Handle one complete MIDI message (note on / off, mod wheel, aftertouch, pitch bend),
+    /// ignoring other channels and messages.
+    pub fn midi<C: Chips>(&mut self, chips: &mut C, message: &[u8]) {
+        let (&status, data) = match message.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        if status & 0x0F != self.midi_channel {
+            return;
+        }
+        let byte = |i: usize| data.get(i).map_or(0, |b| b & 0x7F);
+        match status & 0xF0 {
+            0x90 if byte(1) != 0 => self.note_on(chips, byte(0), byte(1)),
+            0x80 | 0x90 => self.note_off(chips, byte(0)),
+            0xB0 if byte(0) == 1 => self.modulator.set_mod_wheel(byte(1)),
+            0xD0 => self.modulator.set_aftertouch(byte(0)),
+            0xE0 => self.pitch_bend(
+                chips,
+                (byte(0) as u16 | (byte(1) as u16) << 7) as i16 - 0x2000,
+            ),
+            _ => {}
+        }
+    }
+
+    /// Press a key (MIDI note number), velocity 1 - 127: the key, or the learned chord
+    /// from it.
+    pub fn note_on<C: Chips>(&mut self, chips: &mut C, key: u8, velocity: u8) {
+        if self.is_silent() {
+            self.modulator.note_on(velocity);
+            if let Some((frequency, shape)) = self.hardware_envelope {
+                // Restarts the envelope of every chip
+                for index in 0..N.div_ceil(3) {
+                    if let Some(chip) = chips.chip(index) {
+                        chip.use_hardware_envelope(&[], frequency, shape);
+                    }
+                }
+            }
+        }
+        let instrument = self.instrument;
+        let level = instrument.velocity.level(velocity).bits() as u16;
+        let peak = (level * instrument.level.min(15) as u16 / 15) as u8;
+
+        for note in self.keys(key) {
+            let Some(allocation) = self.voices.note_on(note) else {
+                continue;
+            };
+            if let Some(trigger) = self.triggers.get_mut(allocation.voice) {
+                *trigger = key;
+            }
+            let Some((chip, channel)) = chips.voice(allocation.voice) else {
+                continue;
+            };
+            if let Some(slot) = self.peaks.get_mut(allocation.voice) {
+                *slot = peak;
+            }
+            chip.set_legato(channel, false);
+            chip.set_mixer(channel, true, instrument.noise.is_some());
+            chip.release_note(channel);
+            if self.hardware_envelope.is_some() {
+                chip.set_software_envelope(channel, None);
+                chip.volume(channel, Level::envelope());
+            } else {
+                let envelope = instrument
+                    .envelope
+                    .map(|envelope| envelope.scale((peak as u16 * 256 + 7) / 15));
+                chip.set_software_envelope(channel, envelope);
+                if envelope.is_none() {
+                    chip.volume(channel, self.modulation.level(peak));
+                }
+            }
+            chip.play_note(channel, Note::from_midi(note));
+        }
+    }
+
+    /// Release a key, and every note of the chord it started, even if the chord was
+    /// learned or cleared since.
+    pub fn note_off<C: Chips>(&mut self, chips: &mut C, key: u8) {
+        for voice in (0..N).map(VoiceId) {
+            if self.triggers.get(voice) != Some(&key) {
+                continue;
+            }
+            let Some(note) = self.key(voice) else {
+                continue;
+            };
+            self.voices.note_off(note);
+            if let Some((chip, channel)) = chips.voice(voice) {
+                chip.set_software_envelope(channel, None);
+                if chip.is_envelope_driven(channel) {
+                    chip.release_hardware_envelope(&[channel]);
+                }
+                chip.volume(channel, Level::OFF);
+                chip.release_note(channel);
+            }
+        }
+    }
+
+    /// Bend the pitch of every note, from -8192 to 8191 (MIDI pitch bend minus its center)
+    /// over the instrument's bend range.
+    pub fn pitch_bend<C: Chips>(&mut self, chips: &mut C, bend: i16) {
+        self.bend = (bend as i32 * self.instrument.bend_range as i32 * 100 / 0x2000) as i16;
+        self.apply(chips);
+    }
+
+    /// Advance the modulation and the chips by one step, at the rate of [YM2149::tick].
+    pub fn tick<C: Chips>(&mut self, chips: &mut C) {
+        self.modulation = self.modulator.tick();
+        self.apply(chips);
+        for index in 0..N.div_ceil(3) {
+            if let Some(chip) = chips.chip(index) {
+                chip.tick();
+            }
+        }
+    }
+
+    /// Write the modulation and pitch bend.
+    fn apply<C: Chips>(&self, chips: &mut C) {
+        let modulation = self.modulation;
+        let pitch = modulation.pitch.saturating_add(self.bend);
+        let fixed_levels = self.hardware_envelope.is_none() && self.instrument.envelope.is_none();
+        for (voice, &peak) in self.peaks.iter() {
+            let Some((chip, channel)) = chips.voice(voice) else {
+                continue;
+            };
+            chip.set_pitch_modulation(channel, pitch);
+            let level = modulation.level(peak);
+            if fixed_levels
+                && self.key(voice).is_some()
+                && chip.register(chip.level_register(channel)) != level.bits()
+            {
+                chip.volume(channel, level);
+            }
+        }
+        for index in 0..N.div_ceil(3) {
+            let Some(chip) = chips.chip(index) else {
+                continue;
+            };
+            if let Some(noise) = self.instrument.noise {
+                let period = modulation.noise_period(noise);
+                if chip.register(Register::NoiseFreq5bit) != period {
+                    chip.set_noise_freq(period);
+                }
+            }
+            if let Some((frequency, shape)) = self.hardware_envelope {
+                let base = frequency.period(chip.master_clock_frequency(), shape);
+                let period = modulation.envelope_period(base).to_le_bytes();
+                // Only the period: writing the shape would restart the envelope
+                for (register, value) in [
+                    (Register::EFreq8bitFineAdj, period[0]),
+                    (Register::EFreq8bitRoughAdj, period[1]),
+                ] {
+                    if chip.register(register) != value {
+                        chip.write(register, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The keys `key` plays: itself, or the learned chord from it.
+    fn keys(&self, key: u8) -> impl Iterator<Item = u8> {
+        // The first interval is always 0
+        let len = self.chord_len.max(1);
+        self.chord
+            .into_iter()
+            .take(len)
+            .map(move |interval| key.saturating_add(interval))
+            .filter(|&key| key < 128)
+    }
+
+    fn is_silent(&self) -> bool {
+        (0..N).all(|voice| self.key(VoiceId(voice)).is_none())
+    }
+}
@@ -0,0 +1,189 @@
+//! The noise generator: its period in Hz, and textures moving it around for surf, rain,
+//! engines and the like.
+
+use embedded_hal::digital::OutputPin;
+
+use rand_core::RngCore;
+
+use crate::generative::{below, Rng};
+use crate::{OutputBus, Register, YM2149};
+
+/// The noise period (R6) whose output is closest to `hz`, e.g. a cutoff for a sound design.
+///
+/// The noise generator picks a new random level at `fMaster / (16 * NP)` Hz, so that's
+/// about where its spectrum starts rolling off. Clamped to `1..=31`.
+pub const fn noise_period_for_cutoff_hz(master_clock_frequency: u32, hz: u32) -> u8 {
+    if hz == 0 {
+        return 31;
+    }
+    let divisor = 16 * hz as u64;
+    let period = (master_clock_frequency as u64 + divisor / 2) / divisor;
+    if period < 1 {
+        1
+    } else if period > 31 {
+        31
+    } else {
+        period as u8
+    }
+}
+
+/// The rate (in Hz) the noise generator changes level at, for a noise period (R6).
+/// A period of 0 plays like 1.
+pub const fn noise_cutoff_hz_for_period(master_clock_frequency: u32, period: u8) -> u32 {
+    let period = if period & 0x1F == 0 { 1 } else { period & 0x1F };
+    let divisor = 16 * period as u64;
+    ((master_clock_frequency as u64 + divisor / 2) / divisor) as u32
+}
+
+/// How a [NoiseModulator] moves the noise period (R6) around.
+///
+/// Every `rate` ticks, the period heads for a random value within `depth` of `center`,
+/// keeping `smoothing`/256 of where it was. A `swell` slowly sweeps the center up and
+/// down by half the depth on top of that.
+///
+/// Example:
+/// ```no_run
+/// // Light drizzle: short periods, jumping around
+/// const DRIZZLE: NoiseTexture = NoiseTexture {
+///     center: 3,
+///     depth: 2,
+///     rate: 1,
+///     smoothing: 0,
+///     swell: 0,
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoiseTexture {
+    /// Noise period the texture moves around, 0 - 31
+    pub center: u8,
+    /// Largest random move away from the center
+    pub depth: u8,
+    /// Ticks between moves, 0 and 1 both move every tick
+    pub rate: u8,
+    /// Share of the previous period kept on each move, out of 256. Higher is smoother
+    pub smoothing: u8,
+    /// Length in ticks of a slow up and down sweep of the center, 0 for none
+    pub swell: u16,
+}
+
+// Presets, at 50 Hz ticks
+impl NoiseTexture {
+    /// Waves: a smooth hiss rising and falling every 4 seconds.
+    pub const SURF: NoiseTexture = NoiseTexture {
+        center: 14,
+        depth: 10,
+        rate: 2,
+        smoothing: 224,
+        swell: 200,
+    };
+
+    /// Rain: bright, crackling noise.
+    pub const RAIN: NoiseTexture = NoiseTexture {
+        center: 3,
+        depth: 3,
+        rate: 1,
+        smoothing: 0,
+        swell: 0,
+    };
+
+    /// Engine: a low rumble throbbing a few times per second.
+    pub const ENGINE: NoiseTexture = NoiseTexture {
+        center: 24,
+        depth: 5,
+        rate: 1,
+        smoothing: 64,
+        swell: 12,
+    };
+
+    /// Wind: slow gusts.
+    pub const WIND: NoiseTexture = NoiseTexture {
+        center: 18,
+        depth: 8,
+        rate: 4,
+        smoothing: 240,
+        swell: 350,
+    };
+}
+
+/// Plays a [NoiseTexture] on the chip's noise generator.
+///
+/// The noise period is shared by all channels, so this affects every channel with noise
+/// [enabled](YM2149::set_mixer). The same seed always plays the same texture, pass any
+/// other [RngCore] for a different random source.
+///
+/// Example:
+/// ```no_run
+/// let mut surf = NoiseModulator::new(NoiseTexture::SURF, Rng::new(7));
+/// chip.set_mixer(AudioChannel::B, false, true);
+/// chip.volume(AudioChannel::B, Level::fixed(10));
+///
+/// // 50 Hz timer
+/// surf.tick(&mut chip);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NoiseModulator<R = Rng> {
+    texture: NoiseTexture,
+    rng: R,
+    /// Current period, Q8
+    period: i32,
+    /// Ticks since the texture started
+    elapsed: u32,
+}
+
+impl<R: RngCore> NoiseModulator<R> {
+    pub fn new(texture: NoiseTexture, rng: R) -> Self {
+        Self {
+            texture,
+            rng,
+            period: (texture.center as i32) << 8,
+            elapsed: 0,
+        }
+    }
+
+    /// Switch to another texture, moving on from the current period.
+    pub fn set_texture(&mut self, texture: NoiseTexture) {
+        self.texture = texture;
+    }
+
+    /// The noise period the texture is at.
+    pub fn period(&self) -> u8 {
+        ((self.period + 0x80) >> 8).clamp(0, 31) as u8
+    }
+
+    /// Advance by one tick, writing the noise period if it changed.
+    pub fn tick<DATABUS, BC1, BDIR>(&mut self, chip: &mut YM2149<DATABUS, BC1, BDIR>)
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let NoiseTexture {
+            center,
+            depth,
+            rate,
+            smoothing,
+            swell,
+        } = self.texture;
+
+        if self.elapsed.is_multiple_of(rate.max(1) as u32) {
+            let mut center = center as i32;
+            if swell != 0 {
+                // Triangle from -depth/2 to +depth/2 over `swell` ticks
+                let phase = (self.elapsed % swell as u32) as i32;
+                let half = swell as i32 / 2;
+                let triangle = (phase - half).abs() * 2 - half;
+                center += triangle * (depth as i32 / 2) / half.max(1);
+            }
+            let leap = below(&mut self.rng, 2 * depth as u32 + 1) as i32 - depth as i32;
+            let target = (center + leap) << 8;
+            let smoothing = smoothing as i32;
+            self.period = (self.period * smoothing + target * (256 - smoothing)) >> 8;
+        }
+        self.elapsed = self.elapsed.wrapping_add(1);
+
+        let period = self.period();
+        if chip.register(Register::NoiseFreq5bit) != period {
+            chip.set_noise_freq(period);
+        }
+    }
+}
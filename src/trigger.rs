@@ -0,0 +1,258 @@
+//! Pulses on a spare GPIO or I/O port bit in time with the music, for relays, lights or
+//! other gear.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::{Error, IoPort, OutputBus, Register, YM2149};
+
+/// When pulses start and end, in ticks. Shared by [TriggerOut] and [PortTrigger].
+#[derive(Debug, Clone, Copy)]
+struct Pulse {
+    /// Pulse length in ticks
+    length: u16,
+    /// Ticks between automatic pulses, 0 for none
+    period: u16,
+    /// Ticks left before the output goes low
+    remaining: u16,
+    /// Ticks since the last automatic pulse
+    elapsed: u16,
+}
+
+impl Pulse {
+    /// Pulses `ms` milliseconds long, rounded to the nearest tick but at least one.
+    fn new(tick_rate: u32, ms: u32) -> Self {
+        let ticks = (ms as u64 * tick_rate as u64 + 500) / 1000;
+        let length = match ms {
+            0 => 0,
+            _ => ticks.clamp(1, u16::MAX as u64) as u16,
+        };
+        Self {
+            length,
+            period: 0,
+            remaining: 0,
+            elapsed: 0,
+        }
+    }
+
+    fn with_period(self, period: u16) -> Self {
+        Self {
+            period,
+            elapsed: period.saturating_sub(1),
+            ..self
+        }
+    }
+
+    /// Start a pulse, `false` if pulses are too short to show.
+    fn fire(&mut self) -> bool {
+        if self.length == 0 {
+            return false;
+        }
+        self.remaining = self.length;
+        true
+    }
+
+    /// Advance by one tick, returning the new level of the output if it changes.
+    fn tick(&mut self) -> Option<bool> {
+        let mut level = None;
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            if self.remaining == 0 {
+                level = Some(false);
+            }
+        }
+
+        if self.period != 0 {
+            self.elapsed += 1;
+            if self.elapsed >= self.period {
+                self.elapsed = 0;
+                if self.fire() {
+                    level = Some(true);
+                }
+            }
+        }
+        level
+    }
+}
+
+/// Raises a pin for a few milliseconds, on demand or on a steady beat.
+///
+/// Call [tick](TriggerOut::tick) from the same timer as [YM2149::tick], so pulses line up
+/// with the music. A pulse firing while another is still high restarts it. Short of a GPIO,
+/// use an I/O port bit of the chip with a [PortTrigger].
+///
+/// Example:
+/// ```no_run
+/// // At 50 Hz ticks: 120 BPM is a beat every 25 ticks, pulse for 60 ms
+/// let mut beat = TriggerOut::new(pins.gpio15.into_push_pull_output(), 50, 60)?.with_period(25);
+/// // Flash on notes instead
+/// let mut flash = TriggerOut::new(pins.gpio14.into_push_pull_output(), 50, 40)?;
+///
+/// // 50 Hz timer
+/// beat.tick()?;
+/// flash.tick()?;
+///
+/// // Wherever notes start
+/// chip.note(AudioChannel::A, "E4")?;
+/// flash.fire()?;
+/// ```
+pub struct TriggerOut<P> {
+    pin: P,
+    pulse: Pulse,
+}
+
+impl<P> TriggerOut<P>
+where
+    P: OutputPin,
+{
+    /// A trigger raising `pin` for `pulse_ms` milliseconds when [fired](TriggerOut::fire),
+    /// ticked `tick_rate` times per second.
+    ///
+    /// The length is rounded to the nearest tick, and to one tick for anything shorter.
+    pub fn new(mut pin: P, tick_rate: u32, pulse_ms: u32) -> Result<Self, P::Error> {
+        pin.set_low()?;
+        Ok(Self {
+            pin,
+            pulse: Pulse::new(tick_rate, pulse_ms),
+        })
+    }
+
+    /// Also fire every `period` ticks (e.g. on each beat or bar), starting with the next tick.
+    /// `0` disables it.
+    ///
+    /// In ticks rather than milliseconds, so the beat doesn't drift from the music.
+    pub fn with_period(mut self, period: u16) -> Self {
+        self.pulse = self.pulse.with_period(period);
+        self
+    }
+
+    /// Start a pulse now.
+    pub fn fire(&mut self) -> Result<(), P::Error> {
+        if self.pulse.fire() {
+            self.pin.set_high()?;
+        }
+        Ok(())
+    }
+
+    /// Advance by one tick, ending or starting pulses as needed.
+    ///
+    /// If the pin fails, the beat keeps its timing and the error is returned.
+    pub fn tick(&mut self) -> Result<(), P::Error> {
+        match self.pulse.tick() {
+            Some(true) => self.pin.set_high(),
+            Some(false) => self.pin.set_low(),
+            None => Ok(()),
+        }
+    }
+
+    /// Get back the pin.
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
+
+/// Raises one bit of an [I/O port](IoPort) of the chip for a few milliseconds, like a
+/// [TriggerOut] for boards out of GPIOs.
+///
+/// The port is made an output, and only the trigger's bit is changed: the others keep
+/// what was last written to the port. Each edge is a write to R14 or R15.
+///
+/// Example:
+/// ```no_run
+/// // Strobe on IOA0, beat every 25 ticks of a 50 Hz timer
+/// let mut strobe = PortTrigger::new(&mut chip, IoPort::A, 0, 50, 60)?.with_period(25);
+///
+/// // 50 Hz timer
+/// chip.tick();
+/// strobe.tick(&mut chip)?;
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PortTrigger {
+    port: IoPort,
+    mask: u8,
+    pulse: Pulse,
+}
+
+impl PortTrigger {
+    /// A trigger raising `bit` (0 - 7) of `port` for `pulse_ms` milliseconds when
+    /// [fired](PortTrigger::fire), ticked `tick_rate` times per second.
+    ///
+    /// Returns [Error::Unsupported] on chips without I/O ports.
+    pub fn new<DATABUS, BC1, BDIR>(
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+        port: IoPort,
+        bit: u8,
+        tick_rate: u32,
+        pulse_ms: u32,
+    ) -> Result<Self, Error>
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let trigger = Self {
+            port,
+            mask: 1 << (bit & 7),
+            pulse: Pulse::new(tick_rate, pulse_ms),
+        };
+        trigger.set(chip, false)?;
+        chip.set_io_direction(port, true)?;
+        Ok(trigger)
+    }
+
+    /// Also fire every `period` ticks, see [TriggerOut::with_period].
+    pub fn with_period(mut self, period: u16) -> Self {
+        self.pulse = self.pulse.with_period(period);
+        self
+    }
+
+    /// Start a pulse now.
+    pub fn fire<DATABUS, BC1, BDIR>(
+        &mut self,
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+    ) -> Result<(), Error>
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        if self.pulse.fire() {
+            self.set(chip, true)?;
+        }
+        Ok(())
+    }
+
+    /// Advance by one tick, ending or starting pulses as needed.
+    pub fn tick<DATABUS, BC1, BDIR>(
+        &mut self,
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+    ) -> Result<(), Error>
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        match self.pulse.tick() {
+            Some(high) => self.set(chip, high),
+            None => Ok(()),
+        }
+    }
+
+    fn set<DATABUS, BC1, BDIR>(
+        &self,
+        chip: &mut YM2149<DATABUS, BC1, BDIR>,
+        high: bool,
+    ) -> Result<(), Error>
+    where
+        DATABUS: OutputBus,
+        BC1: OutputPin,
+        BDIR: OutputPin,
+    {
+        let value = chip.register(Register::DataIoA as u8 + self.port as u8);
+        let value = if high {
+            value | self.mask
+        } else {
+            value & !self.mask
+        };
+        chip.write_io_port(self.port, value)
+    }
+}
@@ -0,0 +1,364 @@
+//! Voices and voice allocation: deciding which channel plays an incoming note.
+//!
+//! Everything here is generic over the number of voices `N`, 3 per chip, so a single chip
+//! and several chips driven together (6, 9 voices...) share the same code.
+//!
+//! Notes are identified by a key, usually a MIDI note number, so the same allocator
+//! can sit behind MIDI input, a sequencer or anything else producing note-ons.
+
+use core::ops::{Index, IndexMut};
+
+use embedded_hal::digital::OutputPin;
+
+use crate::{AudioChannel, OutputBus, YM2149};
+
+/// One voice among several chips: voice `n` is channel `n % 3` of chip `n / 3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VoiceId(pub usize);
+
+impl VoiceId {
+    /// Index of the chip the voice belongs to.
+    pub const fn chip(self) -> usize {
+        self.0 / 3
+    }
+
+    /// Channel of the voice on its chip.
+    pub const fn channel(self) -> AudioChannel {
+        AudioChannel::wrapping_from_index(self.0)
+    }
+}
+
+impl From<AudioChannel> for VoiceId {
+    /// The voice of a channel on the first chip.
+    fn from(channel: AudioChannel) -> Self {
+        Self(channel as usize)
+    }
+}
+
+/// The chips playing a set of voices: a single [YM2149], or an array of them for 6, 9...
+/// voices. Chip `n` plays voices `3n` to `3n + 2`, see [VoiceId].
+///
+/// Example:
+/// ```no_run
+/// let mut sfx = SfxManager::<6>::with_voices();
+/// let mut chips = [first_chip, second_chip];
+///
+/// // Channel B of the second chip
+/// sfx.play(&mut chips, VoiceId(4), Sfx::laser(8));
+/// ```
+pub trait Chips {
+    type DataBus: OutputBus;
+    type Bc1: OutputPin;
+    type Bdir: OutputPin;
+
+    /// Chip `index`, `None` past the last one.
+    fn chip(&mut self, index: usize) -> Option<&mut ChipOf<Self>>;
+
+    /// The chip playing a voice, and the voice's channel on it.
+    fn voice(&mut self, voice: VoiceId) -> Option<(&mut ChipOf<Self>, AudioChannel)> {
+        Some((self.chip(voice.chip())?, voice.channel()))
+    }
+}
+
+/// The type of the chips in a [Chips].
+type ChipOf<C> = YM2149<<C as Chips>::DataBus, <C as Chips>::Bc1, <C as Chips>::Bdir>;
+
+impl<DATABUS, BC1, BDIR> Chips for YM2149<DATABUS, BC1, BDIR>
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    type DataBus = DATABUS;
+    type Bc1 = BC1;
+    type Bdir = BDIR;
+
+    fn chip(&mut self, index: usize) -> Option<&mut Self> {
+        (index == 0).then_some(self)
+    }
+}
+
+impl<DATABUS, BC1, BDIR, const M: usize> Chips for [YM2149<DATABUS, BC1, BDIR>; M]
+where
+    DATABUS: OutputBus,
+    BC1: OutputPin,
+    BDIR: OutputPin,
+{
+    type DataBus = DATABUS;
+    type Bc1 = BC1;
+    type Bdir = BDIR;
+
+    fn chip(&mut self, index: usize) -> Option<&mut YM2149<DATABUS, BC1, BDIR>> {
+        self.get_mut(index)
+    }
+}
+
+/// Per-voice storage for `N` voices, indexed by [VoiceId] (or [AudioChannel] for the first chip).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Voices<T, const N: usize>(pub [T; N]);
+
+impl<T: Copy, const N: usize> Voices<T, N> {
+    /// All voices set to `value`.
+    pub const fn new(value: T) -> Self {
+        Self([value; N])
+    }
+}
+
+impl<T, const N: usize> Voices<T, N> {
+    /// Every voice with its [VoiceId].
+    pub fn iter(&self) -> impl Iterator<Item = (VoiceId, &T)> {
+        self.0.iter().enumerate().map(|(i, t)| (VoiceId(i), t))
+    }
+
+    /// The voice `voice`, `None` past the last one.
+    pub fn get(&self, voice: impl Into<VoiceId>) -> Option<&T> {
+        self.0.get(voice.into().0)
+    }
+
+    /// The voice `voice`, mutably, `None` past the last one.
+    pub fn get_mut(&mut self, voice: impl Into<VoiceId>) -> Option<&mut T> {
+        self.0.get_mut(voice.into().0)
+    }
+
+    /// Every voice with its [VoiceId], mutably.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (VoiceId, &mut T)> {
+        self.0.iter_mut().enumerate().map(|(i, t)| (VoiceId(i), t))
+    }
+}
+
+/// Panics past the last voice, like a slice. See [Voices::get] for a checked version.
+impl<T, const N: usize, I: Into<VoiceId>> Index<I> for Voices<T, N> {
+    type Output = T;
+
+    #[allow(clippy::indexing_slicing)]
+    fn index(&self, voice: I) -> &T {
+        &self.0[voice.into().0]
+    }
+}
+
+impl<T, const N: usize, I: Into<VoiceId>> IndexMut<I> for Voices<T, N> {
+    #[allow(clippy::indexing_slicing)]
+    fn index_mut(&mut self, voice: I) -> &mut T {
+        &mut self.0[voice.into().0]
+    }
+}
+
+/// What to do when a note-on arrives and every voice is busy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StealPolicy {
+    /// The new note always plays, replacing the most recently started one.
+    #[default]
+    Last,
+    /// The new note always plays, replacing the one held the longest.
+    Oldest,
+    /// Low notes win: the new note replaces the highest one, if it's lower.
+    Lowest,
+    /// High notes win: the new note replaces the lowest one, if it's higher.
+    Highest,
+}
+
+/// What a note is used for, so voices can be reserved for some parts of a song.
+///
+/// Notes started with [VoiceAllocator::note_on] are [Melodic](Role::Melodic).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Role {
+    #[default]
+    Melodic,
+    Bass,
+    Drums,
+}
+
+/// The result of a successful [VoiceAllocator::note_on].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation {
+    /// Voice the note should be played on.
+    pub voice: VoiceId,
+    /// Key that was playing on the voice and got cut off, if any.
+    pub stolen: Option<u8>,
+}
+
+impl Allocation {
+    /// Channel the note should be played on, see [VoiceId::channel].
+    pub const fn channel(&self) -> AudioChannel {
+        self.voice.channel()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    key: u8,
+    role: Role,
+    /// Value of the allocator's counter when the note started
+    started: u32,
+}
+
+/// Assigns notes to `N` voices (3 by default, a single chip) following a [StealPolicy].
+///
+/// Voices can be [reserved](VoiceAllocator::reserve) for a [Role], in which case only notes
+/// of that role may use (or steal) them. Unreserved voices are shared by every role.
+///
+/// Example:
+/// ```no_run
+/// let mut voices = VoiceAllocator::new(StealPolicy::Oldest);
+///
+/// if let Some(allocation) = voices.note_on(60) {
+///     chip.note(allocation.channel(), "C4")?;
+/// }
+/// if let Some(voice) = voices.note_off(60) {
+///     chip.volume(voice.channel(), Level::OFF);
+/// }
+///
+/// // C only plays drums, so a kick never cuts a melody or bass note off
+/// voices.reserve(AudioChannel::C, Some(Role::Drums));
+/// voices.note_on_as(36, Role::Drums);
+///
+/// // Two chips, use VoiceId::chip to pick the one to write to
+/// let mut voices = VoiceAllocator::<6>::with_voices(StealPolicy::Oldest);
+/// ```
+#[derive(Debug, Clone)]
+pub struct VoiceAllocator<const N: usize = 3> {
+    policy: StealPolicy,
+    slots: Voices<Option<Slot>, N>,
+    reservations: Voices<Option<Role>, N>,
+    counter: u32,
+}
+
+impl VoiceAllocator {
+    /// An allocator for the 3 channels of a single chip.
+    pub const fn new(policy: StealPolicy) -> Self {
+        Self::with_voices(policy)
+    }
+}
+
+impl<const N: usize> VoiceAllocator<N> {
+    /// An allocator for `N` voices.
+    pub const fn with_voices(policy: StealPolicy) -> Self {
+        Self {
+            policy,
+            slots: Voices::new(None),
+            reservations: Voices::new(None),
+            counter: 0,
+        }
+    }
+
+    pub fn policy(&self) -> StealPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: StealPolicy) {
+        self.policy = policy;
+    }
+
+    /// Reserve a voice for notes of a given [Role], or share it again with `None`.
+    ///
+    /// A note already playing on the voice is left alone until it's released or stolen.
+    /// Ignored past the last voice.
+    pub fn reserve(&mut self, voice: impl Into<VoiceId>, role: Option<Role>) {
+        if let Some(reservation) = self.reservations.get_mut(voice) {
+            *reservation = role;
+        }
+    }
+
+    /// The [Role] a voice is reserved for, if any.
+    pub fn reservation(&self, voice: impl Into<VoiceId>) -> Option<Role> {
+        self.reservations.get(voice).copied().flatten()
+    }
+
+    /// Find a voice for a [Melodic](Role::Melodic) `key`.
+    ///
+    /// A key that is already playing keeps its voice. Returns `None` if the
+    /// policy decided the new note loses against the ones already playing.
+    pub fn note_on(&mut self, key: u8) -> Option<Allocation> {
+        self.note_on_as(key, Role::Melodic)
+    }
+
+    /// Release a [Melodic](Role::Melodic) `key`, returning the voice it was playing on.
+    pub fn note_off(&mut self, key: u8) -> Option<VoiceId> {
+        self.note_off_as(key, Role::Melodic)
+    }
+
+    /// Find a voice for `key`, among the ones available to `role`.
+    pub fn note_on_as(&mut self, key: u8, role: Role) -> Option<Allocation> {
+        let voice = self
+            .find(key, role)
+            .or_else(|| {
+                self.slots
+                    .iter()
+                    .find(|&(voice, slot)| slot.is_none() && self.allows(voice, role))
+                    .map(|(voice, _)| voice)
+            })
+            .or_else(|| self.victim(key, role))?;
+
+        let slot = self.slots.get_mut(voice)?;
+        let stolen = slot
+            .filter(|slot| slot.key != key || slot.role != role)
+            .map(|slot| slot.key);
+        *slot = Some(Slot {
+            key,
+            role,
+            started: self.counter,
+        });
+        self.counter = self.counter.wrapping_add(1);
+
+        Some(Allocation { voice, stolen })
+    }
+
+    /// Release a `key` of a given `role`, returning the voice it was playing on.
+    pub fn note_off_as(&mut self, key: u8, role: Role) -> Option<VoiceId> {
+        let voice = self.find(key, role)?;
+        *self.slots.get_mut(voice)? = None;
+        Some(voice)
+    }
+
+    /// Key currently assigned to a voice.
+    pub fn key(&self, voice: impl Into<VoiceId>) -> Option<u8> {
+        self.slots
+            .get(voice)
+            .copied()
+            .flatten()
+            .map(|slot| slot.key)
+    }
+
+    /// Release every voice.
+    pub fn reset(&mut self) {
+        self.slots = Voices::new(None);
+    }
+
+    fn find(&self, key: u8, role: Role) -> Option<VoiceId> {
+        self.slots
+            .iter()
+            .find(|(_, slot)| slot.is_some_and(|slot| slot.key == key && slot.role == role))
+            .map(|(voice, _)| voice)
+    }
+
+    /// Whether notes of `role` may use `voice`.
+    fn allows(&self, voice: VoiceId, role: Role) -> bool {
+        self.reservations
+            .get(voice)
+            .is_some_and(|reserved| reserved.is_none_or(|reserved| reserved == role))
+    }
+
+    /// Voice to steal for `key` when all the ones available to `role` are busy.
+    fn victim(&self, key: u8, role: Role) -> Option<VoiceId> {
+        let busy = self
+            .slots
+            .iter()
+            .filter(|&(voice, _)| self.allows(voice, role))
+            .filter_map(|(voice, slot)| slot.map(|slot| (voice, slot)));
+        // Age relative to the counter, so wrapping around doesn't matter
+        let age = |slot: &Slot| self.counter.wrapping_sub(slot.started);
+
+        let (voice, slot) = match self.policy {
+            StealPolicy::Last => busy.min_by_key(|(_, slot)| age(slot)),
+            StealPolicy::Oldest => busy.max_by_key(|(_, slot)| age(slot)),
+            StealPolicy::Lowest => busy.max_by_key(|(_, slot)| slot.key),
+            StealPolicy::Highest => busy.min_by_key(|(_, slot)| slot.key),
+        }?;
+
+        match self.policy {
+            StealPolicy::Lowest if key > slot.key => None,
+            StealPolicy::Highest if key < slot.key => None,
+            _ => Some(voice),
+        }
+    }
+}
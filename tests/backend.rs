@@ -0,0 +1,42 @@
+//! Checks the chip reports bus failures through the backend trait.
+
+mod common;
+
+use common::NullPin;
+use ym2149::*;
+
+/// A bus losing every byte.
+struct FailingBus;
+
+impl OutputBus for FailingBus {
+    fn write_u8(&mut self, _: u8) {}
+
+    fn try_write_u8(&mut self, _: u8) -> Result<(), BusError> {
+        Err(BusError)
+    }
+}
+
+#[test]
+fn frames_report_bus_failures() {
+    let mut chip = YM2149::new(FailingBus, 2_000_000, NullPin, NullPin);
+    let mut frame = Frame::default();
+    frame.registers[Register::ALevel as usize] = 15;
+    frame.registers[Register::BLevel as usize] = 12;
+
+    assert_eq!(PsgBackend::apply_frame(&mut chip, &frame), Err(Error::Bus));
+    // Every register of the frame was still tried
+    assert!(!chip.is_register_known(Register::ALevel));
+    assert!(!chip.is_register_known(Register::BLevel));
+    assert!(chip.is_register_known(Register::CLevel));
+}
+
+#[test]
+fn mirrors_count_frames_failing_on_the_chip() {
+    let chip = YM2149::new(FailingBus, 2_000_000, NullPin, NullPin);
+    let mut psg = MirrorBackend::new(chip, [0u8; 16]);
+    let mut frame = Frame::default();
+    frame.registers[Register::ALevel as usize] = 15;
+
+    assert_eq!(psg.apply_frame(&frame), Err(Error::Bus));
+    assert_eq!(psg.divergences(), 1);
+}
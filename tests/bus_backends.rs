@@ -0,0 +1,289 @@
+//! Checks the exact bus traffic of the SPI and I²C backends and adapters with `embedded-hal-mock`.
+//!
+//! These run on the host, e.g. `cargo test --target x86_64-unknown-linux-gnu --test bus_backends`.
+
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction as PinTransaction};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+use ym2149::*;
+
+/// BDIR and BC1 expectations for `writes` register writes: ADDRESS, INACTIVE, WRITE, INACTIVE.
+fn register_write_pins(writes: usize) -> (PinMock, PinMock) {
+    let bdir = [State::High, State::Low, State::High, State::Low].repeat(writes);
+    let bc1 = [State::High, State::Low, State::Low, State::Low].repeat(writes);
+    let transactions = |states: Vec<State>| -> Vec<PinTransaction> {
+        states.into_iter().map(PinTransaction::set).collect()
+    };
+    (
+        PinMock::new(&transactions(bdir)),
+        PinMock::new(&transactions(bc1)),
+    )
+}
+
+fn spi_byte(byte: u8) -> [SpiTransaction<u8>; 3] {
+    [
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write_vec(vec![byte]),
+        SpiTransaction::transaction_end(),
+    ]
+}
+
+#[test]
+fn shift_register_writes_one_byte_per_transaction() {
+    let mut spi = SpiMock::new(&spi_byte(0xA5));
+    let mut bus = ShiftRegisterBus::new(spi.clone());
+
+    bus.write_u8(0xA5);
+
+    spi.done();
+}
+
+#[test]
+fn shift_register_register_write() {
+    let expectations = [spi_byte(8), spi_byte(0x0F)].concat();
+    let mut spi = SpiMock::new(&expectations);
+    let (mut bdir, mut bc1) = register_write_pins(1);
+
+    let mut chip = YM2149::new(
+        ShiftRegisterBus::new(spi.clone()),
+        2_000_000,
+        bc1.clone(),
+        bdir.clone(),
+    );
+    chip.write_register(Register::ALevel, 0x0F).unwrap();
+
+    spi.done();
+    bdir.done();
+    bc1.done();
+}
+
+#[test]
+fn pcf8574_register_write() {
+    let mut i2c = I2cMock::new(&[
+        I2cTransaction::write(0x20, vec![7]),
+        I2cTransaction::write(0x20, vec![0b0011_1110]),
+    ]);
+    let (mut bdir, mut bc1) = register_write_pins(1);
+
+    let bus = I2cExpanderBus::new(i2c.clone(), Expander::Pcf8574, 0x20).unwrap();
+    let mut chip = YM2149::new(bus, 2_000_000, bc1.clone(), bdir.clone());
+    chip.write_register(Register::IoPortMixerSettings, 0b0011_1110)
+        .unwrap();
+
+    i2c.done();
+    bdir.done();
+    bc1.done();
+}
+
+#[test]
+fn mcp23008_register_write() {
+    let mut i2c = I2cMock::new(&[
+        // All pins as outputs
+        I2cTransaction::write(0x21, vec![0x00, 0x00]),
+        // Then each byte goes to OLAT
+        I2cTransaction::write(0x21, vec![0x0A, 0]),
+        I2cTransaction::write(0x21, vec![0x0A, 0x1C]),
+    ]);
+    let (mut bdir, mut bc1) = register_write_pins(1);
+
+    let bus = I2cExpanderBus::new(i2c.clone(), Expander::Mcp23008, 0x21).unwrap();
+    let mut chip = YM2149::new(bus, 2_000_000, bc1.clone(), bdir.clone());
+    chip.write_register(Register::AFreq8bitFinetone, 0x1C)
+        .unwrap();
+
+    i2c.done();
+    bdir.done();
+    bc1.done();
+}
+
+#[test]
+fn tee_bus_captures_register_writes() {
+    let expectations = [spi_byte(8), spi_byte(0x0F), spi_byte(0), spi_byte(0x1C)].concat();
+    let mut spi = SpiMock::new(&expectations);
+    let (mut bdir, mut bc1) = register_write_pins(2);
+
+    let mut captured = Vec::new();
+    let bus = TeeBus::new(ShiftRegisterBus::new(spi.clone()), |register, value| {
+        captured.push((register, value))
+    });
+    let mut chip = YM2149::new(bus, 2_000_000, bc1.clone(), bdir.clone());
+    chip.write_register(Register::ALevel, 0x0F).unwrap();
+    chip.write_register(Register::AFreq8bitFinetone, 0x1C)
+        .unwrap();
+    drop(chip);
+
+    assert_eq!(captured, [(8, 0x0F), (0, 0x1C)]);
+    spi.done();
+    bdir.done();
+    bc1.done();
+}
+
+#[test]
+fn address_caching_skips_repeated_address_phase() {
+    let expectations = [spi_byte(8), spi_byte(0x0F), spi_byte(0x0E)].concat();
+    let mut spi = SpiMock::new(&expectations);
+    // ADDRESS, INACTIVE, WRITE, INACTIVE, then WRITE, INACTIVE only
+    let transactions = |states: &[State]| -> Vec<PinTransaction> {
+        states.iter().copied().map(PinTransaction::set).collect()
+    };
+    let (high, low) = (State::High, State::Low);
+    let mut bdir = PinMock::new(&transactions(&[high, low, high, low, high, low]));
+    let mut bc1 = PinMock::new(&transactions(&[high, low, low, low, low, low]));
+
+    let bus = ShiftRegisterBus::new(spi.clone()).with_address_caching(true);
+    let mut chip = YM2149::new(bus, 2_000_000, bc1.clone(), bdir.clone());
+    chip.write_register(Register::ALevel, 0x0F).unwrap();
+    chip.write_register(Register::ALevel, 0x0E).unwrap();
+
+    spi.done();
+    bdir.done();
+    bc1.done();
+}
+
+#[test]
+fn chip_drives_a_bus_picked_at_runtime() {
+    let expectations = [spi_byte(8), spi_byte(0x0F)].concat();
+    let mut spi = SpiMock::new(&expectations);
+    let (mut bdir, mut bc1) = register_write_pins(1);
+
+    let mut shift_register = ShiftRegisterBus::new(spi.clone());
+    let bus: &mut dyn OutputBus = &mut shift_register;
+    let mut chip = YM2149::new(bus, 2_000_000, bc1.clone(), bdir.clone());
+    chip.write_register(Register::ALevel, 0x0F).unwrap();
+
+    spi.done();
+    bdir.done();
+    bc1.done();
+}
+
+#[test]
+fn failed_address_phase_leaves_bus_inactive_and_register_unknown() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let mut i2c = I2cMock::new(&[
+        I2cTransaction::write(0x20, vec![8])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+        I2cTransaction::write(0x20, vec![8]),
+        I2cTransaction::write(0x20, vec![0x0F]),
+    ]);
+    // ADDRESS, INACTIVE, then a whole register write
+    let mut bdir = PinMock::new(
+        &[
+            State::High,
+            State::Low,
+            State::High,
+            State::Low,
+            State::High,
+            State::Low,
+        ]
+        .map(PinTransaction::set),
+    );
+    let mut bc1 = PinMock::new(
+        &[
+            State::High,
+            State::Low,
+            State::High,
+            State::Low,
+            State::Low,
+            State::Low,
+        ]
+        .map(PinTransaction::set),
+    );
+
+    let bus = I2cExpanderBus::new(i2c.clone(), Expander::Pcf8574, 0x20).unwrap();
+    let mut chip = YM2149::new(bus, 2_000_000, bc1.clone(), bdir.clone());
+    assert_eq!(chip.write_register(Register::ALevel, 0x0F), Err(Error::Bus));
+    assert!(!chip.is_register_known(Register::ALevel));
+
+    chip.write_register(Register::ALevel, 0x0F).unwrap();
+    assert!(chip.is_register_known(Register::ALevel));
+
+    i2c.done();
+    bdir.done();
+    bc1.done();
+}
+
+#[test]
+fn retrigger_limit_writes_r13_once_per_tick() {
+    let expectations = [
+        spi_byte(13),
+        spi_byte(0x0E),
+        // Held back until the tick
+        spi_byte(13),
+        spi_byte(0x08),
+        // Forced
+        spi_byte(13),
+        spi_byte(0x0C),
+    ]
+    .concat();
+    let mut spi = SpiMock::new(&expectations);
+    let (mut bdir, mut bc1) = register_write_pins(3);
+
+    let mut chip = YM2149::new(
+        ShiftRegisterBus::new(spi.clone()),
+        2_000_000,
+        bc1.clone(),
+        bdir.clone(),
+    )
+    .with_retrigger_limit(true);
+    chip.write_register(Register::EShape, 0x0E).unwrap();
+    chip.write_register(Register::EShape, 0x0A).unwrap();
+    chip.write_register(Register::EShape, 0x08).unwrap();
+    assert_eq!(chip.register(Register::EShape), 0x08);
+
+    chip.tick();
+    chip.write_register(Register::EShape, 0x0C).unwrap();
+    assert_eq!(chip.register(Register::EShape), 0x0C);
+    chip.force_retrigger();
+
+    spi.done();
+    bdir.done();
+    bc1.done();
+}
+
+#[test]
+fn reinitialize_ignores_the_retrigger_limit() {
+    let mut expectations = [spi_byte(13), spi_byte(0x0E)].concat();
+    for register in 0..16 {
+        let value = if register == 13 { 0x08 } else { 0 };
+        expectations.extend(spi_byte(register));
+        expectations.extend(spi_byte(value));
+    }
+    let mut spi = SpiMock::new(&expectations);
+    // One write, INACTIVE before the reset pulse, then every register
+    let write_bdir = [State::High, State::Low, State::High, State::Low];
+    let write_bc1 = [State::High, State::Low, State::Low, State::Low];
+    let pins = |write: [State; 4]| -> Vec<PinTransaction> {
+        [write.to_vec(), vec![State::Low], write.repeat(16)]
+            .concat()
+            .into_iter()
+            .map(PinTransaction::set)
+            .collect()
+    };
+    let mut bdir = PinMock::new(&pins(write_bdir));
+    let mut bc1 = PinMock::new(&pins(write_bc1));
+    let mut reset = PinMock::new(&[
+        PinTransaction::set(State::Low),
+        PinTransaction::set(State::High),
+    ]);
+
+    let mut chip = YM2149::new(
+        ShiftRegisterBus::new(spi.clone()),
+        2_000_000,
+        bc1.clone(),
+        bdir.clone(),
+    )
+    .with_retrigger_limit(true);
+    chip.write_register(Register::EShape, 0x0E).unwrap();
+    chip.write_register(Register::EShape, 0x08).unwrap();
+    chip.reinitialize(&mut reset, &mut NoopDelay::new())
+        .unwrap();
+    // Nothing left held back
+    chip.tick();
+
+    spi.done();
+    bdir.done();
+    bc1.done();
+    reset.done();
+}
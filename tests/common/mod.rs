@@ -0,0 +1,71 @@
+//! Chips on buses that go nowhere, to check the driver's register shadow and writes on the host.
+
+#![allow(dead_code)]
+
+use core::convert::Infallible;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use embedded_hal::digital::{ErrorType, OutputPin};
+use ym2149::{OutputBus, YM2149};
+
+pub struct NullPin;
+
+impl ErrorType for NullPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NullPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+pub struct NullBus;
+
+impl OutputBus for NullBus {
+    fn write_u8(&mut self, _: u8) {}
+}
+
+pub type Chip = YM2149<NullBus, NullPin, NullPin>;
+
+/// A chip clocked at 2 MHz, like on the Atari ST.
+pub fn chip() -> Chip {
+    YM2149::new(NullBus, 2_000_000, NullPin, NullPin)
+}
+
+/// A bus keeping the register writes reaching it, as `(register, value)`. Clones share them.
+#[derive(Clone, Default)]
+pub struct RecordingBus {
+    writes: Rc<RefCell<Vec<(u8, u8)>>>,
+    /// Register address sent, waiting for its value
+    address: Rc<RefCell<Option<u8>>>,
+}
+
+impl RecordingBus {
+    /// The writes so far, forgetting them.
+    pub fn take(&self) -> Vec<(u8, u8)> {
+        self.writes.take()
+    }
+}
+
+impl OutputBus for RecordingBus {
+    fn write_u8(&mut self, data: u8) {
+        let mut address = self.address.borrow_mut();
+        match address.take() {
+            Some(register) => self.writes.borrow_mut().push((register, data)),
+            None => *address = Some(data),
+        }
+    }
+}
+
+/// A chip on a [RecordingBus], and a handle on what it records.
+pub fn recording_chip() -> (YM2149<RecordingBus, NullPin, NullPin>, RecordingBus) {
+    let bus = RecordingBus::default();
+    let chip = YM2149::new(bus.clone(), 2_000_000, NullPin, NullPin);
+    (chip, bus)
+}
@@ -0,0 +1,49 @@
+//! Checks the master attenuation reaches the chip without changing the register shadow.
+
+mod common;
+
+use ym2149::*;
+
+#[test]
+fn ducker_drives_the_master_attenuation() {
+    let (mut chip, writes) = common::recording_chip();
+    chip.volume(AudioChannel::A, Level::fixed(12));
+
+    let mut ducker = Ducker::new(100, 6).with_attack(0).with_release(0);
+    ducker.update(200);
+    writes.take();
+    ducker.drive(&mut chip);
+    assert_eq!(chip.master_attenuation(), 6);
+    assert_eq!(writes.take(), [(8, 6), (9, 0), (10, 0)]);
+    assert_eq!(chip.register(Register::ALevel), 12);
+
+    // Coming back restores the levels as written
+    ducker.update(0);
+    writes.take();
+    ducker.drive(&mut chip);
+    assert_eq!(writes.take(), [(8, 12), (9, 0), (10, 0)]);
+}
+
+#[test]
+fn every_level_write_is_attenuated() {
+    let (mut chip, writes) = common::recording_chip();
+    chip.set_master_attenuation(4);
+
+    writes.take();
+    chip.volume(AudioChannel::A, Level::fixed(10));
+    assert_eq!(writes.take(), [(8, 6)]);
+
+    let mut frame = chip.frame();
+    frame.registers[Register::BLevel as usize] = 15;
+    frame.registers[Register::CLevel as usize] = 0x10;
+    writes.take();
+    chip.apply_frame(&frame);
+    // Envelope-driven channels can't be lowered
+    assert_eq!(writes.take(), [(9, 11), (10, 0x10)]);
+    assert_eq!(chip.register(Register::BLevel), 15);
+
+    chip.play_note(AudioChannel::B, "A4".parse().unwrap());
+    writes.take();
+    chip.set_software_envelope(AudioChannel::B, Some(RawEnvelope::from_slice(&[13])));
+    assert_eq!(writes.take(), [(9, 9)]);
+}
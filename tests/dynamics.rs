@@ -0,0 +1,62 @@
+//! Checks velocity curves map the whole velocity range onto the levels, loudest last.
+
+use ym2149::*;
+
+const CURVES: [VelocityCurve; 5] = [
+    VelocityCurve::Linear,
+    VelocityCurve::Exponential,
+    VelocityCurve::Steps(0),
+    VelocityCurve::Steps(4),
+    VelocityCurve::Steps(20),
+];
+
+fn level(curve: VelocityCurve, velocity: u8) -> u8 {
+    curve.level(velocity).bits()
+}
+
+#[test]
+fn curves_cover_silence_to_full_level() {
+    for curve in CURVES {
+        assert_eq!(curve.level(0), Level::OFF, "{curve:?}");
+        assert_eq!(curve.level(127), Level::MAX, "{curve:?}");
+        assert_eq!(curve.level(255), Level::MAX, "{curve:?}");
+        assert!(level(curve, 1) >= 1, "{curve:?}");
+
+        let levels: Vec<_> = (0..=127).map(|velocity| level(curve, velocity)).collect();
+        assert!(
+            levels.windows(2).all(|pair| pair[0] <= pair[1]),
+            "{curve:?}"
+        );
+    }
+}
+
+#[test]
+fn curves_shape_the_middle_of_the_range() {
+    assert_eq!(level(VelocityCurve::Linear, 64), 8);
+    // Soft playing stays quiet
+    assert_eq!(level(VelocityCurve::Exponential, 64), 3);
+    assert_eq!(level(VelocityCurve::Exponential, 1), 1);
+
+    let mut steps: Vec<_> = (1..=127)
+        .map(|velocity| level(VelocityCurve::Steps(4), velocity))
+        .collect();
+    steps.dedup();
+    assert_eq!(steps, [4, 8, 11, 15]);
+    assert_eq!(level(VelocityCurve::Steps(0), 1), 15);
+}
+
+#[test]
+fn envelopes_peak_at_the_velocity_level() {
+    let pluck = RawEnvelope::from_slice(&[15, 12, 8, 4, 0]);
+    for curve in CURVES {
+        for velocity in [1, 40, 64, 100, 127] {
+            let scaled = curve.envelope(velocity, pluck);
+            let [peak, .., last] = *scaled.levels() else {
+                panic!("empty envelope");
+            };
+            assert_eq!(peak, level(curve, velocity), "{curve:?} at {velocity}");
+            assert_eq!(last, 0);
+            assert!(scaled.levels().windows(2).all(|pair| pair[0] >= pair[1]));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Checks the envelope period math, envelope bass tuning and the R13 shape table.
+
+mod common;
+
+use ym2149::envelope::{envelope_hz_for_period, envelope_period_for_hz, period_for_note};
+use ym2149::*;
+
+const CLOCK: u32 = 2_000_000;
+
+#[test]
+fn periods_and_frequencies_round_to_nearest() {
+    // 2 MHz / 256 = 7812.5
+    assert_eq!(envelope_period_for_hz(CLOCK, 1), 7813);
+    assert_eq!(envelope_period_for_hz(CLOCK, 100), 78);
+    assert_eq!(envelope_hz_for_period(CLOCK, 7813), 1);
+    assert_eq!(envelope_hz_for_period(CLOCK, 78), 100);
+
+    // Out of range
+    assert_eq!(envelope_period_for_hz(CLOCK, 0), u16::MAX);
+    assert_eq!(envelope_period_for_hz(20_000_000, 1), u16::MAX);
+    assert_eq!(envelope_period_for_hz(CLOCK, 1_000_000), 1);
+    assert_eq!(envelope_hz_for_period(CLOCK, 0), 7813);
+    assert_eq!(envelope_hz_for_period(CLOCK, 20_000), 0);
+}
+
+#[test]
+fn triangles_take_two_periods_per_cycle() {
+    let hz = EnvelopeFrequency::Hz(100);
+    assert_eq!(hz.period(CLOCK, EnvelopeShape::SawDown), 78);
+    assert_eq!(hz.period(CLOCK, EnvelopeShape::TriangleUp), 39);
+    // One-shot shapes play for one period
+    assert_eq!(hz.period(CLOCK, EnvelopeShape::Decay), 78);
+    assert_eq!(
+        EnvelopeFrequency::Period(500).period(CLOCK, EnvelopeShape::TriangleUp),
+        500
+    );
+}
+
+/// How far a period plays from `key`, in octaves.
+fn octaves_off(key: u8, period: u16, cycle: u32) -> f64 {
+    let target = 440.0 * 2f64.powf((key as f64 - 69.0) / 12.0);
+    let played = CLOCK as f64 / (256.0 * cycle as f64 * period as f64);
+    (played / target).log2().abs()
+}
+
+#[test]
+fn envelope_bass_picks_the_closest_period_in_pitch() {
+    for (shape, cycle) in [(EnvelopeShape::SawDown, 1), (EnvelopeShape::TriangleUp, 2)] {
+        for key in 24..100 {
+            let period = period_for_note(CLOCK, Note::from_midi(key), shape).unwrap();
+            let off = octaves_off(key, period, cycle);
+            for neighbour in [period - 1, period + 1] {
+                if neighbour != 0 {
+                    assert!(off <= octaves_off(key, neighbour, cycle), "MIDI note {key}");
+                }
+            }
+        }
+    }
+    // A2, 110 Hz: 71.02 for a saw
+    let a2 = Note::from_midi(45);
+    assert_eq!(period_for_note(CLOCK, a2, EnvelopeShape::SawUp), Some(71));
+    assert_eq!(period_for_note(CLOCK, a2, EnvelopeShape::AttackHold), None);
+}
+
+#[test]
+fn envelope_bass_drives_the_channel() {
+    let mut chip = common::chip();
+    let a2 = Note::from_midi(45);
+    chip.envelope_bass(AudioChannel::C, a2, EnvelopeShape::SawDown);
+    assert_eq!(chip.register(Register::EFreq8bitFineAdj), 71);
+    assert_eq!(chip.register(Register::EFreq8bitRoughAdj), 0);
+    assert_eq!(
+        chip.register(Register::EShape),
+        EnvelopeShape::SawDown as u8
+    );
+    assert!(chip.is_envelope_driven(AudioChannel::C));
+
+    // One-shot shapes are refused
+    chip.envelope_bass(AudioChannel::A, a2, EnvelopeShape::Decay);
+    assert!(!chip.is_envelope_driven(AudioChannel::A));
+    assert_eq!(
+        chip.register(Register::EShape),
+        EnvelopeShape::SawDown as u8
+    );
+}
+
+#[test]
+fn every_value_of_r13_has_a_shape() {
+    for (bits, value) in (0..16).zip(ShapeValue::ALL) {
+        assert_eq!(value.bits(), bits);
+        assert_eq!(ShapeValue::from_bits(bits), value);
+        assert_eq!(value.flags().bits(), bits);
+        assert_eq!(value.shape(), EnvelopeShape::TABLE[bits as usize]);
+        assert_eq!(value.is_alias(), value.shape() as u8 != bits);
+    }
+    assert_eq!(EnvelopeShape::from_bits(0x09), EnvelopeShape::Decay);
+    assert_eq!(EnvelopeShape::from_bits(0x0F), EnvelopeShape::Attack);
+    assert_eq!(EnvelopeShape::from_bits(0xF8), EnvelopeShape::SawDown);
+    assert!(ShapeValue::Decay9.is_alias());
+    assert!(!ShapeValue::TriangleUp14.is_alias());
+
+    let flags = ShapeFlags {
+        cont: true,
+        att: true,
+        alt: true,
+        hold: false,
+    };
+    assert_eq!(flags.value(), ShapeValue::TriangleUp14);
+    assert_eq!(flags.shape(), EnvelopeShape::TriangleUp);
+}
@@ -0,0 +1,90 @@
+//! Checks the bytes of exported `.psg` and `.ym` files.
+
+use core::convert::Infallible;
+
+use ym2149::export::{self, ExportSink, YmInfo};
+
+/// A file in memory.
+#[derive(Default)]
+struct Bytes(Vec<u8>);
+
+impl ExportSink for Bytes {
+    type Error = Infallible;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Infallible> {
+        self.0.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Level of A at 0 ms, tone of A at 20 ms, an I/O port write and a retrigger at 120 ms.
+const WRITES: [(u64, u8, u8); 4] = [
+    (1_000, 8, 15),
+    (21_000, 0, 0x1C),
+    (121_000, 14, 0xAA),
+    (121_500, 13, 0x0E),
+];
+
+#[test]
+fn frames_carry_the_state_over() {
+    let frames: Vec<_> = export::frames(WRITES, 50).collect();
+    assert_eq!(frames.len(), 7);
+    assert_eq!(frames[0].registers[8], 15);
+    assert_eq!(frames[0].registers[0], 0);
+    for frame in &frames[1..] {
+        assert_eq!((frame.registers[0], frame.registers[8]), (0x1C, 15));
+    }
+    let retriggers: Vec<_> = frames.iter().map(|frame| frame.retrigger).collect();
+    assert_eq!(retriggers, [false, false, false, false, false, false, true]);
+}
+
+#[test]
+fn psg_groups_writes_by_frame() {
+    let mut out = Bytes::default();
+    export::write_psg(&mut out, WRITES, 50).unwrap();
+
+    let mut expected = b"PSG\x1A\x10\x32".to_vec();
+    expected.extend([0; 10]);
+    expected.extend([8, 15]);
+    expected.extend([0xFF, 0, 0x1C]);
+    // 5 frames later: 4 at once, then 1
+    expected.extend([0xFE, 1, 0xFF, 14, 0xAA, 13, 0x0E]);
+    expected.extend([0xFF, 0xFD]);
+    assert_eq!(out.0, expected);
+}
+
+#[test]
+fn ym_is_interleaved_by_register() {
+    let info = YmInfo {
+        frame_rate: 50,
+        master_clock_frequency: 2_000_000,
+        title: "T",
+        author: "A",
+        comment: "",
+    };
+    let mut out = Bytes::default();
+    export::write_ym(&mut out, WRITES, &info).unwrap();
+
+    let mut expected = b"YM5!LeOnArD!".to_vec();
+    expected.extend(7u32.to_be_bytes());
+    expected.extend(1u32.to_be_bytes());
+    expected.extend(0u16.to_be_bytes());
+    expected.extend(2_000_000u32.to_be_bytes());
+    expected.extend(50u16.to_be_bytes());
+    expected.extend(0u32.to_be_bytes());
+    expected.extend(0u16.to_be_bytes());
+    expected.extend(b"T\0A\0\0");
+    for register in 0..16 {
+        let column = match register {
+            0 => [0, 0x1C, 0x1C, 0x1C, 0x1C, 0x1C, 0x1C],
+            8 => [15; 7],
+            // Only written when the envelope restarts
+            13 => [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x0E],
+            // Includes the I/O ports, which aren't part of the song
+            _ => [0; 7],
+        };
+        expected.extend(column);
+    }
+    expected.extend(b"End!");
+    assert_eq!(out.0, expected);
+}
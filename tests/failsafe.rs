@@ -0,0 +1,27 @@
+//! Checks the failsafe mutes the chip once it stops being fed, and only then.
+
+mod common;
+
+use ym2149::*;
+
+#[test]
+fn mutes_after_a_window_without_feed() {
+    let mut chip = common::chip();
+    chip.volume(AudioChannel::A, Level::fixed(12));
+    let failsafe = AudioFailsafe::new(3);
+
+    assert!(!failsafe.check(&mut chip));
+    failsafe.feed();
+    assert!(!failsafe.check(&mut chip));
+    assert!(!failsafe.check(&mut chip));
+    assert_eq!(chip.register(Register::ALevel), 12);
+
+    assert!(failsafe.check(&mut chip));
+    assert!(failsafe.tripped());
+    assert_eq!(chip.register(Register::ALevel), 0);
+    // Only trips once
+    assert!(!failsafe.check(&mut chip));
+
+    failsafe.feed();
+    assert!(!failsafe.tripped());
+}
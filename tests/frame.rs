@@ -0,0 +1,77 @@
+//! Checks frame diffs: only changed registers, and R13 only when retriggering.
+
+mod common;
+
+use ym2149::*;
+
+fn frame(changes: &[(Register, u8)], retrigger: bool) -> Frame {
+    let mut frame = Frame::default();
+    for &(register, value) in changes {
+        frame.registers[register as usize] = value;
+    }
+    frame.retrigger = retrigger;
+    frame
+}
+
+#[test]
+fn diff_skips_unchanged_registers() {
+    let prev = frame(
+        &[(Register::ALevel, 15), (Register::NoiseFreq5bit, 3)],
+        false,
+    );
+    assert_eq!(Frame::diff(&prev, &prev).count(), 0);
+
+    let next = frame(
+        &[
+            (Register::CLevel, 10),
+            (Register::ALevel, 12),
+            (Register::NoiseFreq5bit, 3),
+        ],
+        false,
+    );
+    let writes: Vec<_> = Frame::diff(&prev, &next).collect();
+    assert_eq!(writes, [(Register::ALevel, 12), (Register::CLevel, 10)]);
+}
+
+#[test]
+fn diff_writes_r13_only_to_retrigger() {
+    let prev = frame(&[(Register::EShape, 0x0E)], false);
+
+    // A new shape alone doesn't restart the envelope
+    let next = frame(&[(Register::EShape, 0x08)], false);
+    assert_eq!(Frame::diff(&prev, &next).count(), 0);
+
+    // Retriggering writes R13 even with the same shape, after everything else
+    let next = frame(
+        &[(Register::EShape, 0x0E), (Register::EFreq8bitRoughAdj, 2)],
+        true,
+    );
+    let writes: Vec<_> = Frame::diff(&prev, &next).collect();
+    assert_eq!(
+        writes,
+        [(Register::EFreq8bitRoughAdj, 2), (Register::EShape, 0x0E)]
+    );
+}
+
+#[test]
+fn apply_frame_writes_the_diff() {
+    let (mut chip, writes) = common::recording_chip();
+    let first = frame(
+        &[(Register::AFreq8bitFinetone, 0x1C), (Register::ALevel, 15)],
+        true,
+    );
+    chip.apply_frame(&first);
+    assert_eq!(
+        writes.take(),
+        [(0, 0x1C), (8, 15), (Register::EShape as u8, 0)]
+    );
+
+    let mut second = first;
+    second.retrigger = false;
+    second.registers[Register::ALevel as usize] = 9;
+    chip.apply_frame(&second);
+    assert_eq!(writes.take(), [(8, 9)]);
+
+    chip.apply_frame(&second);
+    assert_eq!(writes.take(), []);
+}
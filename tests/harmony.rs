@@ -0,0 +1,89 @@
+//! Checks harmonized chords and quantized pitches stay in their scale.
+
+use ym2149::*;
+
+#[test]
+fn quantizer_snaps_up_from_under_the_lowest_note() {
+    // D major: the lowest MIDI notes in it are C#-1 (1) and D-1 (2)
+    let mut quantizer = ScaleQuantizer::new(Scale::major(2), 0);
+    for pitch in [0, 40, 50, 100, 140] {
+        assert_eq!(quantizer.quantize(pitch), 1, "{pitch} cents");
+    }
+    assert_eq!(quantizer.quantize(160), 2);
+}
+
+#[test]
+fn quantizer_only_gives_notes_of_the_scale() {
+    for scale in [
+        Scale::major(2),
+        Scale::new(9, Scale::MINOR_PENTATONIC),
+        Scale::new(11, Scale::HARMONIC_MINOR),
+    ] {
+        let mut quantizer = ScaleQuantizer::new(scale, 0);
+        for pitch in (-200..13000).step_by(10) {
+            let key = quantizer.quantize(pitch);
+            assert!(scale.contains(key), "{pitch} cents gave {key}");
+        }
+    }
+}
+
+fn harmonize(harmonizer: &Harmonizer, key: u8) -> Vec<u8> {
+    harmonizer.harmonize(key).collect()
+}
+
+#[test]
+fn chords_follow_the_scale() {
+    let harmonizer = Harmonizer::new(Scale::major(0), Harmonizer::TRIAD);
+    // C major, D minor, B diminished
+    assert_eq!(harmonize(&harmonizer, 60), [60, 64, 67]);
+    assert_eq!(harmonize(&harmonizer, 62), [62, 65, 69]);
+    assert_eq!(harmonize(&harmonizer, 71), [71, 74, 77]);
+    // Out of the scale: harmonized as the C below
+    assert_eq!(harmonize(&harmonizer, 61), [61, 64, 67]);
+    // Harmony notes past G9 are left out
+    assert_eq!(harmonize(&harmonizer, 127), [127]);
+
+    let seventh = Harmonizer::new(Scale::minor(9), Harmonizer::SEVENTH);
+    assert_eq!(harmonize(&seventh, 57), [57, 60, 64, 67]);
+    let below = Harmonizer::new(Scale::major(0), Harmonizer::THIRD_BELOW);
+    assert_eq!(harmonize(&below, 64), [64, 60]);
+    assert_eq!(harmonize(&below, 0), [0]);
+}
+
+#[test]
+fn degrees_and_keys_round_trip() {
+    let scale = Scale::new(2, Scale::DORIAN);
+    for key in 0..128 {
+        let degree = scale.degree(key);
+        let below = scale.key(degree).unwrap_or(0);
+        assert!(below <= key);
+        assert_eq!(scale.contains(key), below == key, "MIDI note {key}");
+    }
+    assert!(scale.contains(71));
+    assert!(!scale.contains(70));
+    assert_eq!(Scale::new(0, &[]).key(3), None);
+}
+
+#[test]
+fn quantizer_holds_its_note_within_the_hysteresis() {
+    // C major: E4 (64) and F4 (65) are a semitone apart, halfway at 6450 cents
+    let mut quantizer = ScaleQuantizer::new(Scale::major(0), 20);
+    assert_eq!(quantizer.quantize(6400), 64);
+    assert!(quantizer.changed());
+    assert_eq!(quantizer.quantize(6460), 64);
+    assert!(!quantizer.changed());
+    assert_eq!(quantizer.quantize(6471), 65);
+    assert!(quantizer.changed());
+    assert_eq!(quantizer.quantize(6430), 65);
+    assert_eq!(quantizer.quantize(6429), 64);
+
+    // Out of the scale, to the nearest note of it: F#4 is halfway, G4 wins past it
+    assert_eq!(quantizer.quantize(6700), 67);
+    assert_eq!(quantizer.quantize(6640), 67);
+    assert_eq!(quantizer.quantize(6500), 65);
+
+    // A new scale snaps right away: Db major has F4 and Gb4, 6560 is closer to Gb4
+    quantizer.set_scale(Scale::major(1));
+    assert_eq!(quantizer.current(), None);
+    assert_eq!(quantizer.quantize(6560), 66);
+}
@@ -0,0 +1,96 @@
+//! Checks the control surface bindings drive the chip and a player through their traits.
+
+mod common;
+
+use ym2149::*;
+
+#[test]
+fn volume_turns_the_master_attenuation() {
+    let mut chip = common::chip();
+    let mut volume = Volume;
+
+    volume.turn(&mut chip, -3);
+    assert_eq!(chip.master_attenuation(), 3);
+    volume.turn(&mut chip, -100);
+    assert_eq!(chip.master_attenuation(), 15);
+    volume.turn(&mut chip, 20);
+    assert_eq!(chip.master_attenuation(), 0);
+}
+
+#[test]
+fn mute_silences_a_channel_on_its_way_to_the_chip() {
+    let (mut chip, writes) = common::recording_chip();
+    chip.volume(AudioChannel::B, Level::fixed(12));
+    let mut mute = Mute(AudioChannel::B);
+
+    writes.take();
+    mute.press(&mut chip);
+    assert!(chip.is_muted(AudioChannel::B));
+    assert_eq!(writes.take(), [(9, 0)]);
+    assert_eq!(chip.register(Register::BLevel), 12);
+
+    // Envelope-driven channels go silent too
+    chip.volume(AudioChannel::B, Level::envelope());
+    assert_eq!(writes.take(), [(9, 0)]);
+
+    mute.turn(&mut chip, 1);
+    assert!(!chip.is_muted(AudioChannel::B));
+    assert_eq!(writes.take(), [(9, 0x10)]);
+    mute.turn(&mut chip, -1);
+    assert!(chip.is_muted(AudioChannel::B));
+    assert!(!chip.is_muted(AudioChannel::A));
+}
+
+#[test]
+fn transpose_moves_every_channel_within_its_range() {
+    let mut chip = common::chip();
+    chip.play_note(AudioChannel::A, "A4".parse().unwrap());
+    let period = |chip: &common::Chip| {
+        chip.register(Register::AFreq8bitFinetone) as i32
+            | (chip.register(Register::AFreq4bitRoughtone) as i32) << 8
+    };
+    let a4 = period(&chip);
+    let mut transpose = Transpose::new(12);
+
+    transpose.turn(&mut chip, 20);
+    assert_eq!(transpose.semitones(), 12);
+    // An octave up halves the period
+    assert!((period(&chip) - a4 / 2).abs() <= 1);
+
+    transpose.press(&mut chip);
+    assert_eq!(transpose.semitones(), 0);
+    assert_eq!(period(&chip), a4);
+}
+
+#[derive(Default)]
+struct Player {
+    playing: bool,
+    song: i32,
+    tempo: i32,
+}
+
+impl TransportControl for Player {
+    fn toggle_play(&mut self) {
+        self.playing = !self.playing;
+    }
+
+    fn select_song(&mut self, offset: i8) {
+        self.song += offset as i32;
+    }
+
+    fn nudge_tempo(&mut self, steps: i8) {
+        self.tempo += steps as i32;
+    }
+}
+
+#[test]
+fn transport_bindings_drive_the_player() {
+    let mut player = Player::default();
+    let controls: [&mut dyn ParamControl<Player>; 2] = [&mut SongSelect, &mut Tempo];
+
+    controls[0].turn(&mut player, 2);
+    controls[1].turn(&mut player, -5);
+    controls[1].press(&mut player);
+    assert!(player.playing);
+    assert_eq!((player.song, player.tempo), (2, -5));
+}
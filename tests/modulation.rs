@@ -0,0 +1,123 @@
+//! Checks the modulation matrix: sources, routes, depth control and the LFO shape.
+
+mod common;
+
+use ym2149::Destination::{EnvelopeRate, NoisePeriod, Pitch};
+use ym2149::Source::*;
+use ym2149::*;
+
+#[test]
+fn routes_scale_with_their_source() {
+    const ROUTES: &[Route] = &[
+        Route::new(Velocity, Destination::Level, 6),
+        Route::new(Aftertouch, NoisePeriod, -8),
+    ];
+    let mut modulator = Modulator::new(ModMatrix::new(ROUTES));
+    assert_eq!(modulator.tick(), Modulation::default());
+
+    modulator.note_on(127);
+    modulator.set_aftertouch(200);
+    assert_eq!(modulator.source(Aftertouch), 127);
+    let modulation = modulator.tick();
+    assert_eq!((modulation.level, modulation.noise_period), (6, -8));
+
+    modulator.note_on(64);
+    modulator.set_aftertouch(0);
+    let modulation = modulator.tick();
+    assert_eq!((modulation.level, modulation.noise_period), (3, 0));
+}
+
+#[test]
+fn lfos_swing_both_ways() {
+    const VIBRATO: &[Route] = &[Route::new(Lfo1, Pitch, 30)];
+    // A quarter of a cycle per tick
+    let mut modulator = Modulator::new(ModMatrix::new(VIBRATO).with_lfo(0, 64));
+    let pitches: Vec<_> = (0..5).map(|_| modulator.tick().pitch).collect();
+    assert_eq!(pitches, [-30, 0, 30, 0, -30]);
+
+    // LFO 2 isn't running
+    assert_eq!(modulator.source(Lfo2), -127);
+}
+
+#[test]
+fn depth_control_brings_a_source_in() {
+    const VIBRATO: &[Route] = &[
+        Route::new(Lfo1, Pitch, 30),
+        Route::new(Velocity, Destination::Level, 4),
+    ];
+    let matrix = ModMatrix::new(VIBRATO).with_depth_control(Lfo1, ModWheel);
+    let mut modulator = Modulator::new(matrix);
+    modulator.note_on(127);
+
+    // Only the LFO's routes follow the mod wheel
+    let modulation = modulator.tick();
+    assert_eq!((modulation.pitch, modulation.level), (0, 4));
+    modulator.set_mod_wheel(127);
+    assert_eq!(modulator.tick().pitch, -30);
+}
+
+#[test]
+fn the_envelope_restarts_on_every_note() {
+    const ROUTES: &[Route] = &[Route::new(Envelope, NoisePeriod, 10)];
+    let envelope = RawEnvelope::from_slice(&[15, 6, 0]).with_loop(2);
+    let mut modulator = Modulator::new(ModMatrix::new(ROUTES).with_envelope(envelope));
+
+    modulator.note_on(100);
+    let periods: Vec<_> = (0..4).map(|_| modulator.tick().noise_period).collect();
+    assert_eq!(periods, [10, 3, 0, 0]);
+    modulator.note_on(100);
+    assert_eq!(modulator.tick().noise_period, 10);
+}
+
+#[test]
+fn routes_add_up_and_saturate() {
+    const ROUTES: &[Route] = &[
+        Route::new(Velocity, Pitch, 30_000),
+        Route::new(ModWheel, Pitch, 30_000),
+        Route::new(Velocity, EnvelopeRate, 256),
+    ];
+    let mut modulator = Modulator::new(ModMatrix::new(ROUTES));
+    modulator.note_on(127);
+    modulator.set_mod_wheel(127);
+    let modulation = modulator.tick();
+    assert_eq!(modulation.pitch, i16::MAX);
+    // Twice as fast
+    assert_eq!(modulation.envelope_period(100), 50);
+
+    let modulation = Modulation {
+        level: 10,
+        noise_period: -40,
+        envelope_rate: -300,
+        ..Modulation::default()
+    };
+    assert_eq!(modulation.level(9), Level::MAX);
+    assert_eq!(modulation.noise_period(12), 0);
+    assert_eq!(modulation.envelope_period(100), 25_600);
+}
+
+#[test]
+fn envelope_rate_routes_reach_the_hardware_envelope() {
+    const ROUTES: &[Route] = &[Route::new(ModWheel, EnvelopeRate, 256)];
+    let instrument = Instrument {
+        modulation: ModMatrix::new(ROUTES),
+        ..Instrument::BASS
+    };
+    let mut chip = common::chip();
+    let mut synth = ParaSynth::new(0, instrument)
+        .with_hardware_envelope(EnvelopeFrequency::Period(1000), EnvelopeShape::SawDown);
+    let period = |chip: &common::Chip| {
+        u16::from_le_bytes([
+            chip.register(Register::EFreq8bitFineAdj),
+            chip.register(Register::EFreq8bitRoughAdj),
+        ])
+    };
+
+    synth.midi(&mut chip, &[0x90, 60, 100]);
+    synth.tick(&mut chip);
+    assert_eq!(period(&chip), 1000);
+
+    // Mod wheel all the way up: twice as fast
+    synth.midi(&mut chip, &[0xB0, 1, 127]);
+    synth.tick(&mut chip);
+    assert_eq!(period(&chip), 500);
+}
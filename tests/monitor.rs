@@ -0,0 +1,21 @@
+//! Checks a monitor hands out what the chip last published.
+
+mod common;
+
+use ym2149::*;
+
+#[test]
+fn reads_give_the_last_published_state() {
+    let monitor = ChipMonitor::new();
+    let mut chip = common::chip();
+    assert_eq!(monitor.registers(), Some([0; 16]));
+
+    chip.volume(AudioChannel::B, Level::fixed(12));
+    monitor.publish(&chip);
+    let registers = monitor.registers().unwrap();
+    assert_eq!(registers[Register::BLevel as usize], 12);
+    assert_eq!(
+        monitor.stats().unwrap().register_writes,
+        chip.stats().register_writes
+    );
+}
@@ -0,0 +1,101 @@
+//! Checks the tuning math: microtonal tunings, tone periods and period tables.
+
+mod common;
+
+use ym2149::note::{build_period_table, period_table_error_ppm, PERIOD_TABLE_NOTES};
+use ym2149::*;
+
+const ST_PERIODS: [u16; PERIOD_TABLE_NOTES] = build_period_table(2_000_000);
+
+fn a4() -> Note {
+    Note::new(NoteName::A, Accidental::Natural, 4)
+}
+
+#[test]
+fn equal_tunings_round_to_the_nearest_cent() {
+    assert_eq!(Tuning::Equal(12).cents(7), 700);
+    assert_eq!(Tuning::Equal(19).cents(1), 63);
+    assert_eq!(Tuning::Equal(19).cents(19), 1200);
+    assert_eq!(Tuning::Equal(19).cents(-19), -1200);
+    assert_eq!(Tuning::Equal(24).cents(-1), -50);
+    assert_eq!(Tuning::Equal(0).cents(5), 0);
+}
+
+#[test]
+fn tables_repeat_every_period() {
+    const JUST: [u16; 7] = [204, 386, 498, 702, 884, 1088, 1200];
+    let tuning = Tuning::Table(&JUST);
+
+    assert_eq!(tuning.cents(0), 0);
+    assert_eq!(tuning.cents(2), 386);
+    assert_eq!(tuning.cents(7), 1200);
+    assert_eq!(tuning.cents(9), 1200 + 386);
+    assert_eq!(tuning.cents(-1), 1088 - 1200);
+    assert_eq!(Tuning::Table(&[]).cents(3), 0);
+}
+
+#[test]
+fn tuned_notes_keep_their_name() {
+    let c4 = Note::new(NoteName::C, Accidental::Sharp, 4).detune(-100);
+    let note = Tuning::Equal(19).note(c4, 5);
+    assert_eq!(
+        (note.name, note.accidental, note.octave),
+        (NoteName::C, Accidental::Sharp, 4)
+    );
+    assert_eq!(note.cents, -100 + 316);
+    assert_eq!(note.cents_from_a4(), c4.cents_from_a4() + 316);
+}
+
+#[test]
+fn periods_follow_the_pitch() {
+    // fMaster / (16 * 440 Hz) = 284.09
+    assert_eq!(a4().period(2_000_000), 284);
+    assert_eq!(a4().detune(1200).period(2_000_000), 142);
+    assert_eq!(a4().detune(-1200).period(2_000_000), 568);
+    // A#4 at 466.16 Hz, reached with cents rather than an accidental
+    assert_eq!(a4().detune(100).period(2_000_000), 268);
+    // 50 cents up, 452.9 Hz
+    assert_eq!(a4().detune(50).period(2_000_000), 276);
+
+    // Clamped to the 12 bits of the tone registers
+    assert_eq!(Note::from_midi(0).period(2_000_000), 0x0FFF);
+    assert_eq!(Note::from_midi(127).detune(i16::MAX).period(2_000_000), 1);
+}
+
+#[test]
+fn period_tables_match_the_computed_periods() {
+    // C1 is MIDI note 24
+    for (key, &period) in (24..).zip(&ST_PERIODS) {
+        assert_eq!(
+            period,
+            Note::from_midi(key).period(2_000_000),
+            "MIDI note {key}"
+        );
+    }
+    assert_eq!(ST_PERIODS[45], 284);
+
+    // The chip plays the same with and without the table
+    let mut plain = common::chip();
+    let mut tabled = common::chip().with_period_table(&ST_PERIODS);
+    for key in 0..128 {
+        plain.play_note(AudioChannel::A, Note::from_midi(key));
+        tabled.play_note(AudioChannel::A, Note::from_midi(key));
+        assert_eq!(plain.frame(), tabled.frame(), "MIDI note {key}");
+    }
+}
+
+#[test]
+fn period_table_errors_are_under_half_a_period() {
+    let errors = period_table_error_ppm(2_000_000, &ST_PERIODS);
+    for (&error, &period) in errors.iter().zip(&ST_PERIODS) {
+        assert!(
+            error.abs() <= 500_000 / period as i32 + 1,
+            "{error} ppm at {period}"
+        );
+    }
+    // 284.09 played as 284, a little sharp
+    assert!((300..340).contains(&errors[45]), "{}", errors[45]);
+    // The top octave is the furthest off
+    let worst = errors.iter().map(|error| error.abs()).max().unwrap();
+    assert!(errors[84..].iter().any(|error| error.abs() == worst));
+}
@@ -0,0 +1,59 @@
+//! Checks events come out of the scheduler when due, in order, across the tick wrap.
+
+use ym2149::*;
+
+fn drain<const N: usize>(scheduler: &mut Scheduler<char, N>) -> String {
+    core::iter::from_fn(|| scheduler.pop_due()).collect()
+}
+
+#[test]
+fn due_events_come_out_earliest_first() {
+    let mut scheduler: Scheduler<char, 4> = Scheduler::new(50);
+    scheduler.at(3, 'a').unwrap();
+    scheduler.at(1, 'b').unwrap();
+    scheduler.at(3, 'c').unwrap();
+    scheduler.at(2, 'd').unwrap();
+    assert_eq!(scheduler.at(4, 'e'), Err('e'));
+    assert_eq!(scheduler.len(), 4);
+
+    assert_eq!(drain(&mut scheduler), "");
+    scheduler.tick();
+    assert_eq!(drain(&mut scheduler), "b");
+    scheduler.tick();
+    scheduler.tick();
+    // Late ones first, then in scheduling order
+    assert_eq!(drain(&mut scheduler), "dac");
+    assert!(scheduler.is_empty());
+}
+
+#[test]
+fn delays_round_to_the_nearest_tick() {
+    let mut scheduler: Scheduler<char, 4> = Scheduler::new(50);
+    scheduler.after(500, 'a').unwrap();
+    scheduler.after(29, 'b').unwrap();
+    scheduler.after(9, 'c').unwrap();
+
+    assert_eq!(drain(&mut scheduler), "c");
+    scheduler.tick();
+    assert_eq!(drain(&mut scheduler), "b");
+    for _ in 1..25 {
+        scheduler.tick();
+    }
+    assert_eq!(scheduler.now(), 25);
+    assert_eq!(drain(&mut scheduler), "a");
+}
+
+#[test]
+fn ticks_are_compared_across_the_wrap() {
+    let mut scheduler: Scheduler<char, 4> = Scheduler::new(50);
+    // Just before 0, so in the past
+    scheduler.at(u32::MAX - 1, 'a').unwrap();
+    scheduler.at(0, 'b').unwrap();
+    // 2^31 ahead is in the future
+    scheduler.at(1 << 31, 'c').unwrap();
+    assert_eq!(drain(&mut scheduler), "ab");
+    assert_eq!(scheduler.len(), 1);
+
+    scheduler.clear();
+    assert!(scheduler.is_empty());
+}
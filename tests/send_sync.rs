@@ -0,0 +1,27 @@
+//! Checks the types can move between tasks and cores, see "Concurrency" in the crate docs.
+
+use embedded_hal_mock::eh1::digital::Mock as PinMock;
+use embedded_hal_mock::eh1::i2c::Mock as I2cMock;
+use embedded_hal_mock::eh1::spi::Mock as SpiMock;
+use ym2149::*;
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn chips_and_buses_are_send() {
+    assert_send::<ShiftRegisterBus<SpiMock<u8>>>();
+    assert_send::<I2cExpanderBus<I2cMock>>();
+    assert_send::<TransceiverBus<ShiftRegisterBus<SpiMock<u8>>, PinMock>>();
+    assert_send::<YM2149<ShiftRegisterBus<SpiMock<u8>>, PinMock, PinMock>>();
+    assert_send::<YM2149<I2cExpanderBus<I2cMock>, PinMock, PinMock>>();
+    assert_send::<SfxManager>();
+    assert_send::<Echo>();
+}
+
+#[test]
+fn shared_state_is_sync() {
+    assert_sync::<SharedChip<ShiftRegisterBus<SpiMock<u8>>, PinMock, PinMock>>();
+    assert_sync::<ChipMonitor>();
+    assert_sync::<AudioFailsafe>();
+}
@@ -0,0 +1,144 @@
+//! Checks settings survive a trip through a [FlashStore], whatever the flash's write size.
+
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+use ym2149::*;
+
+const SECTOR: usize = 4096;
+
+/// Two sectors of NOR flash in RAM, checking alignment and that bits only go from 1 to 0
+/// between erases.
+struct RamFlash<const WRITE: usize> {
+    bytes: Vec<u8>,
+    writes: usize,
+}
+
+impl<const WRITE: usize> RamFlash<WRITE> {
+    fn new() -> Self {
+        Self {
+            bytes: vec![0; 2 * SECTOR],
+            writes: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FlashError(NorFlashErrorKind);
+
+impl NorFlashError for FlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        self.0
+    }
+}
+
+impl<const WRITE: usize> ErrorType for RamFlash<WRITE> {
+    type Error = FlashError;
+}
+
+impl<const WRITE: usize> ReadNorFlash for RamFlash<WRITE> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), FlashError> {
+        let offset = offset as usize;
+        let source = self
+            .bytes
+            .get(offset..offset + bytes.len())
+            .ok_or(FlashError(NorFlashErrorKind::OutOfBounds))?;
+        bytes.copy_from_slice(source);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl<const WRITE: usize> NorFlash for RamFlash<WRITE> {
+    const WRITE_SIZE: usize = WRITE;
+    const ERASE_SIZE: usize = SECTOR;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), FlashError> {
+        let (from, to) = (from as usize, to as usize);
+        if !from.is_multiple_of(SECTOR) || !to.is_multiple_of(SECTOR) {
+            return Err(FlashError(NorFlashErrorKind::NotAligned));
+        }
+        self.bytes[from..to].fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), FlashError> {
+        let offset = offset as usize;
+        if !offset.is_multiple_of(WRITE) || !bytes.len().is_multiple_of(WRITE) {
+            return Err(FlashError(NorFlashErrorKind::NotAligned));
+        }
+        for (cell, &byte) in self.bytes[offset..offset + bytes.len()]
+            .iter_mut()
+            .zip(bytes)
+        {
+            assert_eq!(*cell & byte, byte, "writing over bytes that weren't erased");
+            *cell = byte;
+        }
+        self.writes += 1;
+        Ok(())
+    }
+}
+
+fn round_trip<const WRITE: usize>(offset: u32) {
+    let settings = Settings {
+        volume: 9,
+        pitch_shift: -35,
+        tempo: 140,
+        song: 7,
+    };
+    let mut store = FlashStore::new(RamFlash::<WRITE>::new(), offset);
+    assert_eq!(Settings::load(&mut store).unwrap(), None);
+
+    settings.save(&mut store).unwrap();
+    assert_eq!(Settings::load(&mut store).unwrap(), Some(settings));
+
+    // Saving again erases first
+    let settings = Settings {
+        song: 8,
+        ..settings
+    };
+    settings.save(&mut store).unwrap();
+    assert_eq!(Settings::load(&mut store).unwrap(), Some(settings));
+}
+
+#[test]
+fn settings_round_trip() {
+    round_trip::<1>(SECTOR as u32);
+    round_trip::<4>(SECTOR as u32);
+    round_trip::<256>(SECTOR as u32);
+}
+
+#[test]
+fn settings_round_trip_with_odd_write_size() {
+    // 3 doesn't divide the blob or a page
+    round_trip::<3>(0);
+}
+
+#[test]
+fn blobs_are_written_whole() {
+    let mut store = FlashStore::new(RamFlash::<256>::new(), 0);
+    let blob: Vec<u8> = (0..600).map(|i| i as u8).collect();
+    store.save(&blob).unwrap();
+
+    let mut loaded = vec![0; blob.len()];
+    store.load(&mut loaded).unwrap();
+    assert_eq!(loaded, blob);
+    assert_eq!(store.release().writes, 2);
+}
+
+#[test]
+fn bad_offsets_and_sizes_are_errors() {
+    let mut store = FlashStore::new(RamFlash::<4>::new(), 0x100);
+    assert_eq!(store.save(&[0; 4]), Err(FlashStoreError::Misaligned));
+
+    let mut store = FlashStore::new(RamFlash::<4>::new(), 0);
+    assert_eq!(store.save(&[0; SECTOR + 1]), Err(FlashStoreError::TooLarge));
+
+    let mut store = FlashStore::new(RamFlash::<512>::new(), 0);
+    assert_eq!(store.save(&[0; 4]), Err(FlashStoreError::Unsupported));
+}
@@ -0,0 +1,102 @@
+//! Checks sound effects and the music's note effects stay out of each other's way.
+
+mod common;
+
+use ym2149::*;
+
+/// Tone period (R0, R1) and level (R8) of channel A.
+fn channel_a(chip: &common::Chip) -> [u8; 3] {
+    [chip.register(0), chip.register(1), chip.register(8)]
+}
+
+#[test]
+fn tick_leaves_an_effect_alone() {
+    let mut chip = common::chip();
+    chip.play_note(AudioChannel::A, "A4".parse().unwrap());
+    chip.set_vibrato(AudioChannel::A, 100, 40);
+    chip.set_software_envelope(
+        AudioChannel::A,
+        Some(RawEnvelope::from_slice(&[15, 11, 7, 3])),
+    );
+    chip.tick();
+
+    let mut sfx = SfxManager::new();
+    assert!(sfx.play(&mut chip, AudioChannel::A, Sfx::laser(8)));
+    for _ in 0..4 {
+        let effect = channel_a(&chip);
+        chip.tick();
+        assert_eq!(channel_a(&chip), effect);
+        sfx.tick(&mut chip);
+    }
+}
+
+#[test]
+fn note_effects_resume_when_the_effect_ends() {
+    let mut chip = common::chip();
+    chip.play_note(AudioChannel::A, "A4".parse().unwrap());
+    chip.set_software_envelope(
+        AudioChannel::A,
+        Some(RawEnvelope::from_slice(&[15, 11, 7, 3]).with_loop(3)),
+    );
+    chip.tick();
+    let before = channel_a(&chip);
+
+    let mut sfx = SfxManager::new();
+    sfx.play(&mut chip, AudioChannel::A, Sfx::laser(8));
+    chip.tick();
+    sfx.stop(&mut chip, AudioChannel::A);
+    assert!(!sfx.is_playing(AudioChannel::A));
+    assert_eq!(channel_a(&chip), before);
+
+    // The envelope was on hold, it carries on from the level it had reached
+    chip.tick();
+    assert_eq!(chip.register(8), 7);
+}
+
+#[test]
+fn effects_play_on_the_chip_of_their_voice() {
+    let mut chips = [common::chip(), common::chip()];
+    let mut sfx = SfxManager::<6>::with_voices();
+
+    assert!(sfx.play(&mut chips, VoiceId(4), Sfx::laser(2)));
+    assert!(sfx.is_playing(VoiceId(4)));
+    assert!(!sfx.is_playing(AudioChannel::B));
+    assert_eq!(chips[0].register(Register::BLevel), 0);
+    assert_eq!(chips[1].register(Register::BLevel), 15);
+    // Past the last voice
+    assert!(!sfx.play(&mut chips, VoiceId(6), Sfx::laser(2)));
+
+    sfx.tick(&mut chips);
+    sfx.tick(&mut chips);
+    assert!(!sfx.is_playing(VoiceId(4)));
+    assert_eq!(chips[1].register(Register::BLevel), 0);
+}
+
+#[test]
+fn frames_leave_an_effect_alone() {
+    let mut chip = common::chip();
+    let mut frame = Frame::default();
+    frame.registers[0] = 0x40;
+    frame.registers[Register::ALevel as usize] = 9;
+    frame.registers[Register::BLevel as usize] = 9;
+    frame.registers[Register::IoPortMixerSettings as usize] = 0x3F;
+
+    let mut sfx = SfxManager::new();
+    sfx.play(&mut chip, AudioChannel::A, Sfx::laser(2));
+    let effect = channel_a(&chip);
+    let mixer = chip.register(Register::IoPortMixerSettings);
+    chip.apply_frame(&frame);
+    assert_eq!(channel_a(&chip), effect);
+    assert_eq!(
+        chip.register(Register::IoPortMixerSettings) & 0x09,
+        mixer & 0x09
+    );
+    // The other channels follow the frame
+    assert_eq!(chip.register(Register::BLevel), 9);
+
+    // Once the effect is over, the next frame brings the channel back to the music
+    sfx.tick(&mut chip);
+    sfx.tick(&mut chip);
+    chip.apply_frame(&frame);
+    assert_eq!(channel_a(&chip), [0x40, 0, 9]);
+}
@@ -0,0 +1,377 @@
+//! Checks the ready-made synths: voices, legato and chord memory.
+
+mod common;
+
+use ym2149::*;
+
+fn note_on(key: u8) -> [u8; 3] {
+    [0x90, key, 100]
+}
+
+fn note_off(key: u8) -> [u8; 3] {
+    [0x80, key, 0]
+}
+
+fn period(chip: &common::Chip, channel: AudioChannel) -> u16 {
+    let fine = chip.register(Register::AFreq8bitFinetone as u8 + 2 * channel as u8);
+    let rough = chip.register(Register::AFreq4bitRoughtone as u8 + 2 * channel as u8);
+    fine as u16 | (rough as u16) << 8
+}
+
+fn period_of(key: u8) -> u16 {
+    Note::from_midi(key).period(2_000_000)
+}
+
+#[test]
+fn mono_synth_plays_the_last_key_held() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::A, 0, Instrument::BASS);
+
+    synth.midi(&mut chip, &note_on(60));
+    synth.midi(&mut chip, &note_on(64));
+    assert_eq!(synth.key(), Some(64));
+    assert_eq!(period(&chip, AudioChannel::A), period_of(64));
+
+    // Releasing a key that isn't playing changes nothing
+    synth.midi(&mut chip, &note_on(67));
+    synth.midi(&mut chip, &note_off(64));
+    assert_eq!(period(&chip, AudioChannel::A), period_of(67));
+
+    synth.midi(&mut chip, &note_off(67));
+    assert_eq!(synth.key(), Some(60));
+    assert_eq!(period(&chip, AudioChannel::A), period_of(60));
+
+    synth.midi(&mut chip, &note_off(60));
+    assert_eq!(synth.key(), None);
+    assert_eq!(chip.register(Register::ALevel), 0);
+}
+
+#[test]
+fn mono_synth_ignores_other_midi_channels() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::B, 3, Instrument::BASS);
+
+    synth.midi(&mut chip, &note_on(60));
+    assert_eq!(synth.key(), None);
+    synth.midi(&mut chip, &[0x93, 60, 100]);
+    assert_eq!(synth.key(), Some(60));
+    assert_eq!(period(&chip, AudioChannel::B), period_of(60));
+}
+
+#[test]
+fn mono_synth_doesnt_retrigger_the_envelope_on_legato_notes() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::A, 0, Instrument::PLUCK);
+
+    synth.note_on(&mut chip, 60, 127);
+    for _ in 0..4 {
+        synth.tick(&mut chip);
+    }
+    let level = chip.register(Register::ALevel);
+    assert!(level < 15);
+
+    synth.note_on(&mut chip, 64, 127);
+    assert_eq!(synth.key(), Some(64));
+    assert!(chip.register(Register::ALevel) <= level);
+
+    // Back to the first key, still without retriggering
+    synth.note_off(&mut chip, 64);
+    assert_eq!(synth.key(), Some(60));
+    assert!(chip.register(Register::ALevel) <= level);
+}
+
+#[test]
+fn mono_synth_glides_between_overlapping_notes() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::A, 0, Instrument::LEAD);
+
+    synth.note_on(&mut chip, 60, 127);
+    synth.note_on(&mut chip, 64, 127);
+    // 400 cents at 15 cents per tick
+    assert_eq!(period(&chip, AudioChannel::A), period_of(60));
+    synth.tick(&mut chip);
+    let gliding = period(&chip, AudioChannel::A);
+    assert!(gliding < period_of(60) && gliding > period_of(64));
+
+    for _ in 0..30 {
+        synth.tick(&mut chip);
+    }
+    assert_eq!(period(&chip, AudioChannel::A), period_of(64));
+
+    // A new note after a release jumps
+    synth.note_off(&mut chip, 64);
+    synth.note_off(&mut chip, 60);
+    synth.note_on(&mut chip, 67, 127);
+    assert_eq!(period(&chip, AudioChannel::A), period_of(67));
+}
+
+#[test]
+fn mono_synth_bends_over_the_instrument_range() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::A, 0, Instrument::LEAD);
+
+    synth.note_on(&mut chip, 60, 127);
+    // Full bend up, 2 semitones for LEAD
+    synth.midi(&mut chip, &[0xE0, 0x7F, 0x7F]);
+    let bent = period(&chip, AudioChannel::A) as i32;
+    assert!((bent - Note::from_midi(60).detune(199).period(2_000_000) as i32).abs() <= 1);
+
+    synth.midi(&mut chip, &[0xE0, 0x00, 0x40]);
+    assert_eq!(period(&chip, AudioChannel::A), period_of(60));
+}
+
+#[test]
+fn pluck_plays_in_tune_until_the_mod_wheel_comes_up() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::A, 0, Instrument::PLUCK);
+
+    synth.note_on(&mut chip, 60, 127);
+    assert_eq!(period(&chip, AudioChannel::A), period_of(60));
+    for _ in 0..10 {
+        synth.tick(&mut chip);
+        assert_eq!(period(&chip, AudioChannel::A), period_of(60));
+    }
+
+    synth.midi(&mut chip, &[0xB0, 1, 127]);
+    let periods: Vec<_> = (0..30)
+        .map(|_| {
+            synth.tick(&mut chip);
+            period(&chip, AudioChannel::A)
+        })
+        .collect();
+    assert!(periods.iter().any(|&p| p > period_of(60)));
+    assert!(periods.iter().any(|&p| p < period_of(60)));
+}
+
+#[test]
+fn mono_synth_ticks_only_write_what_changed() {
+    let (mut chip, writes) = common::recording_chip();
+    let instrument = Instrument {
+        noise: Some(5),
+        ..Instrument::LEAD
+    };
+    let mut synth = MonoSynth::new(AudioChannel::A, 0, instrument);
+
+    synth.note_on(&mut chip, 60, 127);
+    synth.tick(&mut chip);
+    writes.take();
+    for _ in 0..10 {
+        synth.tick(&mut chip);
+    }
+    assert_eq!(writes.take(), []);
+}
+
+#[test]
+fn mono_synth_plays_unison_on_every_channel() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::B, 0, Instrument::SUPERSAW);
+
+    synth.note_on(&mut chip, 57, 127);
+    let periods = AudioChannel::ALL.map(|channel| period(&chip, channel));
+    // The outer channels are detuned either way
+    assert!(periods.contains(&period_of(57)));
+    assert!(periods.iter().any(|&p| p > period_of(57)));
+    assert!(periods.iter().any(|&p| p < period_of(57)));
+
+    synth.note_off(&mut chip, 57);
+    for channel in AudioChannel::ALL {
+        assert_eq!(chip.register(Register::ALevel as u8 + channel as u8), 0);
+    }
+}
+
+#[test]
+fn para_synth_steals_the_oldest_note() {
+    let mut chip = common::chip();
+    let mut synth = ParaSynth::new(0, Instrument::BASS);
+
+    for key in [60, 64, 67] {
+        synth.midi(&mut chip, &note_on(key));
+    }
+    for (channel, key) in AudioChannel::ALL.into_iter().zip([60, 64, 67]) {
+        assert_eq!(synth.key(channel), Some(key));
+        assert_eq!(period(&chip, channel), period_of(key));
+    }
+
+    synth.midi(&mut chip, &note_on(71));
+    assert_eq!(synth.key(AudioChannel::A), Some(71));
+    assert_eq!(period(&chip, AudioChannel::A), period_of(71));
+
+    // The stolen key's note-off doesn't cut the new note
+    synth.midi(&mut chip, &note_off(60));
+    assert_ne!(chip.register(Register::ALevel), 0);
+    synth.midi(&mut chip, &note_off(64));
+    assert_eq!(synth.key(AudioChannel::B), None);
+    assert_eq!(chip.register(Register::BLevel), 0);
+}
+
+#[test]
+fn para_synth_plays_the_learned_chord_from_each_key() {
+    let mut chip = common::chip();
+    let mut synth = ParaSynth::new(0, Instrument::BASS);
+
+    for key in [67, 60, 64] {
+        synth.midi(&mut chip, &note_on(key));
+    }
+    synth.learn_chord();
+    assert_eq!(synth.chord(), [0, 4, 7]);
+    for key in [60, 64, 67] {
+        synth.midi(&mut chip, &note_off(key));
+    }
+
+    synth.midi(&mut chip, &note_on(62));
+    let mut periods = AudioChannel::ALL.map(|channel| period(&chip, channel));
+    periods.sort_unstable();
+    assert_eq!(periods, [69, 66, 62].map(period_of));
+
+    synth.midi(&mut chip, &note_off(62));
+    for channel in AudioChannel::ALL {
+        assert_eq!(synth.key(channel), None);
+        assert_eq!(chip.register(Register::ALevel as u8 + channel as u8), 0);
+    }
+
+    // Learning without a chord held forgets it
+    synth.learn_chord();
+    assert!(synth.chord().is_empty());
+    synth.midi(&mut chip, &note_on(62));
+    assert_eq!(synth.key(AudioChannel::A), Some(62));
+    assert_eq!(synth.key(AudioChannel::B), None);
+}
+
+#[test]
+fn para_synth_releases_the_notes_a_key_started_when_the_chord_changes() {
+    let mut chip = common::chip();
+    let mut synth = ParaSynth::new(0, Instrument::BASS);
+    let playing = |synth: &ParaSynth| AudioChannel::ALL.map(|channel| synth.key(channel));
+
+    // Learned while its keys are held, releasing one only stops its own note
+    for key in [60, 64, 67] {
+        synth.midi(&mut chip, &note_on(key));
+    }
+    synth.learn_chord();
+    synth.midi(&mut chip, &note_off(60));
+    assert_eq!(playing(&synth), [None, Some(64), Some(67)]);
+    synth.midi(&mut chip, &note_off(64));
+    synth.midi(&mut chip, &note_off(67));
+    assert_eq!(playing(&synth), [None; 3]);
+
+    // Cleared while a chord is held, its whole chord stops
+    synth.midi(&mut chip, &note_on(62));
+    assert_eq!(playing(&synth), [Some(62), Some(66), Some(69)]);
+    synth.clear_chord();
+    synth.midi(&mut chip, &note_off(62));
+    assert_eq!(playing(&synth), [None; 3]);
+    for channel in AudioChannel::ALL {
+        assert_eq!(chip.register(Register::ALevel as u8 + channel as u8), 0);
+    }
+}
+
+#[test]
+fn para_synth_ticks_only_write_what_changed() {
+    let (mut chip, writes) = common::recording_chip();
+    let instrument = Instrument {
+        noise: Some(5),
+        ..Instrument::LEAD
+    };
+    let mut synth = ParaSynth::new(0, instrument);
+
+    for key in [60, 64, 67] {
+        synth.midi(&mut chip, &note_on(key));
+    }
+    synth.tick(&mut chip);
+    writes.take();
+    for _ in 0..10 {
+        synth.tick(&mut chip);
+    }
+    assert_eq!(writes.take(), []);
+}
+
+#[test]
+fn para_synth_restarts_the_hardware_envelope_from_silence_only() {
+    let (mut chip, writes) = common::recording_chip();
+    let mut synth = ParaSynth::new(0, Instrument::BASS)
+        .with_hardware_envelope(EnvelopeFrequency::Hz(2), EnvelopeShape::SawDown);
+    let shape_writes = |writes: &common::RecordingBus| {
+        writes
+            .take()
+            .iter()
+            .filter(|&&(register, _)| register == Register::EShape as u8)
+            .count()
+    };
+
+    synth.midi(&mut chip, &note_on(60));
+    assert_eq!(shape_writes(&writes), 1);
+    assert_eq!(chip.register(Register::ALevel), 0x10);
+
+    // Overlapping notes share the running envelope
+    synth.midi(&mut chip, &note_on(64));
+    assert_eq!(shape_writes(&writes), 0);
+    assert_eq!(chip.register(Register::BLevel), 0x10);
+
+    synth.midi(&mut chip, &note_off(60));
+    synth.midi(&mut chip, &note_off(64));
+    assert_eq!(chip.register(Register::ALevel), 0);
+    assert_eq!(chip.register(Register::BLevel), 0);
+    writes.take();
+
+    synth.midi(&mut chip, &note_on(67));
+    assert_eq!(shape_writes(&writes), 1);
+}
+
+#[test]
+fn para_synth_spreads_notes_over_several_chips() {
+    let mut chips = [common::chip(), common::chip()];
+    let mut synth = ParaSynth::<6>::with_voices(0, Instrument::LEAD);
+
+    for key in 60..66 {
+        synth.midi(&mut chips, &note_on(key));
+    }
+    let keys: Vec<_> = (0..6).map(|voice| synth.key(VoiceId(voice))).collect();
+    assert!(keys.iter().all(Option::is_some));
+    for chip in &chips {
+        for channel in AudioChannel::ALL {
+            assert_ne!(chip.register(Register::ALevel as u8 + channel as u8), 0);
+        }
+    }
+
+    synth.midi(&mut chips, &note_off(64));
+    assert!((0..6).all(|voice| synth.key(VoiceId(voice)) != Some(64)));
+}
+
+#[test]
+fn mono_synth_retriggers_a_key_pressed_again() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::A, 0, Instrument::PLUCK);
+
+    synth.note_on(&mut chip, 60, 127);
+    assert_eq!(chip.register(Register::ALevel), 15);
+    for _ in 0..3 {
+        synth.tick(&mut chip);
+    }
+    assert!(chip.register(Register::ALevel) < 15);
+
+    // The note-off got lost, the same key again isn't legato
+    synth.note_on(&mut chip, 60, 127);
+    assert_eq!(chip.register(Register::ALevel), 15);
+}
+
+#[test]
+fn mono_synth_releases_the_unison_channels_when_leaving_unison() {
+    let mut chip = common::chip();
+    let mut synth = MonoSynth::new(AudioChannel::A, 0, Instrument::SUPERSAW);
+
+    synth.note_on(&mut chip, 60, 127);
+    for channel in AudioChannel::ALL {
+        assert_ne!(chip.register(Register::ALevel as u8 + channel as u8), 0);
+    }
+
+    synth.set_instrument(&mut chip, Instrument::LEAD);
+    assert_eq!(synth.key(), None);
+    for channel in AudioChannel::ALL {
+        assert_eq!(chip.register(Register::ALevel as u8 + channel as u8), 0);
+    }
+
+    synth.note_on(&mut chip, 62, 127);
+    assert_ne!(chip.register(Register::ALevel), 0);
+    assert_eq!(chip.register(Register::BLevel), 0);
+    assert_eq!(chip.register(Register::CLevel), 0);
+}
@@ -0,0 +1,59 @@
+//! Checks trigger pulses are timed in milliseconds and reach their output.
+
+mod common;
+
+use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction};
+use ym2149::*;
+
+#[test]
+fn pulse_length_is_rounded_to_ticks() {
+    // 50 Hz: 60 ms is 3 ticks
+    let mut pin = PinMock::new(&[
+        Transaction::set(State::Low),
+        Transaction::set(State::High),
+        Transaction::set(State::Low),
+    ]);
+    let mut trigger = TriggerOut::new(pin.clone(), 50, 60).unwrap();
+
+    trigger.fire().unwrap();
+    for _ in 0..3 {
+        trigger.tick().unwrap();
+    }
+    trigger.tick().unwrap();
+
+    pin.done();
+}
+
+#[test]
+fn short_pulses_last_a_tick() {
+    let mut pin = PinMock::new(&[
+        Transaction::set(State::Low),
+        Transaction::set(State::High),
+        Transaction::set(State::Low),
+    ]);
+    let mut trigger = TriggerOut::new(pin.clone(), 50, 1).unwrap();
+
+    trigger.fire().unwrap();
+    trigger.tick().unwrap();
+
+    pin.done();
+}
+
+#[test]
+fn port_trigger_only_touches_its_bit() {
+    let mut chip = common::chip();
+    chip.write_io_port(IoPort::B, 0b1000_0001).unwrap();
+    let mut trigger = PortTrigger::new(&mut chip, IoPort::B, 4, 50, 40)
+        .unwrap()
+        .with_period(5);
+    assert_eq!(chip.register(Register::IoPortMixerSettings) & 0x80, 0x80);
+    assert_eq!(chip.register(Register::DataIoB), 0b1000_0001);
+
+    let mut levels = Vec::new();
+    for _ in 0..7 {
+        trigger.tick(&mut chip).unwrap();
+        levels.push(chip.register(Register::DataIoB));
+    }
+    let (high, low) = (0b1001_0001, 0b1000_0001);
+    assert_eq!(levels, [high, high, low, low, low, high, high]);
+}
@@ -0,0 +1,123 @@
+//! Checks voice allocation: steal policies, reservations and several chips.
+
+use ym2149::*;
+
+/// An allocator with keys 60, 61 and 62 started in that order, on A, B and C.
+fn full(policy: StealPolicy) -> VoiceAllocator {
+    let mut voices = VoiceAllocator::new(policy);
+    for key in 60..63 {
+        voices.note_on(key);
+    }
+    voices
+}
+
+fn allocation(voice: usize, stolen: Option<u8>) -> Option<Allocation> {
+    Some(Allocation {
+        voice: VoiceId(voice),
+        stolen,
+    })
+}
+
+#[test]
+fn free_voices_are_used_first() {
+    let mut voices = VoiceAllocator::new(StealPolicy::Last);
+    assert_eq!(voices.note_on(60), allocation(0, None));
+    assert_eq!(voices.note_on(64), allocation(1, None));
+    // A key already playing keeps its voice
+    assert_eq!(voices.note_on(60), allocation(0, None));
+
+    assert_eq!(voices.note_off(60), Some(VoiceId(0)));
+    assert_eq!(voices.note_off(60), None);
+    assert_eq!(voices.key(AudioChannel::A), None);
+    assert_eq!(voices.note_on(67), allocation(0, None));
+    assert_eq!(voices.key(AudioChannel::A), Some(67));
+}
+
+#[test]
+fn last_steals_the_newest_note() {
+    let mut voices = full(StealPolicy::Last);
+    assert_eq!(voices.note_on(70), allocation(2, Some(62)));
+    assert_eq!(voices.note_on(71), allocation(2, Some(70)));
+}
+
+#[test]
+fn oldest_steals_the_longest_held_note() {
+    let mut voices = full(StealPolicy::Oldest);
+    assert_eq!(voices.note_on(70), allocation(0, Some(60)));
+    assert_eq!(voices.note_on(71), allocation(1, Some(61)));
+    // Retriggering a key makes it the newest
+    voices.note_on(62);
+    assert_eq!(voices.note_on(72), allocation(0, Some(70)));
+}
+
+#[test]
+fn lowest_keeps_the_low_notes() {
+    let mut voices = full(StealPolicy::Lowest);
+    assert_eq!(voices.note_on(70), None);
+    assert_eq!(voices.note_on(50), allocation(2, Some(62)));
+    assert_eq!(voices.note_on(55), allocation(1, Some(61)));
+}
+
+#[test]
+fn highest_keeps_the_high_notes() {
+    let mut voices = full(StealPolicy::Highest);
+    assert_eq!(voices.note_on(50), None);
+    assert_eq!(voices.note_on(70), allocation(0, Some(60)));
+    assert_eq!(voices.note_on(65), allocation(1, Some(61)));
+}
+
+#[test]
+fn drums_never_steal_the_bass() {
+    let mut voices = VoiceAllocator::new(StealPolicy::Oldest);
+    voices.reserve(AudioChannel::A, Some(Role::Bass));
+    voices.reserve(AudioChannel::C, Some(Role::Drums));
+    assert_eq!(voices.reservation(AudioChannel::C), Some(Role::Drums));
+    assert_eq!(voices.reservation(AudioChannel::B), None);
+
+    assert_eq!(voices.note_on_as(40, Role::Bass), allocation(0, None));
+    assert_eq!(voices.note_on(60), allocation(1, None));
+    assert_eq!(voices.note_on_as(36, Role::Drums), allocation(2, None));
+
+    // Melodic notes only get B, drums B and C, however old the bass note is
+    assert_eq!(voices.note_on(64), allocation(1, Some(60)));
+    assert_eq!(voices.note_on_as(38, Role::Drums), allocation(2, Some(36)));
+    assert_eq!(voices.key(AudioChannel::A), Some(40));
+
+    // The same key in another role is another note
+    assert_eq!(voices.note_off_as(38, Role::Melodic), None);
+    assert_eq!(voices.note_off_as(38, Role::Drums), Some(VoiceId(2)));
+}
+
+#[test]
+fn reservations_change_at_runtime() {
+    let mut voices = VoiceAllocator::new(StealPolicy::Oldest);
+    voices.reserve(AudioChannel::A, Some(Role::Bass));
+    voices.reserve(AudioChannel::B, Some(Role::Bass));
+    voices.reserve(AudioChannel::C, Some(Role::Bass));
+    assert_eq!(voices.note_on(60), None);
+
+    voices.reserve(AudioChannel::B, None);
+    assert_eq!(voices.note_on(60), allocation(1, None));
+
+    // The note keeps playing on a voice reserved under it
+    voices.reserve(AudioChannel::B, Some(Role::Drums));
+    assert_eq!(voices.key(AudioChannel::B), Some(60));
+    assert_eq!(voices.note_on_as(36, Role::Drums), allocation(1, Some(60)));
+
+    // Past the last voice
+    voices.reserve(VoiceId(3), Some(Role::Drums));
+    assert_eq!(voices.reservation(VoiceId(3)), None);
+}
+
+#[test]
+fn voices_spread_over_several_chips() {
+    let mut voices = VoiceAllocator::<6>::with_voices(StealPolicy::Oldest);
+    let allocated: Vec<_> = (60..66)
+        .map(|key| voices.note_on(key).unwrap().voice)
+        .collect();
+    assert_eq!(allocated, (0..6).map(VoiceId).collect::<Vec<_>>());
+
+    assert_eq!(VoiceId(4).chip(), 1);
+    assert_eq!(VoiceId(4).channel(), AudioChannel::B);
+    assert_eq!(voices.note_on(70), allocation(0, Some(60)));
+}